@@ -0,0 +1,665 @@
+use serde::Serialize;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+use crate::game::{ self, ai, analysis, book, engine, ggf, net, rules, server, turn, wthor };
+use crate::game::board::cell::Color;
+
+pub const USAGE: &str = "usage: othello [command] [options]
+
+commands:
+  play                  open the game window (default)
+  tui                   play in the terminal
+  selfplay              let the AI play against itself
+  analyze <transcript>  compare each move with the AI's choice (e.g. f5d6c3d3c4)
+  solve <position>      exact endgame result (64 cells of X/O/- from a1 to h8, then X/O to move)
+  serve                 run a game server with a lobby, ratings and clocks
+  nboard                talk the NBoard protocol on stdin/stdout (for external GUIs)
+  view <file>           replay a saved GGF game in the window (same as play --record <file>)
+  wthor <dir>           search the WTHOR database in dir (WTHOR.JOU, WTHOR.TRN and .wtb files)
+
+options:
+  --variant <standard|cross>  starting position (default: standard)
+  --level <1-10>              AI strength (default: 4)
+  --threads <n>               threads the AI searches with (default: number of CPU cores)
+  --format <text|json>        output of selfplay, analyze, solve and wthor (default: text)
+  --black <human|ai|engine>   who plays black in play/tui (default: human)
+  --white <human|ai|engine>   who plays white in play/tui (default: human)
+  --engine <command>          external engine for the engine player (e.g. \"edax -nboard\")
+  --engine-protocol <nboard|text>  how to talk to the engine (default: nboard)
+  --record <file>             replay a saved GGF game instead of playing (play/tui)
+  --game <n>                  with --record, which game in the file (default: 1)
+                              with wthor, replay the n-th game found in the window
+  --games <n>                 number of selfplay games (default: 1)
+  --learn                     with selfplay, add the games to the learned opening book (learned_book.txt)
+  --ascii                     ASCII-only board in the terminal
+  --host <port>               wait for an opponent over the network (play/tui, you play black)
+  --connect <address>         join a game or server at address[:port] (play/tui)
+  --name <name>               your name shown to the opponent
+  --opponent <name>           on a server, play this player instead of the closest rating
+  --watch <address>           watch a game on a server or a broadcast (play/tui)
+  --player <name>             with --watch, the game this player is in (default: the latest game)
+                              with wthor, games of players whose name contains this
+  --year <year>               with wthor, games played in this year
+  --opening <transcript>      with wthor, games starting with these moves (e.g. f5d6c3)
+  --broadcast <port>          let others watch this game (play/tui)
+  --port <port>               port of the server (serve, default: 7650)
+  --time <seconds>            time per player on the server (serve, default: 600)
+  --data <dir>                where the server keeps ratings and games (serve, default: ./server)
+  -h, --help                  show this help";
+
+const RANDOM_OPENING_MOVES: usize = 4;  //  自己対局で序盤にランダムに打つ手数 ( 毎回同じ棋譜にならないように )
+
+//  サブコマンド
+#[derive(Copy, Clone, PartialEq)]
+pub enum Command {
+    PLAY,
+    TUI,
+    SELFPLAY,
+    ANALYZE,
+    SOLVE,
+    SERVE,
+    NBOARD,
+    WTHOR,
+    HELP,
+}
+
+//  出力形式
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+    TEXT,
+    JSON,
+}
+
+pub struct Options {
+    pub command: Command,
+    pub argument: Option<String>,   //  analyze の棋譜 / solve の局面
+    pub config: game::Config,
+    pub format: Format,
+    pub games: u32,
+    pub learn: bool,                //  自己対局を定石に覚える
+    pub ascii: bool,
+    pub host: Option<u16>,          //  通信対戦で待ち受けるポート
+    pub connect: Option<String>,    //  通信対戦の接続先
+    pub name: String,               //  通信対戦での名前
+    pub opponent: Option<String>,   //  サーバーで対局したい相手
+    pub watch: Option<String>,      //  観戦先
+    pub player: Option<String>,     //  観戦したいプレイヤー
+    pub broadcast: Option<u16>,     //  観戦者を待ち受けるポート
+    pub port: u16,                  //  サーバーのポート
+    pub time: u64,                  //  サーバーの持ち時間 ( 秒 )
+    pub data: PathBuf,              //  サーバーの保存先
+    pub engine: Option<String>,     //  外部のエンジンのコマンド
+    pub engine_protocol: engine::Protocol,
+    pub record: Option<PathBuf>,    //  再生する棋譜 ( GGF )
+    pub game: Option<usize>,        //  棋譜のファイル・WTHOR の検索結果の何局目か ( 1から )
+    pub year: Option<u16>,          //  WTHOR の検索条件
+    pub opening: Option<String>,    //  WTHOR の検索条件 ( 棋譜 )
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            command: Command::PLAY,
+            argument: None,
+            config: game::Config::default(),
+            format: Format::TEXT,
+            games: 1,
+            learn: false,
+            ascii: false,
+            host: None,
+            connect: None,
+            name: std::env::var( "USER" ).or_else( | _ | std::env::var( "USERNAME" ) ).unwrap_or( "player".to_string() ),
+            opponent: None,
+            watch: None,
+            player: None,
+            broadcast: None,
+            port: net::DEFAULT_PORT,
+            time: server::DEFAULT_TIME,
+            data: PathBuf::from( server::DEFAULT_DIRECTORY ),
+            engine: None,
+            engine_protocol: engine::Protocol::NBOARD,
+            record: None,
+            game: None,
+            year: None,
+            opening: None,
+        }
+    }
+}
+
+//  コマンドライン引数の解析 ( プログラム名は除いて渡す )
+pub fn parse<I: Iterator<Item = String>>( args: I ) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut command: Option<Command> = None;
+    let mut args = args;
+    let mut view = false;
+
+    while let Some( arg ) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => command = Some( Command::HELP ),
+            "--tui" => command = Some( Command::TUI ),     //  以前のオプション
+            "--ascii" => options.ascii = true,
+            "--learn" => options.learn = true,
+            "--variant" => {
+                let value = value( &mut args, &arg )?;
+                options.config.variant = rules::Variant::parse( &value ).ok_or( format!( "unknown variant: {}", value ) )?;
+            },
+            "--level" => {
+                let value = value( &mut args, &arg )?;
+                let level: u32 = value.parse().map_err( | _ | format!( "invalid level: {}", value ) )?;
+                if level < 1 || level > ai::MAX_LEVEL {
+                    return Err( format!( "level must be 1-{}: {}", ai::MAX_LEVEL, level ) );
+                }
+                options.config.level = level;
+            },
+            "--threads" => {
+                let value = value( &mut args, &arg )?;
+                let threads: usize = value.parse().map_err( | _ | format!( "invalid threads: {}", value ) )?;
+                if threads < 1 {
+                    return Err( format!( "threads must be at least 1: {}", threads ) );
+                }
+                options.config.threads = threads;
+            },
+            "--format" => {
+                let value = value( &mut args, &arg )?;
+                options.format = match value.as_str() {
+                    "text" => Format::TEXT,
+                    "json" => Format::JSON,
+                    _ => return Err( format!( "unknown format: {}", value ) ),
+                };
+            },
+            "--black" | "--white" => {
+                let value = value( &mut args, &arg )?;
+                let kind = turn::Kind::parse( &value ).ok_or( format!( "unknown player: {}", value ) )?;
+                let player_num = if arg == "--black" { turn::PLAYER::ONE } else { turn::PLAYER::TWO };
+                options.config.players[player_num as usize] = kind;
+            },
+            "--games" => {
+                let value = value( &mut args, &arg )?;
+                options.games = value.parse().map_err( | _ | format!( "invalid number of games: {}", value ) )?;
+            },
+            "--host" => {
+                let value = value( &mut args, &arg )?;
+                options.host = Some( value.parse().map_err( | _ | format!( "invalid port: {}", value ) )? );
+            },
+            "--connect" => options.connect = Some( value( &mut args, &arg )? ),
+            "--name" => options.name = value( &mut args, &arg )?,
+            "--opponent" => options.opponent = Some( value( &mut args, &arg )? ),
+            "--watch" => options.watch = Some( value( &mut args, &arg )? ),
+            "--player" => options.player = Some( value( &mut args, &arg )? ),
+            "--broadcast" => {
+                let value = value( &mut args, &arg )?;
+                options.broadcast = Some( value.parse().map_err( | _ | format!( "invalid port: {}", value ) )? );
+            },
+            "--port" => {
+                let value = value( &mut args, &arg )?;
+                options.port = value.parse().map_err( | _ | format!( "invalid port: {}", value ) )?;
+            },
+            "--time" => {
+                let value = value( &mut args, &arg )?;
+                options.time = match value.parse() {
+                    Ok( time ) if time > 0 => time,
+                    _ => return Err( format!( "invalid time: {}", value ) ),
+                };
+            },
+            "--data" => options.data = PathBuf::from( value( &mut args, &arg )? ),
+            "--record" => options.record = Some( PathBuf::from( value( &mut args, &arg )? ) ),
+            "--game" => {
+                let value = value( &mut args, &arg )?;
+                options.game = match value.parse() {
+                    Ok( game ) if game > 0 => Some( game ),
+                    _ => return Err( format!( "invalid game number: {}", value ) ),
+                };
+            },
+            "--year" => {
+                let value = value( &mut args, &arg )?;
+                options.year = Some( value.parse().map_err( | _ | format!( "invalid year: {}", value ) )? );
+            },
+            "--opening" => {
+                let value = value( &mut args, &arg )?;
+                rules::parse_transcript( &value ).ok_or( format!( "invalid transcript: {}", value ) )?;
+                options.opening = Some( value );
+            },
+            "--engine" => options.engine = Some( value( &mut args, &arg )? ),
+            "--engine-protocol" => {
+                let value = value( &mut args, &arg )?;
+                options.engine_protocol = engine::Protocol::parse( &value ).ok_or( format!( "unknown engine protocol: {}", value ) )?;
+            },
+            //  局面文字列は "-" で始まることがあるので除く
+            _ if arg.starts_with( "--" ) && rules::Rules::parse_position( &arg ).is_none() => return Err( format!( "unknown option: {}", arg ) ),
+            _ if command.is_none() => {
+                command = Some( match arg.as_str() {
+                    "play" => Command::PLAY,
+                    "tui" => Command::TUI,
+                    "selfplay" => Command::SELFPLAY,
+                    "analyze" => Command::ANALYZE,
+                    "solve" => Command::SOLVE,
+                    "serve" => Command::SERVE,
+                    "nboard" => Command::NBOARD,
+                    "wthor" => Command::WTHOR,
+                    "view" => {
+                        view = true;
+                        Command::PLAY
+                    },
+                    "help" => Command::HELP,
+                    _ => return Err( format!( "unknown command: {}", arg ) ),
+                } );
+            },
+            _ if options.argument.is_none() => options.argument = Some( arg ),
+            _ => return Err( format!( "unexpected argument: {}", arg ) ),
+        }
+    }
+
+    options.command = command.unwrap_or( Command::PLAY );
+
+    //  view の引数は棋譜のファイル
+    if view {
+        let path = options.argument.take().ok_or( "missing argument".to_string() )?;
+        options.record = Some( PathBuf::from( path ) );
+    }
+
+    if options.host.is_some() || options.connect.is_some() {
+        if options.host.is_some() && options.connect.is_some() {
+            return Err( "--host and --connect cannot be used together".to_string() );
+        }
+        if options.command != Command::PLAY && options.command != Command::TUI {
+            return Err( "--host and --connect are only for play and tui".to_string() );
+        }
+    }
+
+    if options.opponent.is_some() && options.connect.is_none() {
+        return Err( "--opponent needs --connect".to_string() );
+    }
+
+    if options.watch.is_some() || options.broadcast.is_some() {
+        if options.command != Command::PLAY && options.command != Command::TUI {
+            return Err( "--watch and --broadcast are only for play and tui".to_string() );
+        }
+        if options.watch.is_some() && ( options.host.is_some() || options.connect.is_some() || options.broadcast.is_some() ) {
+            return Err( "--watch cannot be used with --host, --connect or --broadcast".to_string() );
+        }
+    }
+
+    if options.player.is_some() && options.watch.is_none() && options.command != Command::WTHOR {
+        return Err( "--player needs --watch or wthor".to_string() );
+    }
+
+    if options.learn && options.command != Command::SELFPLAY {
+        return Err( "--learn is only for selfplay".to_string() );
+    }
+
+    if ( options.year.is_some() || options.opening.is_some() ) && options.command != Command::WTHOR {
+        return Err( "--year and --opening are only for wthor".to_string() );
+    }
+
+    //  エンジンのプレイヤーにはコマンドが必要
+    match &options.engine {
+        Some( command ) => {
+            options.config.engine = Some( engine::EngineConfig { command: command.clone(), protocol: options.engine_protocol } );
+        },
+        None => {
+            if options.config.players.contains( &turn::Kind::ENGINE ) {
+                return Err( "engine player needs --engine".to_string() );
+            }
+        },
+    }
+
+    //  棋譜の再生は両方の手番を REPLAY にする
+    if let Some( path ) = &options.record {
+        if options.command != Command::PLAY && options.command != Command::TUI {
+            return Err( "--record is only for play and tui".to_string() );
+        }
+        if options.host.is_some() || options.connect.is_some() || options.watch.is_some() || options.broadcast.is_some() {
+            return Err( "--record cannot be used with --host, --connect, --watch or --broadcast".to_string() );
+        }
+        let record = load_record( path, options.game.unwrap_or( 1 ) )?;
+        options.config.variant = record.variant().ok_or( format!( "{}: unsupported starting position", path.display() ) )?;
+        options.config.players = [ turn::Kind::REPLAY, turn::Kind::REPLAY ];
+        options.config.record = Some( record );
+    } else if options.game.is_some() && options.command != Command::WTHOR {
+        return Err( "--game needs --record or wthor".to_string() );
+    }
+
+    match options.command {
+        Command::ANALYZE | Command::SOLVE | Command::WTHOR => {
+            if options.argument.is_none() {
+                return Err( "missing argument".to_string() );
+            }
+        },
+        _ => {
+            if let Some( argument ) = &options.argument {
+                return Err( format!( "unexpected argument: {}", argument ) );
+            }
+        },
+    }
+
+    //  WTHOR の対局を選んだらウィンドウで再生する
+    if let ( Command::WTHOR, Some( game ) ) = ( options.command, options.game ) {
+        let database = load_wthor( &options )?;
+        let games = database.search( &wthor_filter( &options ) );
+        let found = games.get( game - 1 ).ok_or( format!( "only {} games found", games.len() ) )?;
+        options.config.record = Some( found.to_record() );
+        options.config.variant = rules::Variant::STANDARD;
+        options.config.players = [ turn::Kind::REPLAY, turn::Kind::REPLAY ];
+        options.command = Command::PLAY;
+        options.argument = None;
+    }
+
+    return Ok( options );
+}
+
+//  棋譜のファイルから n 局目 ( 1から ) を取り出す
+fn load_record( path: &Path, game: usize ) -> Result<ggf::Record, String> {
+    let mut records = ggf::Record::load( path ).map_err( | e | format!( "{}: {}", path.display(), e ) )?;
+    if game > records.len() {
+        return Err( format!( "{}: only {} games", path.display(), records.len() ) );
+    }
+    return Ok( records.swap_remove( game - 1 ) );
+}
+
+//  オプションの値
+fn value<I: Iterator<Item = String>>( args: &mut I, name: &str ) -> Result<String, String> {
+    return args.next().ok_or( format!( "{} needs a value", name ) );
+}
+
+//  通信の接続 ( 通信対戦は相手の色の手番を、観戦は両方の手番を REMOTE にする )
+pub fn open_network( options: &mut Options ) -> Result<net::Network, String> {
+    let mut network = net::Network::default();
+
+    let session = if let Some( port ) = options.host {
+        Some( net::host( port, &options.name ) )
+    } else if let Some( address ) = &options.connect {
+        Some( net::connect( address, &options.name, options.opponent.as_deref() ) )
+    } else {
+        None
+    };
+
+    if let Some( session ) = session {
+        let session = session.map_err( | e | format!( "network: {}", e ) )?;
+        let remote_player = if session.color == Color::BLACK { turn::PLAYER::TWO } else { turn::PLAYER::ONE };
+        options.config.players[remote_player as usize] = turn::Kind::REMOTE;
+        println!( "playing {} against {}", analysis::color_name( session.color ), session.opponent );
+        network.session = Some( session );
+    }
+
+    if let Some( address ) = &options.watch {
+        let watch = net::watch( address, &options.name, options.player.as_deref() ).map_err( | e | format!( "network: {}", e ) )?;
+        options.config.variant = watch.variant;
+        options.config.players = [ turn::Kind::REMOTE, turn::Kind::REMOTE ];
+        println!( "watching {} vs {}", watch.black, watch.white );
+        network.watch = Some( watch );
+    }
+
+    if let Some( port ) = options.broadcast {
+        let opponent = network.session.as_ref().map( | s | s.opponent.clone() ).unwrap_or_default();
+        let player_name = | kind: turn::Kind | match kind {
+            turn::Kind::HUMAN => options.name.clone(),
+            turn::Kind::AI => "ai".to_string(),
+            turn::Kind::ENGINE => "engine".to_string(),
+            turn::Kind::REMOTE => opponent.clone(),
+            turn::Kind::REPLAY => "replay".to_string(),
+        };
+        let black = player_name( options.config.players[turn::PLAYER::ONE as usize] );
+        let white = player_name( options.config.players[turn::PLAYER::TWO as usize] );
+        let spectators = net::Spectators::listen( port, &options.name, options.config.variant, &black, &white );
+        network.spectators = Some( spectators.map_err( | e | format!( "network: {}", e ) )? );
+    }
+
+    return Ok( network );
+}
+
+//  ウィンドウを使わないサブコマンドの実行 ( 戻り値は終了コード )
+pub fn run( options: &Options, network: net::Network ) -> i32 {
+    let result = match options.command {
+        Command::PLAY => Ok(()),
+        Command::HELP => {
+            println!( "{}", USAGE );
+            Ok(())
+        },
+        Command::TUI => game::tui::run( !options.ascii, &options.config, network ).map_err( | e | e.to_string() ),
+        Command::SELFPLAY => selfplay( options ),
+        Command::ANALYZE => analyze( options ),
+        Command::SOLVE => solve( options ),
+        Command::SERVE => serve( options ),
+        Command::NBOARD => game::nboard::run( options.config.level ).map_err( | e | e.to_string() ),
+        Command::WTHOR => search_wthor( options ),
+    };
+
+    if let Err( e ) = result {
+        eprintln!( "{}", e );
+        return 1;
+    }
+
+    return 0;
+}
+
+fn serve( options: &Options ) -> Result<(), String> {
+    let config = server::ServerConfig {
+        port: options.port,
+        variant: options.config.variant,
+        time: Duration::from_secs( options.time ),
+        directory: options.data.clone(),
+    };
+    return server::serve( &config ).map_err( | e | format!( "serve: {}", e ) );
+}
+
+fn print_json<T: Serialize>( value: &T ) -> Result<(), String> {
+    let text = serde_json::to_string_pretty( value ).map_err( | e | e.to_string() )?;
+    println!( "{}", text );
+    return Ok(());
+}
+
+//  符号付きの評価値
+fn signed( score: i32 ) -> String {
+    return format!( "{:+}", score );
+}
+
+//  自己対局の結果
+#[derive(Serialize)]
+struct SelfplayGame {
+    transcript: String,
+    black: i32,
+    white: i32,
+    winner: &'static str,
+}
+
+#[derive(Serialize)]
+struct SelfplaySummary {
+    games: Vec<SelfplayGame>,
+    black_wins: usize,
+    white_wins: usize,
+    draws: usize,
+}
+
+fn selfplay( options: &Options ) -> Result<(), String> {
+    let mut seed = SystemTime::now().duration_since( UNIX_EPOCH ).map( | d | d.as_nanos() as u64 ).unwrap_or( 0 ) | 1;
+    let mut games = Vec::with_capacity( options.games as usize );
+
+    let mut book = if options.learn { Some( book::Book::load() ) } else { None };
+
+    for i in 0 .. options.games {
+        let game = selfplay_game( &options.config, &mut seed );
+        if options.format == Format::TEXT {
+            println!( "{}: {} {}-{} {}", i + 1, game.transcript, game.black, game.white, game.winner );
+        }
+        if let Some( book ) = book.as_mut() {
+            let moves = rules::parse_transcript( &game.transcript ).unwrap_or_default();
+            if let Err( e ) = book.learn( options.config.variant, &moves ) {
+                eprintln!( "learn game {}: {}", i + 1, e );
+            }
+        }
+        games.push( game );
+    }
+
+    if let Some( book ) = book.as_ref() {
+        book.save().map_err( | e | format!( "{}: {}", book::LEARNED_PATH, e ) )?;
+    }
+
+    let count = | winner: &str | games.iter().filter( | g | g.winner == winner ).count();
+    let summary = SelfplaySummary {
+        black_wins: count( turn::Outcome::BLACK_WIN.name() ),
+        white_wins: count( turn::Outcome::WHITE_WIN.name() ),
+        draws: count( turn::Outcome::DRAW.name() ),
+        games,
+    };
+
+    match options.format {
+        Format::TEXT => {
+            println!( "black {}  white {}  draw {}", summary.black_wins, summary.white_wins, summary.draws );
+            return Ok(());
+        },
+        Format::JSON => return print_json( &summary ),
+    }
+}
+
+fn selfplay_game( config: &game::Config, seed: &mut u64 ) -> SelfplayGame {
+    let mut rules = rules::Rules::new( config.variant );
+    let mut turn = turn::Turn::new();
+    let mut moves = Vec::with_capacity( 60 );
+
+    loop {
+        match turn.start( &rules ) {
+            turn::Phase::PLAY => {
+                let color = turn.get_color();
+                let position = if moves.len() < RANDOM_OPENING_MOVES {
+                    let positions = rules.get_enable_positions( color );
+                    Some( positions[next_random( seed ) as usize % positions.len()] )
+                } else {
+                    ai::search( &rules, color, config.level ).position
+                };
+
+                if let Some( position ) = position {
+                    rules.set_piece( position.x, position.y, color );
+                    moves.push( position );
+                }
+            },
+            turn::Phase::PASS => {},
+            turn::Phase::END => break,
+        }
+
+        if turn.finish( &rules ) == turn::Phase::END {
+            break;
+        }
+    }
+
+    return SelfplayGame {
+        transcript: rules::to_transcript( &moves ),
+        black: rules.get_piece_count( Color::BLACK ),
+        white: rules.get_piece_count( Color::WHITE ),
+        winner: turn::get_outcome( &rules ).name(),
+    };
+}
+
+//  xorshift ( 序盤のランダムな手に使うだけなので簡易なもの )
+fn next_random( seed: &mut u64 ) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    return *seed;
+}
+
+fn analyze( options: &Options ) -> Result<(), String> {
+    let text = options.argument.as_deref().unwrap_or( "" );
+    let moves = rules::parse_transcript( text ).ok_or( format!( "invalid transcript: {}", text ) )?;
+    let result = analysis::analyze( &moves, options.config.variant, options.config.level, None )?;
+
+    match options.format {
+        Format::TEXT => {
+            for iter in result.iter() {
+                println!( "{:>2} {:<5} {:<4} best {}  {} / {}{}",
+                    iter.number, iter.color, format!( "{}{}", iter.played, iter.mark.map_or( "", | m | m.symbol() ) ), iter.best,
+                    signed( iter.played_score ), signed( iter.best_score ),
+                    if iter.exact { " (exact)" } else { "" } );
+            }
+            println!( "{}", analysis::annotate( &result ) );
+            return Ok(());
+        },
+        Format::JSON => return print_json( &result ),
+    }
+}
+
+fn load_wthor( options: &Options ) -> Result<wthor::Database, String> {
+    let directory = PathBuf::from( options.argument.as_deref().unwrap_or( "" ) );
+    let database = wthor::Database::load( &directory ).map_err( | e | format!( "wthor: {}", e ) )?;
+    if database.skipped > 0 {
+        eprintln!( "wthor: skipped {} games with illegal moves", database.skipped );
+    }
+    return Ok( database );
+}
+
+fn wthor_filter( options: &Options ) -> wthor::Filter {
+    return wthor::Filter {
+        player: options.player.clone(),
+        year: options.year,
+        opening: options.opening.as_deref().and_then( rules::parse_transcript ).unwrap_or_default(),
+    };
+}
+
+//  WTHOR の対局
+#[derive(Serialize)]
+struct WthorGame {
+    number: usize,      //  --game で指定する番号
+    year: u16,
+    tournament: String,
+    black: String,
+    white: String,
+    black_score: u8,
+    white_score: u8,
+    theoretical_score: u8,  //  完全読みでの黒の石数
+    transcript: String,
+}
+
+fn search_wthor( options: &Options ) -> Result<(), String> {
+    let database = load_wthor( options )?;
+    let games: Vec<WthorGame> = database.search( &wthor_filter( options ) ).iter().enumerate().map( | ( i, game ) | WthorGame {
+        number: i + 1,
+        year: game.year,
+        tournament: game.tournament.clone(),
+        black: game.black.clone(),
+        white: game.white.clone(),
+        black_score: game.black_score,
+        white_score: game.white_score(),
+        theoretical_score: game.theoretical_score,
+        transcript: rules::to_transcript( &game.moves ),
+    } ).collect();
+
+    match options.format {
+        Format::TEXT => {
+            for iter in games.iter() {
+                println!( "{:>4}: {} {} {}-{} {}  ({})  {}", iter.number, iter.year,
+                    iter.black, iter.black_score, iter.white_score, iter.white, iter.tournament, iter.transcript );
+            }
+            println!( "{} of {} games", games.len(), database.games.len() );
+            return Ok(());
+        },
+        Format::JSON => return print_json( &games ),
+    }
+}
+
+//  完全読みの結果
+#[derive(Serialize)]
+struct SolveResult {
+    position: String,
+    color: &'static str,
+    best: Option<String>,   //  パスなら None
+    score: i32,             //  手番側から見た最終石差
+}
+
+fn solve( options: &Options ) -> Result<(), String> {
+    let text = options.argument.as_deref().unwrap_or( "" );
+    let ( rules, color ) = rules::Rules::parse_position( text ).ok_or( format!( "invalid position: {}", text ) )?;
+    let search = ai::solve( &rules, color );
+    let result = SolveResult {
+        position: rules.to_position_string( color ),
+        color: analysis::color_name( color ),
+        best: search.position.map( rules::to_notation ),
+        score: search.score,
+    };
+
+    match options.format {
+        Format::TEXT => {
+            println!( "{} to move: {} {}", result.color, result.best.as_deref().unwrap_or( "pass" ), signed( result.score ) );
+            return Ok(());
+        },
+        Format::JSON => return print_json( &result ),
+    }
+}
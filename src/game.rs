@@ -1,18 +1,67 @@
-use tetra::{ Context };
-use tetra::graphics::{ DrawParams };
+use tetra::{ Context, window };
+use tetra::graphics::{ Color, DrawParams };
 use tetra::math::Vec2;
-use tetra::input::{ self, MouseButton };
+use tetra::input::{ self, Key };
+use std::collections::VecDeque;
+use std::time::{ Duration, Instant };
 
-mod board;
+pub mod ai;
+pub mod analysis;
+mod assets;
+mod audio;
+pub mod board;
+pub mod book;
+pub mod engine;
+pub mod ggf;
+mod graph;
+mod layout;
+mod locale;
+mod menu;
+pub mod nboard;
+pub mod net;
 mod player;
+mod review;
+pub mod rules;
+pub mod server;
+mod settings;
+mod theme;
+pub mod tui;
+pub mod turn;
 mod ui;
+pub mod wthor;
 
-const PLAYER_NUM: usize = 2;
+use turn::{ PLAYER, PLAYER_NUM };
 
-#[derive(Copy, Clone)]
-enum PLAYER {
-    ONE = 0,
-    TWO = 1,
+const REPLAY_INTERVAL: Duration = Duration::from_millis( 800 );    //  棋譜の再生で 1手ごとに待つ時間
+const MOVE_LIST_NUM: usize = 6;     //  振り返りの手順一覧に並べる数
+const MOVE_LIST_Y: f32 = 240.0;     //  手順一覧の上端 ( 基準座標 )
+const MOVE_LIST_HEIGHT: f32 = 22.0; //  手順一覧の行の間隔
+const GRAPH_Y: f32 = 380.0;         //  評価値のグラフの上端 ( 基準座標 )
+const GRAPH_WIDTH: f32 = 200.0;     //  評価値のグラフの大きさ ( 基準 )
+const GRAPH_HEIGHT: f32 = 80.0;
+
+//  対局設定 ( コマンドラインで指定 )
+#[derive(Clone)]
+pub struct Config {
+    pub variant: rules::Variant,            //  初期配置
+    pub players: [turn::Kind; PLAYER_NUM],  //  黒・白それぞれ人間か AI か
+    pub level: u32,                         //  AI の強さ
+    pub threads: usize,                     //  AI の探索に使うスレッド数
+    pub engine: Option<engine::EngineConfig>,   //  ENGINE のプレイヤーが使う外部のエンジン
+    pub record: Option<ggf::Record>,            //  REPLAY のプレイヤーが並べる棋譜
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            variant: rules::Variant::STANDARD,
+            players: [ turn::Kind::HUMAN, turn::Kind::HUMAN ],
+            level: ai::DEFAULT_LEVEL,
+            threads: ai::default_threads(),
+            engine: None,
+            record: None,
+        }
+    }
 }
 
 enum State {
@@ -22,47 +71,126 @@ enum State {
     TURN_FINISH,
     RESULT,
     RESULT_WAIT,
+    REVIEW,
     RESET,
 }
 
 pub struct Game {
     state: State,
     players: [player::Player; PLAYER_NUM],
-    player_num: PLAYER,
+    turn: turn::Turn,
     board: board::Board,
     ui: ui::UI,
-    skip_count: i32,
+    assets: assets::Assets,
+    settings: settings::Settings,
+    themes: Vec<theme::Theme>,
+    theme_num: usize,
+    menu: menu::Menu,
+    layout: layout::Layout,
+    audio: audio::Audio,
+    level: u32,
+    remote: Option<net::Session>,           //  通信対戦の相手 ( 通信対戦でなければ None )
+    remote_moves: VecDeque<net::Message>,   //  相手から届いた手 ( MOVE / PASS ) 相手の手番で取り出す
+    draw_offered: bool,                     //  相手から引き分けの提案を受けている
+    forced_outcome: Option<turn::Outcome>,  //  投了・合意の引き分けでの勝敗
+    info: Option<locale::Message>,          //  通信対戦のお知らせ
+    chat_input: Option<String>,             //  入力中のチャット
+    moves: Vec<Vec2<i32>>,                  //  この対局の手順 ( 観戦者に送る )
+    watch: Option<net::Watch>,              //  観戦中の対局 ( 観戦でなければ None )
+    spectators: Option<net::Spectators>,    //  この対局を観戦している人 ( 中継しなければ None )
+    record: Option<ggf::Record>,            //  再生中の棋譜 ( 再生でなければ None )
+    replay_time: Instant,                   //  棋譜の最後の手を並べた時刻
+    review: Option<review::Review>,         //  終局後の振り返り ( 振り返り中でなければ None )
+    graph: graph::Graph,                    //  振り返りの評価値のグラフ
+    book: book::Book,                       //  AI が使う定石
+    thinking: Option<ai::Thinking>,         //  別スレッドで読んでいる AI ( 読んでいなければ None )
+    learner: book::Learner,                 //  終わった対局を別スレッドで完全読みして定石に覚える
 }
 
 impl Game {
-    pub fn new( ctx: &mut Context ) -> tetra::Result<Game> {
+    pub fn new( ctx: &mut Context, config: &Config, network: net::Network ) -> tetra::Result<Game> {
         let state = State::TURN_START;
-        let players: [player::Player; PLAYER_NUM] = 
-        [ player::Player::new( player::Color::BLACK, ctx )?, 
-          player::Player::new( player::Color::WHITE, ctx )?
+        let settings = settings::Settings::load();
+        let themes = theme::Theme::load_all();
+        let theme_num = themes.iter().position( | t | t.name == settings.theme ).unwrap_or( 0 );
+        let theme = &themes[theme_num];
+        let mut assets = assets::Assets::new( &theme.font.path, &theme.font.fallback );
+        let mut players: [player::Player; PLAYER_NUM] = 
+        [ player::Player::new( player::Color::BLACK, config.players[PLAYER::ONE as usize], &mut assets, theme, ctx )?, 
+          player::Player::new( player::Color::WHITE, config.players[PLAYER::TWO as usize], &mut assets, theme, ctx )?
         ];
-        let player_num = PLAYER::ONE;
-        let board = board::Board::new( ctx )?;
-        let ui = ui::UI::new( ctx )?;
-        let skip_count = 0;
+        for player in players.iter_mut() {
+            if let ( turn::Kind::ENGINE, Some( engine_config ) ) = ( player.get_kind(), config.engine.as_ref() ) {
+                let engine = engine::Engine::start( engine_config, config.level )
+                    .map_err( | e | tetra::TetraError::PlatformError( format!( "engine: {}", e ) ) )?;
+                player.set_engine( engine );
+            }
+        }
+        let turn = turn::Turn::new();
+        let mut board = board::Board::new( ctx, &mut assets, theme )?;
+        board.set_flipped( settings.flip_board );
+        board.set_variant( config.variant );
+        let mut ui = ui::UI::new( ctx )?;
+        ui.set_color( theme.text_color() );
+        let menu = menu::Menu::new();
+        let layout = layout::Layout::new( crate::WINDOW_WIDTH, crate::WINDOW_HEIGHT );
+
+        if settings.fullscreen {
+            window::set_fullscreen( ctx, true )?;
+        }
+
+        let audio = audio::Audio::new( ctx, settings.volume, settings.mute, settings.music );
 
         Ok( Game {
             state,
             players,
-            player_num,
+            turn,
             board,
             ui,
-            skip_count,
+            assets,
+            settings,
+            themes,
+            theme_num,
+            menu,
+            layout,
+            audio,
+            level: config.level,
+            remote: network.session,
+            remote_moves: VecDeque::new(),
+            draw_offered: false,
+            forced_outcome: None,
+            info: None,
+            chat_input: None,
+            moves: Vec::with_capacity( 60 ),
+            watch: network.watch,
+            spectators: network.spectators,
+            record: config.record.clone(),
+            replay_time: Instant::now(),
+            review: None,
+            graph: graph::Graph::new( Vec2::new( layout::PANEL_X, GRAPH_Y ), Vec2::new( GRAPH_WIDTH, GRAPH_HEIGHT ) ),
+            book: book::Book::load(),
+            thinking: None,
+            learner: book::Learner::start(),
         } )
     }
 
     pub fn init( &mut self, ctx: &mut Context ) {
-        self.player_num = PLAYER::ONE;
-        self.skip_count = 0;
+        self.turn.init();
         self.players[PLAYER::ONE as usize].init( ctx );
         self.players[PLAYER::TWO as usize].init( ctx );
         self.board.init( ctx );
-        self.ui.init( ctx );
+        self.moves.clear();
+        self.replay_watch();
+        self.replay_time = Instant::now();
+        self.review = None;
+        self.graph.clear();
+        self.thinking = None;
+        self.ui.init();
+        self.push_settings_button( ctx );
+        self.push_remote_texts( ctx );
+
+        let ( width, height ) = window::get_size( ctx );
+        self.resize( ctx, width, height );
 
         //let mut test = &self;
         //let mut func: fn( &mut Context ) = | ctx | {
@@ -76,6 +204,41 @@ impl Game {
     }
 
     pub fn update( &mut self, ctx: &mut Context ) {
+        self.poll_learning();
+
+        //  設定メニュー表示中はゲームを止める
+        if self.menu.is_open() {
+            self.update_menu( ctx );
+            return;
+        }
+
+        if input::is_key_pressed( ctx, Key::F1 ) || self.ui.is_clicked( "settings" ) {
+            self.open_menu( ctx );
+            return;
+        }
+
+        self.update_remote( ctx );
+        if let Some( spectators ) = self.spectators.as_mut() {
+            spectators.update( &self.moves, self.board.get_rules(), self.turn.get_color() );
+        }
+
+        //  チャット入力中はキー操作を受け付けない
+        if !self.update_chat( ctx ) {
+            if input::is_key_pressed( ctx, Key::F11 ) {
+                self.toggle_fullscreen( ctx );
+            }
+
+            if input::is_key_pressed( ctx, Key::F ) {
+                self.toggle_flip_board();
+            }
+
+            if input::is_key_pressed( ctx, Key::M ) {
+                self.toggle_mute( ctx );
+            }
+
+            self.update_remote_keys( ctx );
+        }
+
         match self.state {
             State::NONE => {},
             //  ターン開始
@@ -101,6 +264,10 @@ impl Game {
             State::RESULT_WAIT => {
                 self.result_wait( ctx );
             },
+            //  振り返り
+            State::REVIEW => {
+                self.review_update( ctx );
+            },
         }
 
         self.ui.update( ctx );
@@ -108,77 +275,755 @@ impl Game {
 
     pub fn draw( &mut self, ctx: &mut Context ) {
         self.board.draw( ctx );
-        self.players[self.player_num as usize].draw( ctx );
+        //  AI・通信相手の手番と振り返り中はカーソルを出さない
+        let player_num = self.turn.get_player() as usize;
+        if self.players[player_num].get_kind() == turn::Kind::HUMAN && self.review.is_none() {
+            self.players[player_num].draw( ctx );
+        }
         self.ui.draw( ctx );
+        if self.review.is_some() {
+            self.graph.draw( ctx );
+        }
+        self.menu.draw( ctx );
     }
 
-    //  ターン開始
-    fn turn_start( &mut self, ctx: &mut Context ) {
-        self.board.init_turn(self.transform_color());
-        self.players[self.player_num as usize].init( ctx );
+    //  背景色
+    pub fn get_background_color( &self ) -> Color {
+        return self.themes[self.theme_num].background_color();
+    }
 
-        if self.board.there_is_select_cells() {
-            self.skip_count = 0;
+    //  表示言語でのメッセージ
+    fn message( &self, message: locale::Message ) -> &'static str {
+        return locale::text( self.settings.language, message );
+    }
 
-            self.ui.pop_textUI( "turn".to_string() );
-            let mut text: String;
-            match self.player_num {
-                PLAYER::ONE => { text = "Black Turn".to_string() },
-                PLAYER::TWO => { text = "White Turn".to_string() },
-            }
-            let turn_text = ui::TextUI::new( "turn".to_string(), 
-            text, 32.0, DrawParams::new().position( Vec2::new( 720.0, 80.0 ) ), ctx );
-            self.ui.push_textUI( turn_text.unwrap() );
+    //  設定メニューを開く
+    fn open_menu( &mut self, ctx: &mut Context ) {
+        let theme = &self.themes[self.theme_num];
+        let fullscreen = if window::is_fullscreen( ctx ) { locale::Message::ON } else { locale::Message::OFF };
+        let view_from = if self.board.is_flipped() { locale::Message::WHITE } else { locale::Message::BLACK };
+        let sound = if self.audio.is_mute() { locale::Message::OFF } else { locale::Message::ON };
+        let music = if self.settings.music { locale::Message::ON } else { locale::Message::OFF };
+        let items = [
+            ( "theme", format!( "{}: {}", self.message( locale::Message::THEME ), theme.name ) ),
+            ( "fullscreen", format!( "{}: {}", self.message( locale::Message::FULLSCREEN ), self.message( fullscreen ) ) ),
+            ( "flip_board", format!( "{}: {}", self.message( locale::Message::VIEW_FROM ), self.message( view_from ) ) ),
+            ( "language", format!( "{}: {}", self.message( locale::Message::LANGUAGE ), self.settings.language.name() ) ),
+            ( "volume", format!( "{}: {}%", self.message( locale::Message::VOLUME ), ( self.audio.get_volume() * 100.0 ).round() ) ),
+            ( "mute", format!( "{}: {}", self.message( locale::Message::SOUND ), self.message( sound ) ) ),
+            ( "music", format!( "{}: {}", self.message( locale::Message::MUSIC ), self.message( music ) ) ),
+            ( "close", self.message( locale::Message::CLOSE ).to_string() ),
+        ];
+        self.menu.open( &items, theme.text_color(), &self.layout, &mut self.assets, ctx ).unwrap();
+    }
 
-            self.state = State::TURN_UPDATE;
+    //  設定メニュー更新
+    fn update_menu( &mut self, ctx: &mut Context ) {
+        if input::is_key_pressed( ctx, Key::F1 ) || input::is_key_pressed( ctx, Key::Backspace ) {
+            self.menu.close();
             return;
         }
 
-        //  置くところが無ければターン終了
-        self.skip_count += 1;
+        let key = match self.menu.update( ctx ) {
+            Some( key ) => key,
+            None => return,
+        };
 
-        //  2回連続スキップはリザルトへ
-        if self.skip_count >= 2 {
-            self.state = State::RESULT;
+        match key.as_str() {
+            "theme" => {
+                self.theme_num = ( self.theme_num + 1 ) % self.themes.len();
+                self.apply_theme( ctx );
+                self.settings.theme = self.themes[self.theme_num].name.clone();
+                self.settings.save();
+                self.open_menu( ctx );
+            },
+            "fullscreen" => {
+                self.toggle_fullscreen( ctx );
+                self.open_menu( ctx );
+            },
+            "flip_board" => {
+                self.toggle_flip_board();
+                self.open_menu( ctx );
+            },
+            "language" => {
+                self.settings.language = self.settings.language.next();
+                self.settings.save();
+                self.refresh_texts( ctx );
+                self.open_menu( ctx );
+            },
+            "volume" => {
+                //  20% 刻みで一周
+                let volume = self.audio.get_volume() + 0.2;
+                self.audio.set_volume( ctx, if volume > 1.01 { 0.0 } else { volume } );
+                self.settings.volume = self.audio.get_volume();
+                self.settings.save();
+                self.open_menu( ctx );
+            },
+            "mute" => {
+                self.toggle_mute( ctx );
+                self.open_menu( ctx );
+            },
+            "music" => {
+                let music = !self.settings.music;
+                self.audio.set_music( ctx, music );
+                self.settings.music = music;
+                self.settings.save();
+                self.open_menu( ctx );
+            },
+            _ => {
+                self.menu.close();
+            },
         }
+    }
 
-        self.state = State::TURN_FINISH;
+    //  ウィンドウサイズ変更
+    pub fn resize( &mut self, ctx: &mut Context, width: i32, height: i32 ) {
+        if !self.layout.is_changed( width, height ) {
+            return;
+        }
+
+        self.layout = layout::Layout::new( width, height );
+        self.apply_layout( ctx );
+
+        if self.menu.is_open() {
+            self.open_menu( ctx );
+        }
+    }
+
+    fn toggle_fullscreen( &mut self, ctx: &mut Context ) {
+        let fullscreen = !window::is_fullscreen( ctx );
+        if let Err( e ) = window::set_fullscreen( ctx, fullscreen ) {
+            eprintln!( "fullscreen: {}", e );
+            return;
+        }
+
+        self.settings.fullscreen = fullscreen;
+        self.settings.save();
+    }
+
+    fn toggle_mute( &mut self, ctx: &mut Context ) {
+        let mute = !self.audio.is_mute();
+        self.audio.set_mute( ctx, mute );
+        self.settings.mute = mute;
+        self.settings.save();
+    }
+
+    //  盤面の向きを反転 ( 白側から見る )
+    fn toggle_flip_board( &mut self ) {
+        let flipped = !self.board.is_flipped();
+        self.board.set_flipped( flipped );
+        self.settings.flip_board = flipped;
+        self.settings.save();
+    }
+
+    //  画面配置を反映
+    fn apply_layout( &mut self, ctx: &mut Context ) {
+        let theme = &self.themes[self.theme_num];
+        self.board.set_layout( ctx, &mut self.assets, theme, self.layout.board_position(), self.layout.cell_size() ).unwrap();
+        for i in 0 .. PLAYER_NUM {
+            self.players[i].set_scale( self.layout.get_scale() );
+        }
+        self.ui.set_layout( &self.layout, &mut self.assets, ctx ).unwrap();
+        self.graph.set_layout( &self.layout );
+    }
+
+    //  選択中のテーマを反映
+    fn apply_theme( &mut self, ctx: &mut Context ) {
+        let theme = &self.themes[self.theme_num];
+        self.assets.set_font_paths( &theme.font.path, &theme.font.fallback );
+        self.board.set_theme( ctx, &mut self.assets, theme ).unwrap();
+        for i in 0 .. PLAYER_NUM {
+            self.players[i].set_theme( ctx, &mut self.assets, theme ).unwrap();
+        }
+        self.ui.set_color( theme.text_color() );
+        self.graph.set_color( theme.text_color() );
+    }
+
+    //  表示中のテキストを現在の言語で作り直す
+    fn refresh_texts( &mut self, ctx: &mut Context ) {
+        self.push_settings_button( ctx );
+
+        if self.ui.pop_textUI( "turn".to_string() ).is_some() {
+            self.push_turn_text( ctx );
+        }
+
+        if self.ui.pop_textUI( "result".to_string() ).is_some() {
+            self.push_result_text( ctx );
+        }
+
+        if self.ui.pop_textUI( "opening".to_string() ).is_some() {
+            self.push_opening_text( ctx );
+        }
+
+        if self.ui.pop_buttonUI( "review".to_string() ).is_some() {
+            self.push_result_buttons( ctx );
+        }
+
+        if self.review.is_some() {
+            self.push_review_texts( ctx );
+        }
+
+        self.push_remote_texts( ctx );
+    }
+
+    fn push_settings_button( &mut self, ctx: &mut Context ) {
+        self.ui.pop_buttonUI( "settings".to_string() );
+        let text = self.message( locale::Message::SETTINGS ).to_string();
+        let settings_button = ui::ButtonUI::new( "settings".to_string(), text, 32.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 640.0 ) ), | _ | {}, &mut self.assets, ctx );
+        self.ui.push_buttonUI( settings_button.unwrap(), &mut self.assets, ctx );
+    }
+
+    //  手番表示
+    fn push_turn_text( &mut self, ctx: &mut Context ) {
+        self.ui.pop_textUI( "turn".to_string() );
+        let text = match self.turn.get_player() {
+            PLAYER::ONE => self.message( locale::Message::BLACK_TURN ),
+            PLAYER::TWO => self.message( locale::Message::WHITE_TURN ),
+        };
+        let turn_text = ui::TextUI::new( "turn".to_string(), 
+        text.to_string(), 32.0, DrawParams::new().position( Vec2::new( layout::PANEL_X, 80.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( turn_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    //  定石の名前 ( 定石を外れたら消す )
+    fn push_opening_text( &mut self, ctx: &mut Context ) {
+        self.ui.pop_textUI( "opening".to_string() );
+        let name = match self.book.get_name( self.board.get_rules().get_variant(), &self.moves ) {
+            Some( name ) => name,
+            None => return,
+        };
+        let text = format!( "{}: {}", self.message( locale::Message::OPENING ), name );
+        let opening_text = ui::TextUI::new( "opening".to_string(), text, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 120.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( opening_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    //  勝敗表示
+    fn push_result_text( &mut self, ctx: &mut Context ) {
+        self.ui.pop_textUI( "result".to_string() );
+        let outcome = self.forced_outcome.unwrap_or( turn::get_outcome( self.board.get_rules() ) );
+        let message = match outcome {
+            turn::Outcome::BLACK_WIN => locale::Message::BLACK_WIN,
+            turn::Outcome::WHITE_WIN => locale::Message::WHITE_WIN,
+            turn::Outcome::DRAW => locale::Message::DRAW,
+        };
+
+        let result_text = ui::TextUI::new( "result".to_string(), self.message( message ).to_string(),
+        64.0, DrawParams::new().position( Vec2::new( 800.0, 400.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( result_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    //  ターン開始
+    fn turn_start( &mut self, ctx: &mut Context ) {
+        self.board.init_turn( self.turn.get_color() );
+        self.players[self.turn.get_player() as usize].init( ctx );
+
+        match self.turn.start( self.board.get_rules() ) {
+            turn::Phase::PLAY => {
+                self.push_turn_text( ctx );
+                self.push_opening_text( ctx );
+                self.state = State::TURN_UPDATE;
+            },
+            //  置くところが無ければターン終了
+            turn::Phase::PASS => {
+                //  通信相手のパスは相手から PASS が届くのを待つ
+                if self.players[self.turn.get_player() as usize].get_kind() == turn::Kind::REMOTE {
+                    self.state = State::TURN_UPDATE;
+                    return;
+                }
+
+                self.send_remote( net::Message::PASS );
+                self.send_spectators( net::Message::PASS );
+                self.audio.play( ctx, audio::Se::PASS );
+                self.state = State::TURN_FINISH;
+            },
+            //  2回連続スキップはリザルトへ
+            turn::Phase::END => {
+                self.state = State::RESULT;
+            },
+        }
     }
 
     //  ターン更新
     fn turn_update( &mut self, ctx: &mut Context ) {
-        let player_num = self.player_num as usize;
+        let player_num = self.turn.get_player() as usize;
+        match self.players[player_num].get_kind() {
+            turn::Kind::HUMAN => {},
+            turn::Kind::AI => {
+                self.ai_update( ctx );
+                return;
+            },
+            turn::Kind::REMOTE => {
+                self.remote_update( ctx );
+                return;
+            },
+            turn::Kind::ENGINE => {
+                self.engine_update( ctx );
+                return;
+            },
+            turn::Kind::REPLAY => {
+                self.replay_update( ctx );
+                return;
+            },
+        }
+
         self.players[player_num].update( ctx );
 
         if self.players[player_num].get_selected() {
             let cell_position = self.board.transform_board_num(self.players[player_num].get_position());
-            if self.board.set_piece(cell_position.x, cell_position.y, self.transform_color()) {
+            if self.place_piece( ctx, cell_position ) {
+                self.send_remote( net::Message::MOVE( cell_position ) );
                 self.state = State::TURN_FINISH;
             } else {
+                //  盤外のクリック ( ボタンなど ) では鳴らさない
+                if cell_position.x >= 0 && cell_position.y >= 0 {
+                    self.audio.play( ctx, audio::Se::ILLEGAL );
+                }
                 self.players[player_num].cancel();
             }
         }
     }
 
-    //  ターン終了
-    fn turn_finish( &mut self ) {
-        //  終了チェック
-        if self.board.get_piece_count( board::cell::Color::NONE ) == 0 {
-            self.state = State::RESULT;
+    //  AI の手番
+    //  読みは別スレッドで行い、結果が届くまで毎フレーム確認する
+    fn ai_update( &mut self, ctx: &mut Context ) {
+        let color = self.turn.get_color();
+        let rules = self.board.get_rules();
+        //  持ち時間があれば残り時間から 1手の時間を決める
+        let clock = self.remote.as_ref().and_then( | s | s.clock )
+            .map( | ( black, white ) | if color == board::cell::Color::BLACK { black } else { white } );
+        let budget = ai::time_budget( rules, self.level, clock );
+
+        let position = match self.thinking.as_ref().map( | t | t.poll() ) {
+            None => {
+                //  定石の中なら読まずに打つ
+                match self.book.choose( rules.get_variant(), &self.moves ).filter( | p | rules.can_piece( p.x, p.y, color ) ) {
+                    Some( position ) => Some( position ),
+                    None => {
+                        self.thinking = Some( ai::Thinking::start( rules, color, self.level, Some( budget ) ) );
+                        return;
+                    },
+                }
+            },
+            Some( None ) => return,
+            Some( Some( Ok( result ) ) ) => result.position,
+            //  読みのスレッドが手を返さずに終わったら 1手読みで打つ ( 描画を止めない )
+            Some( Some( Err( _ ) ) ) => ai::search( rules, color, 1 ).position,
+        };
+        self.thinking = None;
+
+        if let Some( position ) = position {
+            if self.place_piece( ctx, position ) {
+                self.send_remote( net::Message::MOVE( position ) );
+            }
+        }
+        self.state = State::TURN_FINISH;
+    }
+
+    //  外部のエンジンの手番 ( 手を聞いて、返事が届くまで毎フレーム確認 )
+    fn engine_update( &mut self, ctx: &mut Context ) {
+        let variant = self.board.get_rules().get_variant();
+        let engine = match self.players[self.turn.get_player() as usize].get_engine_mut() {
+            Some( engine ) => engine,
+            None => return,
+        };
+
+        if !engine.is_thinking() {
+            if let Err( e ) = engine.request( variant, &self.moves ) {
+                self.engine_error( ctx, e.to_string() );
+            }
+            return;
+        }
+
+        match engine.poll() {
+            None => {},
+            Some( Ok( Some( position ) ) ) => {
+                if self.place_piece( ctx, position ) {
+                    self.send_remote( net::Message::MOVE( position ) );
+                    self.state = State::TURN_FINISH;
+                } else {
+                    self.engine_error( ctx, format!( "illegal move {}", rules::to_notation( position ) ) );
+                }
+            },
+            //  置けるマスがあるのにパス
+            Some( Ok( None ) ) => self.engine_error( ctx, "illegal pass".to_string() ),
+            Some( Err( e ) ) => self.engine_error( ctx, e ),
+        }
+    }
+
+    //  エンジンが正しい手を返さなければ負け
+    fn engine_error( &mut self, ctx: &mut Context, text: String ) {
+        eprintln!( "engine: {}", text );
+        let outcome = match self.turn.get_player() {
+            PLAYER::ONE => turn::Outcome::WHITE_WIN,
+            PLAYER::TWO => turn::Outcome::BLACK_WIN,
+        };
+        self.send_remote( net::Message::RESIGN );
+        self.finish_remote( ctx, Some( outcome ), locale::Message::ENGINE_ERROR );
+    }
+
+    //  棋譜の手番 ( 一定の間隔で次の手を並べる )
+    fn replay_update( &mut self, ctx: &mut Context ) {
+        if self.replay_time.elapsed() < REPLAY_INTERVAL {
             return;
         }
+        self.replay_time = Instant::now();
+
+        let record = match self.record.as_ref() {
+            Some( record ) => record,
+            None => return,
+        };
 
-        //  プレイヤー交代
-        match self.player_num {
-            PLAYER::ONE => {
-                self.player_num = PLAYER::TWO;
+        match record.positions().get( self.moves.len() ).copied() {
+            Some( position ) => {
+                if self.place_piece( ctx, position ) {
+                    self.state = State::TURN_FINISH;
+                } else {
+                    eprintln!( "record: illegal move {}", rules::to_notation( position ) );
+                    self.finish_remote( ctx, None, locale::Message::DESYNC );
+                }
             },
-            PLAYER::TWO => {
-                self.player_num = PLAYER::ONE;
+            //  投了・時間切れで終わった棋譜
+            None => {
+                let result = record.result;
+                let info = match result.map( | r | r.ending ) {
+                    Some( ggf::Ending::TIME ) => locale::Message::TIME_UP,
+                    Some( ggf::Ending::AGREEMENT ) => locale::Message::DRAW,
+                    Some( _ ) => locale::Message::RESIGNED,
+                    None => locale::Message::RECORD_END,
+                };
+                self.finish_remote( ctx, result.map( | r | r.outcome() ), info );
             },
         }
+    }
 
-        self.state = State::TURN_START;
+    //  通信相手の手番 ( 届いた手を盤面と照らし合わせてから反映 )
+    fn remote_update( &mut self, ctx: &mut Context ) {
+        let message = match self.remote_moves.pop_front() {
+            Some( message ) => message,
+            None => return,
+        };
+
+        match message {
+            net::Message::MOVE( position ) => {
+                if self.place_piece( ctx, position ) {
+                    self.state = State::TURN_FINISH;
+                } else {
+                    self.reject_remote( ctx, format!( "illegal move {}", rules::to_notation( position ) ) );
+                }
+            },
+            net::Message::PASS => {
+                if self.board.there_is_select_cells() {
+                    self.reject_remote( ctx, "illegal pass".to_string() );
+                } else {
+                    self.send_spectators( net::Message::PASS );
+                    self.audio.play( ctx, audio::Se::PASS );
+                    self.state = State::TURN_FINISH;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    //  手番のピースを置いて効果音 ( 置けなければ false )
+    fn place_piece( &mut self, ctx: &mut Context, position: Vec2<i32> ) -> bool {
+        if !self.board.set_piece( position.x, position.y, self.turn.get_color() ) {
+            return false;
+        }
+
+        self.moves.push( position );
+        self.send_spectators( net::Message::MOVE( position ) );
+        self.audio.play( ctx, audio::Se::PLACE );
+        self.audio.play_flip( ctx, self.board.get_last_reverse_count() );
+        return true;
+    }
+
+    fn send_spectators( &mut self, message: net::Message ) {
+        if let Some( spectators ) = self.spectators.as_mut() {
+            spectators.send( &message );
+        }
+    }
+
+    //  途中から観戦する場合はそこまでの手順を並べる
+    fn replay_watch( &mut self ) {
+        let ( moves, color ) = match self.watch.as_ref() {
+            Some( watch ) => ( watch.moves.clone(), watch.color ),
+            None => return,
+        };
+
+        for position in moves {
+            //  パスは手番を進めるだけ
+            if self.turn.start( self.board.get_rules() ) == turn::Phase::PASS {
+                self.turn.finish( self.board.get_rules() );
+                self.turn.start( self.board.get_rules() );
+            }
+            self.board.set_piece( position.x, position.y, self.turn.get_color() );
+            self.moves.push( position );
+            self.turn.finish( self.board.get_rules() );
+        }
+
+        //  観戦を始める前にパスが済んでいる
+        if self.turn.get_color() != color {
+            self.turn.start( self.board.get_rules() );
+            self.turn.finish( self.board.get_rules() );
+        }
+    }
+
+    //  通信相手へ送信 ( 失敗は次の受信で切断として扱われる )
+    fn send_remote( &mut self, message: net::Message ) {
+        if let Some( session ) = self.remote.as_mut() {
+            if let Err( e ) = session.connection.send( &message ) {
+                eprintln!( "network: {}", e );
+            }
+        }
+    }
+
+    //  通信相手からの受信 ( 手以外はすぐに処理 )
+    fn update_remote( &mut self, ctx: &mut Context ) {
+        let messages = if let Some( session ) = self.remote.as_mut() {
+            session.connection.receive()
+        } else if let Some( watch ) = self.watch.as_mut() {
+            watch.connection.receive()
+        } else {
+            return;
+        };
+
+        for message in messages {
+            match message {
+                net::Message::MOVE( _ ) | net::Message::PASS => self.remote_moves.push_back( message ),
+                net::Message::RESIGN => {
+                    let outcome = self.local_win_outcome();
+                    self.finish_remote( ctx, Some( outcome ), locale::Message::OPPONENT_RESIGNED );
+                },
+                net::Message::DRAW_OFFER => {
+                    if !self.is_game_over() {
+                        self.draw_offered = true;
+                        self.set_info( ctx, locale::Message::DRAW_OFFERED );
+                    }
+                },
+                net::Message::DRAW_ACCEPT => self.finish_remote( ctx, Some( turn::Outcome::DRAW ), locale::Message::DRAW ),
+                net::Message::DRAW_DECLINE => self.set_info( ctx, locale::Message::DRAW_DECLINED ),
+                net::Message::CHAT( text ) => {
+                    let opponent = self.remote.as_ref().map( | s | s.opponent.clone() ).unwrap_or_default();
+                    self.push_chat_text( ctx, format!( "{}: {}", opponent, text ) );
+                },
+                net::Message::ERROR( text ) => {
+                    eprintln!( "network: {}", text );
+                    self.finish_remote( ctx, None, locale::Message::DESYNC );
+                },
+                net::Message::BYE => self.finish_remote( ctx, None, locale::Message::DISCONNECTED ),
+                net::Message::CLOCK( black, white ) => {
+                    if let Some( session ) = self.remote.as_mut() {
+                        session.clock = Some( ( black, white ) );
+                    }
+                    if let Some( watch ) = self.watch.as_mut() {
+                        watch.clock = Some( ( black, white ) );
+                    }
+                    self.push_clock_text( ctx );
+                },
+                //  観戦中の対局の終局
+                net::Message::RESULT( outcome, _ ) => self.finish_remote( ctx, Some( outcome ), locale::Message::SPECTATING ),
+                net::Message::TIMEOUT( color ) => {
+                    let outcome = if color == board::cell::Color::BLACK { turn::Outcome::WHITE_WIN } else { turn::Outcome::BLACK_WIN };
+                    self.finish_remote( ctx, Some( outcome ), locale::Message::TIME_UP );
+                },
+                _ => {},
+            }
+        }
+    }
+
+    //  通信対戦のキー操作 ( R: 投了、D: 引き分けの提案、Y / N: 提案への返事 )
+    fn update_remote_keys( &mut self, ctx: &mut Context ) {
+        if self.remote.is_none() || self.is_game_over() {
+            return;
+        }
+
+        if self.draw_offered {
+            if input::is_key_pressed( ctx, Key::Y ) {
+                self.draw_offered = false;
+                self.send_remote( net::Message::DRAW_ACCEPT );
+                self.finish_remote( ctx, Some( turn::Outcome::DRAW ), locale::Message::DRAW );
+            } else if input::is_key_pressed( ctx, Key::N ) {
+                self.draw_offered = false;
+                self.send_remote( net::Message::DRAW_DECLINE );
+                self.set_info( ctx, locale::Message::NETWORK_KEYS );
+            }
+            return;
+        }
+
+        if input::is_key_pressed( ctx, Key::R ) {
+            self.send_remote( net::Message::RESIGN );
+            let outcome = match self.local_win_outcome() {
+                turn::Outcome::BLACK_WIN => turn::Outcome::WHITE_WIN,
+                _ => turn::Outcome::BLACK_WIN,
+            };
+            self.finish_remote( ctx, Some( outcome ), locale::Message::YOU_RESIGNED );
+        } else if input::is_key_pressed( ctx, Key::D ) {
+            self.send_remote( net::Message::DRAW_OFFER );
+            self.set_info( ctx, locale::Message::DRAW_OFFER_SENT );
+        }
+    }
+
+    //  チャット入力 ( Enter で開始・送信。入力中なら true )
+    fn update_chat( &mut self, ctx: &mut Context ) -> bool {
+        if self.remote.is_none() {
+            return false;
+        }
+
+        let enter = input::is_key_pressed( ctx, Key::Enter ) || input::is_key_pressed( ctx, Key::NumPadEnter );
+        let mut text = match self.chat_input.take() {
+            Some( text ) => text,
+            None => {
+                if enter {
+                    self.chat_input = Some( String::new() );
+                    self.push_chat_input_text( ctx );
+                    return true;
+                }
+                return false;
+            },
+        };
+
+        if enter {
+            self.ui.pop_textUI( "chat_input".to_string() );
+            if !text.trim().is_empty() {
+                self.send_remote( net::Message::CHAT( text.clone() ) );
+                let name = self.remote.as_ref().map( | s | s.name.clone() ).unwrap_or_default();
+                self.push_chat_text( ctx, format!( "{}: {}", name, text ) );
+            }
+            return true;
+        }
+
+        let mut changed = false;
+        if let Some( input ) = input::get_text_input( ctx ) {
+            text.push_str( input );
+            changed = true;
+        }
+        if input::is_key_pressed( ctx, Key::Backspace ) {
+            text.pop();
+            changed = true;
+        }
+
+        self.chat_input = Some( text );
+        if changed {
+            self.push_chat_input_text( ctx );
+        }
+        return true;
+    }
+
+    //  盤面が合わない手は相手に知らせて対局を止める
+    fn reject_remote( &mut self, ctx: &mut Context, text: String ) {
+        eprintln!( "network: {}", text );
+        self.send_remote( net::Message::ERROR( text ) );
+        self.finish_remote( ctx, None, locale::Message::DESYNC );
+    }
+
+    //  通信対戦の終了 ( outcome が None なら勝敗は付けない )
+    fn finish_remote( &mut self, ctx: &mut Context, outcome: Option<turn::Outcome>, info: locale::Message ) {
+        self.set_info( ctx, info );
+        if self.is_game_over() {
+            return;
+        }
+
+        self.draw_offered = false;
+        match outcome {
+            Some( outcome ) => {
+                self.forced_outcome = Some( outcome );
+                self.state = State::RESULT;
+            },
+            None => self.state = State::RESULT_WAIT,
+        }
+    }
+
+    //  自分が勝った場合の勝敗
+    fn local_win_outcome( &self ) -> turn::Outcome {
+        match self.remote.as_ref().map( | s | s.color ) {
+            Some( board::cell::Color::WHITE ) => return turn::Outcome::WHITE_WIN,
+            _ => return turn::Outcome::BLACK_WIN,
+        }
+    }
+
+    fn is_game_over( &self ) -> bool {
+        match self.state {
+            State::RESULT | State::RESULT_WAIT => return true,
+            _ => return false,
+        }
+    }
+
+    //  通信対戦の表示 ( 相手の名前・お知らせ )
+    fn push_remote_texts( &mut self, ctx: &mut Context ) {
+        let text = if let Some( session ) = self.remote.as_ref() {
+            format!( "{} {}", self.message( locale::Message::VERSUS ), session.opponent )
+        } else if let Some( watch ) = self.watch.as_ref() {
+            format!( "{} vs {}", watch.black, watch.white )
+        } else if let Some( record ) = self.record.as_ref() {
+            format!( "{} vs {}", record.black, record.white )
+        } else {
+            return;
+        };
+
+        self.ui.pop_textUI( "versus".to_string() );
+        let versus_text = ui::TextUI::new( "versus".to_string(), text, 24.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 140.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( versus_text.unwrap(), &mut self.assets, ctx );
+
+        let keys = if self.watch.is_some() {
+            locale::Message::SPECTATING
+        } else if self.record.is_some() {
+            locale::Message::REPLAYING
+        } else {
+            locale::Message::NETWORK_KEYS
+        };
+        let info = self.info.unwrap_or( keys );
+        self.set_info( ctx, info );
+        self.push_clock_text( ctx );
+    }
+
+    //  サーバーの持ち時間 ( 届いた時点の値を表示 )
+    fn push_clock_text( &mut self, ctx: &mut Context ) {
+        let clock = self.remote.as_ref().and_then( | s | s.clock ).or( self.watch.as_ref().and_then( | w | w.clock ) );
+        let ( black, white ) = match clock {
+            Some( clock ) => clock,
+            None => return,
+        };
+
+        self.ui.pop_textUI( "clock".to_string() );
+        let text = format!( "{} {}  {} {}", self.message( locale::Message::BLACK ), net::format_clock( black ),
+            self.message( locale::Message::WHITE ), net::format_clock( white ) );
+        let clock_text = ui::TextUI::new( "clock".to_string(), text, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 175.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( clock_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    fn set_info( &mut self, ctx: &mut Context, info: locale::Message ) {
+        self.info = Some( info );
+        self.ui.pop_textUI( "info".to_string() );
+        let info_text = ui::TextUI::new( "info".to_string(), self.message( info ).to_string(), 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 480.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( info_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    fn push_chat_text( &mut self, ctx: &mut Context, text: String ) {
+        self.ui.pop_textUI( "chat".to_string() );
+        let chat_text = ui::TextUI::new( "chat".to_string(), text, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 520.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( chat_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    fn push_chat_input_text( &mut self, ctx: &mut Context ) {
+        self.ui.pop_textUI( "chat_input".to_string() );
+        let text = format!( "> {}", self.chat_input.as_deref().unwrap_or( "" ) );
+        let input_text = ui::TextUI::new( "chat_input".to_string(), text, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 550.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( input_text.unwrap(), &mut self.assets, ctx );
+    }
+
+    //  ターン終了 ( 終了チェックとプレイヤー交代 )
+    fn turn_finish( &mut self ) {
+        match self.turn.finish( self.board.get_rules() ) {
+            turn::Phase::END => self.state = State::RESULT,
+            _ => self.state = State::TURN_START,
+        }
     }
 
     //  リセット
@@ -189,38 +1034,220 @@ impl Game {
 
     //  リザルト
     fn result( &mut self, ctx: &mut Context ) {
-        let black_piece_num = self.board.get_piece_count( board::cell::Color::BLACK );
-        let white_piece_num = self.board.get_piece_count( board::cell::Color::WHITE );
-        let result_text: String;
-
-        if black_piece_num == white_piece_num {
-            result_text = "Draw".to_string();
-        } else if black_piece_num < white_piece_num {
-            result_text = "White Win".to_string();
-        } else {
-            result_text = "Black Win".to_string();
+        if let Some( spectators ) = self.spectators.as_mut() {
+            let outcome = self.forced_outcome.unwrap_or( turn::get_outcome( self.board.get_rules() ) );
+            let reason = match ( self.forced_outcome, self.info ) {
+                ( None, _ ) => "finished",
+                ( Some( _ ), Some( locale::Message::TIME_UP ) ) => "time",
+                ( Some( _ ), Some( locale::Message::DRAW ) ) => "agreement",
+                _ => "resign",
+            };
+            spectators.finish( outcome, reason );
         }
 
-        self.ui.push_textUI( ui::TextUI::new( "result".to_string(), result_text,
-        64.0, DrawParams::new().position( Vec2::new( 800.0, 400.0 ) ), ctx ).unwrap() );
-
+        self.thinking = None;
+        self.learn_game();
+        self.push_result_text( ctx );
+        self.push_result_buttons( ctx );
+        self.audio.play( ctx, audio::Se::GAME_END );
         self.state = State::RESULT_WAIT;
     }
 
+    //  AI が打った対局は定石に覚える ( 完全読みできる所まで進んでいなければ覚えない )
+    fn learn_game( &mut self ) {
+        if !self.players.iter().any( | p | p.get_kind() == turn::Kind::AI ) {
+            return;
+        }
+
+        //  完全読みは時間がかかるので別スレッドで
+        self.learner.push( self.board.get_rules().get_variant(), &self.moves );
+    }
+
+    //  完全読みが終わった対局を定石に覚えて保存
+    fn poll_learning( &mut self ) {
+        let mut learned = false;
+        while let Some( result ) = self.learner.poll() {
+            if let Ok( ( moves, score ) ) = result {
+                self.book.add_solved( moves, score );
+                learned = true;
+            }
+        }
+
+        if learned {
+            if let Err( e ) = self.book.save() {
+                eprintln!( "book {}: {}", book::LEARNED_PATH, e );
+            }
+        }
+    }
+
+    //  振り返り・新しい対局のボタン
+    fn push_result_buttons( &mut self, ctx: &mut Context ) {
+        self.ui.pop_buttonUI( "review".to_string() );
+        let text = self.message( locale::Message::REVIEW ).to_string();
+        let review_button = ui::ButtonUI::new( "review".to_string(), text, 24.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 595.0 ) ), | _ | {}, &mut self.assets, ctx );
+        self.ui.push_buttonUI( review_button.unwrap(), &mut self.assets, ctx );
+        self.push_new_game_button( ctx );
+    }
+
+    //  通信対戦・観戦・棋譜の再生は 1局で終わり
+    fn push_new_game_button( &mut self, ctx: &mut Context ) {
+        self.ui.pop_buttonUI( "new_game".to_string() );
+        if self.remote.is_some() || self.watch.is_some() || self.record.is_some() {
+            return;
+        }
+
+        let text = self.message( locale::Message::NEW_GAME ).to_string();
+        let new_game_button = ui::ButtonUI::new( "new_game".to_string(), text, 24.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X + 100.0, 595.0 ) ), | _ | {}, &mut self.assets, ctx );
+        self.ui.push_buttonUI( new_game_button.unwrap(), &mut self.assets, ctx );
+    }
+
     fn result_wait( &mut self, ctx: &mut Context ) {
-        if input::is_mouse_button_released( ctx, MouseButton::Left ) {
+        if self.ui.is_clicked( "review" ) {
+            self.start_review( ctx );
+        } else if self.ui.is_clicked( "new_game" ) {
             self.state = State::RESET;
         }
     }
 
-    fn transform_color( &self ) -> board::cell::Color {
-        match self.player_num {
-            PLAYER::ONE => {
-                return board::cell::Color::BLACK;
-            },
-            PLAYER::TWO => {
-                return board::cell::Color::WHITE;
-            },
+    //  振り返りの開始 ( 最後の局面から )
+    fn start_review( &mut self, ctx: &mut Context ) {
+        let variant = self.board.get_rules().get_variant();
+        let mut review = review::Review::new( variant, &self.moves );
+        review.start_analysis( variant, &self.moves, self.level );
+        self.review = Some( review );
+        self.ui.pop_textUI( "result".to_string() );
+        self.ui.pop_textUI( "opening".to_string() );
+        self.ui.pop_buttonUI( "review".to_string() );
+        self.push_review_texts( ctx );
+        self.state = State::REVIEW;
+    }
+
+    //  振り返り ( 矢印キー・ボタン・手順一覧のクリックで局面を移動 )
+    fn review_update( &mut self, ctx: &mut Context ) {
+        if self.ui.is_clicked( "new_game" ) {
+            self.state = State::RESET;
+            return;
+        }
+
+        let review = match self.review.as_mut() {
+            Some( review ) => review,
+            None => return,
+        };
+
+        //  解析が届いたら印を付けて表示し直す
+        let mut changed = review.poll_analysis();
+        if input::is_key_pressed( ctx, Key::Left ) || self.ui.is_clicked( "review_prev" ) {
+            changed |= review.prev();
+        }
+        if input::is_key_pressed( ctx, Key::Right ) || self.ui.is_clicked( "review_next" ) {
+            changed |= review.next();
+        }
+        if input::is_key_pressed( ctx, Key::Home ) || input::is_key_pressed( ctx, Key::Up ) {
+            changed |= review.go( 0 );
+        }
+        if input::is_key_pressed( ctx, Key::End ) || input::is_key_pressed( ctx, Key::Down ) {
+            changed |= review.go( review.get_move_count() );
+        }
+
+        let ( start, end ) = move_list_range( review.get_index(), review.get_move_count() );
+        for number in start .. end {
+            if self.ui.is_clicked( &format!( "move_{}", number ) ) {
+                changed |= review.go( number );
+            }
+        }
+        if let Some( index ) = self.graph.get_clicked( ctx ) {
+            changed |= review.go( index );
+        }
+
+        if changed {
+            self.push_review_texts( ctx );
+        }
+    }
+
+    //  振り返りの表示 ( 盤面、手数、石数、手順一覧、評価値のグラフ )
+    fn push_review_texts( &mut self, ctx: &mut Context ) {
+        let review = match self.review.as_ref() {
+            Some( review ) => review,
+            None => return,
+        };
+        self.board.show_position( review.get_rules() );
+
+        let index = review.get_index();
+        let len = review.get_move_count();
+        let black = review.get_rules().get_piece_count( board::cell::Color::BLACK );
+        let white = review.get_rules().get_piece_count( board::cell::Color::WHITE );
+        let ( start, end ) = move_list_range( index, len );
+        let items: Vec<( usize, String )> = ( start .. end ).filter_map( | number | {
+            let ( color, position ) = review.get_move( number )?;
+            let player = if color == board::cell::Color::BLACK { locale::Message::BLACK } else { locale::Message::WHITE };
+            let cursor = if number == index { "> " } else { "" };
+            let mark = review.get_analysis( number ).and_then( | a | a.mark ).map_or( "", | m | m.symbol() );
+            Some( ( number, format!( "{}{}. {} {}{}", cursor, number, self.message( player ), rules::to_notation( position ), mark ) ) )
+        } ).collect();
+        let evaluations = review.get_evaluations();
+        let analysis = if review.is_analyzing() {
+            self.message( locale::Message::ANALYZING ).to_string()
+        } else {
+            match review.get_analysis( index ) {
+                Some( a ) if a.played == a.best => self.message( locale::Message::BEST_MOVE ).to_string(),
+                Some( a ) => {
+                    let mark = match a.mark {
+                        Some( analysis::Mark::BLUNDER ) => format!( "{}  ", self.message( locale::Message::BLUNDER ) ),
+                        Some( analysis::Mark::MISTAKE ) => format!( "{}  ", self.message( locale::Message::MISTAKE ) ),
+                        None => String::new(),
+                    };
+                    format!( "{}{} {}  {:+.1}", mark, self.message( locale::Message::BEST ), a.best, -a.loss )
+                },
+                None => String::new(),
+            }
+        };
+
+        self.ui.pop_textUI( "turn".to_string() );
+        let turn_text = ui::TextUI::new( "turn".to_string(), format!( "{} / {}", index, len ), 32.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 80.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( turn_text.unwrap(), &mut self.assets, ctx );
+
+        self.ui.pop_textUI( "review_count".to_string() );
+        let count = format!( "{} {}  {} {}", self.message( locale::Message::BLACK ), black, self.message( locale::Message::WHITE ), white );
+        let count_text = ui::TextUI::new( "review_count".to_string(), count, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 120.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( count_text.unwrap(), &mut self.assets, ctx );
+
+        self.ui.pop_textUI( "review_analysis".to_string() );
+        let analysis_text = ui::TextUI::new( "review_analysis".to_string(), analysis, 16.0,
+        DrawParams::new().position( Vec2::new( layout::PANEL_X, 210.0 ) ), &mut self.assets, ctx );
+        self.ui.push_textUI( analysis_text.unwrap(), &mut self.assets, ctx );
+
+        for number in 1 ..= len {
+            self.ui.pop_buttonUI( format!( "move_{}", number ) );
         }
+        for ( row, ( number, text ) ) in items.into_iter().enumerate() {
+            let position = Vec2::new( layout::PANEL_X, MOVE_LIST_Y + MOVE_LIST_HEIGHT * row as f32 );
+            let move_button = ui::ButtonUI::new( format!( "move_{}", number ), text, 16.0,
+            DrawParams::new().position( position ), | _ | {}, &mut self.assets, ctx );
+            self.ui.push_buttonUI( move_button.unwrap(), &mut self.assets, ctx );
+        }
+
+        //  前後の移動は手数の横に並べる ( 手順一覧の下はグラフ )
+        for ( key, text, x ) in [ ( "review_prev", "<", 150.0 ), ( "review_next", ">", 195.0 ) ].iter() {
+            self.ui.pop_buttonUI( key.to_string() );
+            let nav_button = ui::ButtonUI::new( key.to_string(), text.to_string(), 32.0,
+            DrawParams::new().position( Vec2::new( layout::PANEL_X + x, 80.0 ) ), | _ | {}, &mut self.assets, ctx );
+            self.ui.push_buttonUI( nav_button.unwrap(), &mut self.assets, ctx );
+        }
+
+        let color = self.themes[self.theme_num].text_color();
+        self.graph.set( ctx, &evaluations, index, color, &self.layout ).unwrap();
+
+        self.push_new_game_button( ctx );
     }
-}
\ No newline at end of file
+}
+
+//  手順一覧に並べる手 ( 1から数えた start .. end。表示中の手が真ん中あたりに来るように )
+fn move_list_range( index: usize, len: usize ) -> ( usize, usize ) {
+    let start = index.saturating_sub( MOVE_LIST_NUM / 2 ).min( ( len + 1 ).saturating_sub( MOVE_LIST_NUM ) ).max( 1 );
+    let end = ( start + MOVE_LIST_NUM ).min( len + 1 );
+    return ( start, end );
+}
@@ -0,0 +1,490 @@
+use std::sync::{ Arc, OnceLock };
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+use std::sync::mpsc::{ self, Receiver, TryRecvError };
+use std::thread;
+use std::time::{ Duration, Instant };
+use tetra::math::Vec2;
+use super::board::cell::Color;
+use super::rules::{ self, Rules };
+use table::{ Bound, Entry, Table };
+
+mod table;
+mod zobrist;
+
+pub const DEFAULT_LEVEL: u32 = 4;   //  標準の強さ
+pub const MAX_LEVEL: u32 = 10;      //  最大の強さ ( 読みの深さ )
+
+const MAX_ENDGAME_EMPTIES: u32 = 14;    //  完全読みする空きマス数の上限
+const WIN_SCORE: i32 = 10000;           //  読み切りで勝ちの評価値 ( 石差を加える )
+const INFINITY: i32 = 1000000;
+const MOBILITY_WEIGHT: i32 = 8;         //  着手可能数 1 つあたりの評価値
+const SCORE_PER_DISC: f32 = 10.0;       //  評価値を石差に直すときの目安
+const TABLE_BITS: u32 = 20;             //  置換表の大きさ ( 2 の 20乗エントリ、16MB )
+const EXACT_DEPTH: i32 = 64;            //  完全読みの値を置換表に入れるときの深さ ( どの深さの読みよりも優先 )
+const CHECK_INTERVAL: u64 = 256;        //  時間切れを確かめる間隔 ( ノード数 )
+const MOVE_TIME: Duration = Duration::from_secs( 3 );       //  持ち時間が無いときの中盤の 1手の時間
+const MIN_MOVE_TIME: Duration = Duration::from_millis( 50 );
+const RESERVE_MOVES: u32 = 4;           //  持ち時間を割るときに残りの手数に足しておく余裕
+const OPENING_EMPTIES: u32 = 44;        //  空きマスがこれより多ければ序盤
+const EXACT_START_MOVES: u32 = 4;       //  完全読みに入ってから長めに時間を使う手数 ( 空きマス数 )
+
+//  置換表 ( 全ての探索で共有する。最初に使うときに確保 )
+static TABLE: OnceLock<Table> = OnceLock::new();
+//  1つの探索に使うスレッド数 ( 0 なら CPU のコア数 )
+static THREADS: AtomicUsize = AtomicUsize::new( 0 );
+
+//  マスの重み ( 隅が高く、隅の隣は低い )
+const WEIGHTS: [[i32; 8]; 8] = [
+    [ 100, -20,  10,   5,   5,  10, -20, 100 ],
+    [ -20, -50,  -2,  -2,  -2,  -2, -50, -20 ],
+    [  10,  -2,   1,   1,   1,   1,  -2,  10 ],
+    [   5,  -2,   1,   0,   0,   1,  -2,   5 ],
+    [   5,  -2,   1,   0,   0,   1,  -2,   5 ],
+    [  10,  -2,   1,   1,   1,   1,  -2,  10 ],
+    [ -20, -50,  -2,  -2,  -2,  -2, -50, -20 ],
+    [ 100, -20,  10,   5,   5,  10, -20, 100 ],
+];
+
+//  探索結果
+#[derive(Copy, Clone)]
+pub struct SearchResult {
+    pub position: Option<Vec2<i32>>,    //  最善手 ( 置けなければ None )
+    pub score: i32,                     //  手番側から見た評価値 ( exact なら最終石差 )
+    pub exact: bool,                    //  終局まで読み切った
+}
+
+//  探索の打ち切り ( 時間切れ・中止になったら以降のノードはすぐ戻り、その深さの結果は捨てる )
+struct Control {
+    deadline: Option<Instant>,
+    cancel: Option<Arc<AtomicBool>>,    //  別スレッドの探索を止める
+    helper: usize,                      //  並列探索の補助スレッドの番号 ( 0 は結果を返すスレッド )
+    nodes: u64,
+    aborted: bool,
+}
+
+impl Control {
+    fn new( deadline: Option<Instant>, cancel: Option<Arc<AtomicBool>> ) -> Control {
+        return Control { deadline, cancel, helper: 0, nodes: 0, aborted: false };
+    }
+
+    fn should_stop( &mut self ) -> bool {
+        self.nodes += 1;
+        if !self.aborted && self.nodes % CHECK_INTERVAL == 0 {
+            self.aborted = self.deadline.map_or( false, | d | Instant::now() >= d )
+                || self.cancel.as_ref().map_or( false, | c | c.load( Ordering::Relaxed ) );
+        }
+        return self.aborted;
+    }
+}
+
+//  別スレッドでの探索 ( 描画を止めないように毎フレーム poll で結果を確かめる。drop すると打ち切る )
+pub struct Thinking {
+    result: Receiver<SearchResult>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Thinking {
+    pub fn start( rules: &Rules, color: Color, level: u32, budget: Option<Duration> ) -> Thinking {
+        let ( sender, result ) = mpsc::channel();
+        let cancel = Arc::new( AtomicBool::new( false ) );
+        let control = Control::new( budget.map( | b | Instant::now() + b ), Some( cancel.clone() ) );
+        let rules = *rules;
+        thread::spawn( move || {
+            let _ = sender.send( search_with( &rules, color, level, control ) );
+        } );
+        return Thinking { result, cancel };
+    }
+
+    //  読み終わっていれば結果 ( スレッドが結果を返さずに終わったら Err )
+    pub fn poll( &self ) -> Option<Result<SearchResult, String>> {
+        match self.result.try_recv() {
+            Ok( result ) => return Some( Ok( result ) ),
+            Err( TryRecvError::Empty ) => return None,
+            Err( TryRecvError::Disconnected ) => return Some( Err( "search stopped".to_string() ) ),
+        }
+    }
+}
+
+impl Drop for Thinking {
+    fn drop( &mut self ) {
+        self.cancel.store( true, Ordering::Relaxed );
+    }
+}
+
+//  強さに応じて最善手を探す ( 終盤は完全読み )
+pub fn search( rules: &Rules, color: Color, level: u32 ) -> SearchResult {
+    return search_timed( rules, color, level, None );
+}
+
+//  時間内で最善手を探す ( 1手ずつ深く読み、時間切れなら読み終えた一番深い結果を返す )
+//  終盤は続けて完全読みし、読み切れたらそちらを使う
+pub fn search_timed( rules: &Rules, color: Color, level: u32, budget: Option<Duration> ) -> SearchResult {
+    return search_with( rules, color, level, Control::new( budget.map( | b | Instant::now() + b ), None ) );
+}
+
+fn search_with( rules: &Rules, color: Color, level: u32, mut control: Control ) -> SearchResult {
+    let level = level.max( 1 ).min( MAX_LEVEL );
+    return parallel( &mut control, | control | deepen( rules, color, level, control ) );
+}
+
+//  補助スレッドは奇数番なら 1手深く読み始めて、結果を返すスレッドより先に置換表を埋める
+fn deepen( rules: &Rules, color: Color, level: u32, control: &mut Control ) -> SearchResult {
+    //  1手読みは打ち切らない ( いつでも返せる手を用意しておく )
+    let mut best = root( rules, color, 1, false, &mut Control::new( None, None ) );
+    for depth in 2 ..= level as i32 {
+        let depth = ( depth + ( control.helper % 2 ) as i32 ).min( level as i32 );
+        let result = root( rules, color, depth, false, control );
+        if control.aborted {
+            return best;
+        }
+        best = result;
+    }
+
+    if is_endgame( rules, level ) {
+        let result = root( rules, color, 0, true, control );
+        if !control.aborted {
+            best = result;
+        }
+    }
+    return best;
+}
+
+//  全ての合法手の評価値 ( 評価の高い順 )
+//  search と同じ深さ・読み方で、手ごとに窓を狭めずに読むので最善手と他の手の値を比べられる
+//  cancel が立ったら None
+pub fn score_moves( rules: &Rules, color: Color, level: u32, cancel: Option<Arc<AtomicBool>> ) -> Option<Vec<( Vec2<i32>, i32 )>> {
+    let level = level.max( 1 ).min( MAX_LEVEL );
+    let exact = is_endgame( rules, level );
+    let mut control = Control::new( None, cancel );
+    let scores = parallel( &mut control, | control | root_scores( rules, color, level as i32, exact, control ) );
+    if control.aborted {
+        return None;
+    }
+    return Some( scores );
+}
+
+//  終局まで完全読み
+pub fn solve( rules: &Rules, color: Color ) -> SearchResult {
+    return parallel( &mut Control::new( None, None ), | control | root( rules, color, 0, true, control ) );
+}
+
+//  別スレッドから止められる完全読み ( 止められたら読み切れていないので None )
+pub fn solve_cancellable( rules: &Rules, color: Color, cancel: Option<Arc<AtomicBool>> ) -> Option<SearchResult> {
+    let mut control = Control::new( None, cancel );
+    let result = parallel( &mut control, | control | root( rules, color, 0, true, control ) );
+    if control.aborted {
+        return None;
+    }
+    return Some( result );
+}
+
+//  探索に使うスレッド数を決める ( 0 なら CPU のコア数 )
+pub fn set_threads( threads: usize ) {
+    THREADS.store( threads, Ordering::Relaxed );
+}
+
+pub fn default_threads() -> usize {
+    return thread::available_parallelism().map_or( 1, | n | n.get() );
+}
+
+fn get_threads() -> usize {
+    match THREADS.load( Ordering::Relaxed ) {
+        0 => return default_threads(),
+        threads => return threads,
+    }
+}
+
+//  並列探索 ( 補助スレッドも同じ局面を読んで置換表を共有し、結果を返すスレッドの探索を速くする )
+//  補助スレッドは手の順番を変えて別の枝から読み、結果を返すスレッドが終わったら止める
+fn parallel<T, F>( control: &mut Control, run: F ) -> T
+    where F: Fn( &mut Control ) -> T + Sync
+{
+    let threads = get_threads();
+    if threads <= 1 {
+        return run( control );
+    }
+
+    let stop = Arc::new( AtomicBool::new( false ) );
+    return thread::scope( | scope | {
+        for helper in 1 .. threads {
+            let mut helper_control = Control { helper, ..Control::new( None, Some( stop.clone() ) ) };
+            let run = &run;
+            scope.spawn( move || {
+                run( &mut helper_control );
+            } );
+        }
+
+        let result = run( control );
+        stop.store( true, Ordering::Relaxed );
+        return result;
+    } );
+}
+
+//  1手に使う時間 ( 持ち時間 ( ミリ秒 ) があれば残りの自分の手数で割る。無ければ MOVE_TIME )
+//  どちらも局面の進み具合で重みを付ける
+pub fn time_budget( rules: &Rules, level: u32, remaining: Option<u64> ) -> Duration {
+    let empties = rules.get_piece_count( Color::NONE ) as u32;
+    let weight = phase_weight( empties, level.max( 1 ).min( MAX_LEVEL ) );
+    let remaining = match remaining {
+        Some( ms ) => Duration::from_millis( ms ),
+        None => return MOVE_TIME * weight / 100,
+    };
+
+    let moves = ( empties + 1 ) / 2 + RESERVE_MOVES;
+    return ( remaining / moves * weight / 100 ).max( MIN_MOVE_TIME ).min( remaining / 2 );
+}
+
+//  1手の時間の重み ( 百分率 )
+//  序盤は短く、中盤と完全読みに入ったばかりの所は長く、読み切った後の残りの手は短く
+fn phase_weight( empties: u32, level: u32 ) -> u32 {
+    let exact = ( level * 2 ).min( MAX_ENDGAME_EMPTIES );
+    if empties > OPENING_EMPTIES {
+        return 50;
+    } else if empties > exact {
+        return 100;
+    } else if empties + EXACT_START_MOVES > exact {
+        return 150;
+    }
+    return 25;
+}
+
+//  評価値をおおよその石差に直す ( 外部の GUI に返す用 )
+pub fn to_discs( score: i32, exact: bool ) -> f32 {
+    if exact {
+        return score as f32;
+    } else if score >= WIN_SCORE {
+        return ( score - WIN_SCORE ) as f32;
+    } else if score <= -WIN_SCORE {
+        return ( score + WIN_SCORE ) as f32;
+    }
+
+    return score as f32 / SCORE_PER_DISC;
+}
+
+//  完全読みに切り替える空きマス数
+pub fn is_endgame( rules: &Rules, level: u32 ) -> bool {
+    let empties = rules.get_piece_count( Color::NONE ) as u32;
+    return empties <= ( level * 2 ).min( MAX_ENDGAME_EMPTIES );
+}
+
+fn table() -> &'static Table {
+    return TABLE.get_or_init( || Table::new( TABLE_BITS ) );
+}
+
+//  置換表のキー ( 完全読みは読みの値と別の局面として扱う )
+fn table_key( rules: &Rules, color: Color, exact: bool ) -> u64 {
+    return zobrist::hash( rules, color ) ^ if exact { zobrist::EXACT_KEY } else { 0 };
+}
+
+//  打ち切られたら結果は使えない ( control.aborted を確かめる )
+fn root( rules: &Rules, color: Color, depth: i32, exact: bool, control: &mut Control ) -> SearchResult {
+    let key = table_key( rules, color, exact );
+    let mut moves = ordered_moves( rules, color, table().probe( key ).and_then( | e | e.best ) );
+    //  補助スレッドは最初の手の後の順番をずらす
+    if control.helper > 0 && moves.len() > 2 {
+        let rest = moves.len() - 1;
+        moves[1 ..].rotate_left( control.helper % rest );
+    }
+    if moves.len() == 0 {
+        let score = negamax( rules, color, depth, -INFINITY, INFINITY, exact, control );
+        return SearchResult { position: None, score, exact };
+    }
+
+    let mut best = SearchResult { position: None, score: -INFINITY, exact };
+    let mut alpha = -INFINITY;
+    for position in moves.iter() {
+        let mut child = *rules;
+        child.set_piece( position.x, position.y, color );
+        let score = -negamax( &child, rules::get_enemy_piece( color ), depth - 1, -INFINITY, -alpha, exact, control );
+        if control.aborted {
+            return best;
+        }
+
+        if best.position.is_none() || score > best.score {
+            best.position = Some( *position );
+            best.score = score;
+        }
+        alpha = alpha.max( score );
+    }
+
+    let depth = if exact { EXACT_DEPTH } else { depth };
+    table().store( key, Entry { score: best.score, depth, bound: Bound::EXACT, best: best.position } );
+    return best;
+}
+
+//  root と同じ順に全ての手を窓を狭めずに読む ( score_moves 用 )
+fn root_scores( rules: &Rules, color: Color, depth: i32, exact: bool, control: &mut Control ) -> Vec<( Vec2<i32>, i32 )> {
+    let mut moves = ordered_moves( rules, color, None );
+    if control.helper > 0 && moves.len() > 2 {
+        let rest = moves.len() - 1;
+        moves[1 ..].rotate_left( control.helper % rest );
+    }
+
+    let mut scores = Vec::with_capacity( moves.len() );
+    for position in moves.iter() {
+        let mut child = *rules;
+        child.set_piece( position.x, position.y, color );
+        let score = -negamax( &child, rules::get_enemy_piece( color ), depth - 1, -INFINITY, INFINITY, exact, control );
+        if control.aborted {
+            break;
+        }
+        scores.push( ( *position, score ) );
+    }
+
+    //  同点なら root が先に読む手を先に
+    scores.sort_by_key( | ( _, score ) | -score );
+    return scores;
+}
+
+fn negamax( rules: &Rules, color: Color, depth: i32, mut alpha: i32, beta: i32, exact: bool, control: &mut Control ) -> i32 {
+    if control.should_stop() {
+        return 0;
+    }
+    if !exact && depth <= 0 {
+        return evaluate( rules, color );
+    }
+
+    //  同じ深さで読んだ局面は置換表の値を使う ( 上限・下限は窓の外なら枝刈り )
+    //  深く読んだ値を混ぜると前に何を読んだかで値が変わり、探索と score_moves の値が食い違う ( 手の順番には使う )
+    let key = table_key( rules, color, exact );
+    let table_depth = if exact { EXACT_DEPTH } else { depth };
+    let entry = table().probe( key );
+    if let Some( entry ) = entry.filter( | e | e.depth == table_depth ) {
+        match entry.bound {
+            Bound::EXACT => return entry.score,
+            Bound::LOWER if entry.score >= beta => return entry.score,
+            Bound::UPPER if entry.score <= alpha => return entry.score,
+            _ => {},
+        }
+    }
+
+    let moves = ordered_moves( rules, color, entry.and_then( | e | e.best ) );
+    if moves.len() == 0 {
+        let enemy = rules::get_enemy_piece( color );
+        //  両者置けなければ終局
+        if !rules.there_is_enable_position( enemy ) {
+            return final_score( rules, color, exact );
+        }
+        //  パス
+        return -negamax( rules, enemy, depth, -beta, -alpha, exact, control );
+    }
+
+    let alpha_start = alpha;
+    let mut best = -INFINITY;
+    let mut best_position = None;
+    for position in moves.iter() {
+        let mut child = *rules;
+        child.set_piece( position.x, position.y, color );
+        let score = -negamax( &child, rules::get_enemy_piece( color ), depth - 1, -beta, -alpha, exact, control );
+        //  打ち切られた値は置換表に入れない
+        if control.aborted {
+            return 0;
+        }
+
+        if score > best {
+            best = score;
+            best_position = Some( *position );
+        }
+        alpha = alpha.max( score );
+        if alpha >= beta {
+            break;
+        }
+    }
+
+    let bound = if best >= beta {
+        Bound::LOWER
+    } else if best <= alpha_start {
+        Bound::UPPER
+    } else {
+        Bound::EXACT
+    };
+    table().store( key, Entry { score: best, depth: table_depth, bound, best: best_position } );
+    return best;
+}
+
+//  置けるマスを並べる ( 置換表の最善手を先頭に、残りは重みの高い順。枝刈りが効きやすくなる )
+fn ordered_moves( rules: &Rules, color: Color, first: Option<Vec2<i32>> ) -> Vec<Vec2<i32>> {
+    let mut moves = rules.get_enable_positions( color );
+    moves.sort_by_key( | p | ( Some( *p ) != first, -WEIGHTS[p.y as usize][p.x as usize] ) );
+    return moves;
+}
+
+//  終局時の評価 ( 石差、読み切りでない探索では勝敗を優先 )
+fn final_score( rules: &Rules, color: Color, exact: bool ) -> i32 {
+    let diff = rules.get_piece_count( color ) - rules.get_piece_count( rules::get_enemy_piece( color ) );
+    if exact {
+        return diff;
+    }
+
+    if diff > 0 {
+        return WIN_SCORE + diff;
+    } else if diff < 0 {
+        return -WIN_SCORE + diff;
+    }
+
+    return 0;
+}
+
+//  局面の評価 ( マスの重み + 着手可能数 )
+pub fn evaluate( rules: &Rules, color: Color ) -> i32 {
+    let enemy = rules::get_enemy_piece( color );
+    let mut score = 0;
+
+    for k in 0 .. 8 {
+        for i in 0 .. 8 {
+            let piece = rules.get_piece( i, k );
+            if piece == color {
+                score += WEIGHTS[k as usize][i as usize];
+            } else if piece == enemy {
+                score -= WEIGHTS[k as usize][i as usize];
+            }
+        }
+    }
+
+    let mobility = rules.get_enable_positions( color ).len() as i32 - rules.get_enable_positions( enemy ).len() as i32;
+    return score + mobility * MOBILITY_WEIGHT;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::rules::Variant;
+
+    const GAME: &str = "d3c3f5f4e3f6c4e2f3c2f1d2e6d7g4d6c5e1d1c1b1b6c6b4a6c7g5g6f2b5a5a3c8d8f7a4b3a7e8h5e7f8g8a2h4h3g3h2h7g2h1h6b2h8g7a1b7a8g1b8";
+
+    //  length 手打った局面 ( 空きマスは 60 - length )
+    fn played( length: usize ) -> Rules {
+        let moves = rules::parse_transcript( GAME ).unwrap();
+        return rules::replay( Variant::STANDARD, &moves[.. length] ).unwrap().0;
+    }
+
+    #[test]
+    fn budget_follows_the_phase_of_the_game() {
+        let opening = time_budget( &played( 4 ), MAX_LEVEL, None );
+        let midgame = time_budget( &played( 30 ), MAX_LEVEL, None );
+        let solving = time_budget( &played( 47 ), MAX_LEVEL, None );
+        let solved = time_budget( &played( 56 ), MAX_LEVEL, None );
+        assert_eq!( midgame, MOVE_TIME );
+        assert!( opening < midgame && midgame < solving && solved < opening );
+
+        //  弱い AI は完全読みを始めるのが遅い
+        assert_eq!( time_budget( &played( 47 ), 3, None ), MOVE_TIME );
+        assert!( time_budget( &played( 55 ), 3, None ) > MOVE_TIME );
+
+        let clock = Some( 60000 );
+        assert!( time_budget( &played( 4 ), MAX_LEVEL, clock ) < time_budget( &played( 30 ), MAX_LEVEL, clock ) );
+    }
+
+    #[test]
+    fn budget_never_takes_half_of_the_clock() {
+        for length in 0 ..= 60 {
+            let rules = played( length );
+            for remaining in [ 0, 30, 120, 1000, 60000, 600000 ].iter() {
+                for level in 1 ..= MAX_LEVEL {
+                    let budget = time_budget( &rules, level, Some( *remaining ) );
+                    assert!( budget <= Duration::from_millis( *remaining ) / 2, "{} {} {}", length, remaining, level );
+                }
+            }
+        }
+    }
+}
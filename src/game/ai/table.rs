@@ -0,0 +1,141 @@
+use std::sync::atomic::{ AtomicU64, Ordering };
+use tetra::math::Vec2;
+
+//  置換表 ( 局面のハッシュごとに探索結果を覚えておき、同じ局面を読み直さない )
+//  1エントリはハッシュ ^ データとデータの 2つの u64。書き込みが重なって壊れたエントリはハッシュが合わなくなるので、
+//  ロックせずに複数のスレッドから共有できる
+
+const USED_BIT: u64 = 1 << 63;
+const NO_MOVE: u64 = 64;
+
+//  値の種類 ( 枝刈りされた探索では値の上限・下限しか分からない )
+#[derive(Copy, Clone, PartialEq)]
+pub enum Bound {
+    EXACT,  //  正確な値
+    LOWER,  //  これ以上 ( beta カット )
+    UPPER,  //  これ以下 ( alpha を超えなかった )
+}
+
+#[derive(Copy, Clone)]
+pub struct Entry {
+    pub score: i32,
+    pub depth: i32,                 //  読んだ深さ
+    pub bound: Bound,
+    pub best: Option<Vec2<i32>>,    //  最善手 ( 次に読むときに最初に試す )
+}
+
+impl Entry {
+    fn pack( &self ) -> u64 {
+        let bound = match self.bound {
+            Bound::EXACT => 0,
+            Bound::LOWER => 1,
+            Bound::UPPER => 2,
+        };
+        let best = self.best.map_or( NO_MOVE, | p | ( p.y * 8 + p.x ) as u64 );
+        return USED_BIT | best << 48 | bound << 40 | ( self.depth.max( 0 ).min( 255 ) as u64 ) << 32 | self.score as u32 as u64;
+    }
+
+    fn unpack( data: u64 ) -> Entry {
+        let bound = match ( data >> 40 ) & 3 {
+            1 => Bound::LOWER,
+            2 => Bound::UPPER,
+            _ => Bound::EXACT,
+        };
+        let best = ( data >> 48 ) & 0x7F;
+        return Entry {
+            score: data as u32 as i32,
+            depth: ( ( data >> 32 ) & 0xFF ) as i32,
+            bound,
+            best: if best < NO_MOVE { Some( Vec2::new( ( best % 8 ) as i32, ( best / 8 ) as i32 ) ) } else { None },
+        };
+    }
+}
+
+pub struct Table {
+    entries: Vec<[AtomicU64; 2]>,
+    mask: usize,
+}
+
+impl Table {
+    //  2 の bits 乗のエントリ
+    pub fn new( bits: u32 ) -> Table {
+        let size = 1usize << bits;
+        return Table {
+            entries: ( 0 .. size ).map( | _ | [ AtomicU64::new( 0 ), AtomicU64::new( 0 ) ] ).collect(),
+            mask: size - 1,
+        };
+    }
+
+    pub fn probe( &self, key: u64 ) -> Option<Entry> {
+        let entry = &self.entries[key as usize & self.mask];
+        let check = entry[0].load( Ordering::Relaxed );
+        let data = entry[1].load( Ordering::Relaxed );
+        if data & USED_BIT == 0 || check ^ data != key {
+            return None;
+        }
+        return Some( Entry::unpack( data ) );
+    }
+
+    //  同じ場所のエントリは上書きする
+    pub fn store( &self, key: u64, entry: Entry ) {
+        let data = entry.pack();
+        let slot = &self.entries[key as usize & self.mask];
+        slot[0].store( key ^ data, Ordering::Relaxed );
+        slot[1].store( data, Ordering::Relaxed );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same( a: Entry, b: Entry ) -> bool {
+        return a.score == b.score && a.depth == b.depth && a.bound == b.bound && a.best == b.best;
+    }
+
+    #[test]
+    fn pack_keeps_every_field() {
+        let entries = [
+            Entry { score: -10064, depth: 64, bound: Bound::EXACT, best: Some( Vec2::new( 7, 7 ) ) },
+            Entry { score: -1, depth: 1, bound: Bound::UPPER, best: None },
+            Entry { score: 1000000, depth: 0, bound: Bound::LOWER, best: Some( Vec2::new( 0, 0 ) ) },
+            Entry { score: i32::MIN, depth: 255, bound: Bound::LOWER, best: Some( Vec2::new( 5, 3 ) ) },
+        ];
+        for entry in entries.iter() {
+            let data = entry.pack();
+            assert!( data & USED_BIT != 0 );
+            assert!( same( Entry::unpack( data ), *entry ), "{:x}", data );
+        }
+
+        //  表せない深さは端に寄せる
+        let deep = Entry::unpack( Entry { score: 3, depth: 300, bound: Bound::EXACT, best: None }.pack() );
+        assert_eq!( deep.depth, 255 );
+    }
+
+    #[test]
+    fn probe_finds_only_the_stored_key() {
+        let table = Table::new( 4 );
+        let entry = Entry { score: -25, depth: 6, bound: Bound::UPPER, best: None };
+        assert!( table.probe( 0x1234 ).is_none() );
+
+        table.store( 0x1234, entry );
+        assert!( same( table.probe( 0x1234 ).unwrap(), entry ) );
+        //  同じ場所でもキーが違えば無い
+        assert!( table.probe( 0x5234 ).is_none() );
+
+        table.store( 0x5234, Entry { score: 9, ..entry } );
+        assert!( table.probe( 0x1234 ).is_none() );
+        assert_eq!( table.probe( 0x5234 ).unwrap().score, 9 );
+    }
+
+    #[test]
+    fn torn_write_is_not_returned() {
+        let table = Table::new( 4 );
+        table.store( 0x1234, Entry { score: 40, depth: 8, bound: Bound::EXACT, best: None } );
+
+        //  別のスレッドのデータだけが書き込まれた状態
+        let other = Entry { score: -40, depth: 2, bound: Bound::LOWER, best: Some( Vec2::new( 2, 3 ) ) }.pack();
+        table.entries[0x1234 & table.mask][1].store( other, Ordering::Relaxed );
+        assert!( table.probe( 0x1234 ).is_none() );
+    }
+}
@@ -0,0 +1,103 @@
+use super::super::board::cell::Color;
+use super::super::rules::Rules;
+
+//  Zobrist ハッシュ ( マスごと・色ごとの乱数の XOR で局面を 64bit にする )
+
+const CELL_NUM: usize = 64;
+
+//  乱数表 ( 毎回同じ値になるようにコンパイル時に作る )
+const KEYS: [[u64; CELL_NUM]; 2] = make_keys();
+const SIDE_KEY: u64 = mix( 0x5851F42D4C957F2D );       //  白番
+pub const EXACT_KEY: u64 = mix( 0x14057B7EF767814F );  //  完全読みの値 ( 読みの値と区別する )
+
+//  splitmix64
+const fn mix( seed: u64 ) -> u64 {
+    let mut z = seed.wrapping_add( 0x9E3779B97F4A7C15 );
+    z = ( z ^ ( z >> 30 ) ).wrapping_mul( 0xBF58476D1CE4E5B9 );
+    z = ( z ^ ( z >> 27 ) ).wrapping_mul( 0x94D049BB133111EB );
+    return z ^ ( z >> 31 );
+}
+
+const fn make_keys() -> [[u64; CELL_NUM]; 2] {
+    let mut keys = [[0; CELL_NUM]; 2];
+    let mut seed: u64 = 0;
+    let mut color = 0;
+    while color < 2 {
+        let mut i = 0;
+        while i < CELL_NUM {
+            seed = mix( seed );
+            keys[color][i] = seed;
+            i += 1;
+        }
+        color += 1;
+    }
+    return keys;
+}
+
+//  石の配置と手番のハッシュ
+pub fn hash( rules: &Rules, color: Color ) -> u64 {
+    let mut key = if color == Color::WHITE { SIDE_KEY } else { 0 };
+    for i in 0 .. CELL_NUM {
+        match rules.get_piece( ( i % 8 ) as i32, ( i / 8 ) as i32 ) {
+            Color::BLACK => key ^= KEYS[0][i],
+            Color::WHITE => key ^= KEYS[1][i],
+            Color::NONE => {},
+        }
+    }
+    return key;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::rules::{ self, Variant };
+
+    //  変わったマスの乱数だけを XOR し直す ( 探索中に差分で更新した場合のキー )
+    fn updated( key: u64, before: &Rules, after: &Rules ) -> u64 {
+        let mut key = key ^ SIDE_KEY;
+        for i in 0 .. CELL_NUM {
+            let ( x, y ) = ( ( i % 8 ) as i32, ( i / 8 ) as i32 );
+            for ( color, keys ) in [ ( Color::BLACK, &KEYS[0] ), ( Color::WHITE, &KEYS[1] ) ].iter() {
+                if ( before.get_piece( x, y ) == *color ) != ( after.get_piece( x, y ) == *color ) {
+                    key ^= keys[i];
+                }
+            }
+        }
+        return key;
+    }
+
+    #[test]
+    fn update_matches_recomputed_key() {
+        let mut board = Rules::new( Variant::STANDARD );
+        let mut color = Color::BLACK;
+        let mut key = hash( &board, color );
+
+        for position in rules::parse_transcript( "f5d6c3d3c4f4f6f3e6e7d7c6" ).unwrap().iter() {
+            let before = board;
+            board.set_piece( position.x, position.y, color ).unwrap();
+            color = rules::get_enemy_piece( color );
+
+            key = updated( key, &before, &board );
+            assert_eq!( key, hash( &board, color ) );
+        }
+    }
+
+    #[test]
+    fn side_and_exact_keys_separate_positions() {
+        let board = Rules::new( Variant::STANDARD );
+        assert_ne!( hash( &board, Color::BLACK ), hash( &board, Color::WHITE ) );
+        assert_ne!( hash( &board, Color::BLACK ), hash( &Rules::new( Variant::CROSS ), Color::BLACK ) );
+        assert_ne!( EXACT_KEY, SIDE_KEY );
+        assert_ne!( EXACT_KEY, 0 );
+    }
+
+    #[test]
+    fn keys_are_distinct() {
+        let mut keys: Vec<u64> = KEYS.iter().flat_map( | k | k.iter().copied() ).collect();
+        keys.push( SIDE_KEY );
+        keys.push( EXACT_KEY );
+        keys.sort();
+        keys.dedup();
+        assert_eq!( keys.len(), CELL_NUM * 2 + 2 );
+    }
+}
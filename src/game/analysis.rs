@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use tetra::math::Vec2;
+use super::ai;
+use super::board::cell::Color;
+use super::rules::{ self, Rules, Variant };
+
+const MISTAKE_LOSS: f32 = 4.0;      //  悪手とする損失 ( 石差 )
+const BLUNDER_LOSS: f32 = 10.0;     //  大悪手とする損失 ( 石差 )
+
+//  損失の大きい手の印
+#[derive(Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mark {
+    MISTAKE,    //  悪手 ( ? )
+    BLUNDER,    //  大悪手 ( ?? )
+}
+
+impl Mark {
+    fn from_loss( loss: f32 ) -> Option<Mark> {
+        if loss >= BLUNDER_LOSS {
+            return Some( Mark::BLUNDER );
+        } else if loss >= MISTAKE_LOSS {
+            return Some( Mark::MISTAKE );
+        }
+        return None;
+    }
+
+    //  棋譜に付ける記号
+    pub fn symbol( self ) -> &'static str {
+        match self {
+            Mark::MISTAKE => return "?",
+            Mark::BLUNDER => return "??",
+        }
+    }
+}
+
+//  1手ごとの解析結果
+#[derive(Clone, Serialize)]
+pub struct MoveAnalysis {
+    pub number: usize,          //  手数 ( 1 から )
+    pub color: &'static str,    //  "black" / "white"
+    pub played: String,         //  実際の手
+    pub best: String,           //  AI の最善手
+    pub played_score: i32,      //  実際の手の評価値 ( 手番側から見た値 )
+    pub best_score: i32,        //  最善手の評価値
+    pub exact: bool,            //  読み切りの値かどうか
+    pub loss: f32,              //  最善手との差 ( おおよその石差 )
+    pub mark: Option<Mark>,     //  悪手・大悪手
+}
+
+pub fn color_name( color: Color ) -> &'static str {
+    match color {
+        Color::NONE => return "none",
+        Color::BLACK => return "black",
+        Color::WHITE => return "white",
+    }
+}
+
+//  棋譜を最初から並べ、各局面で AI の最善手と実際の手を比べる ( cancel が立ったら途中でやめる )
+pub fn analyze( moves: &[Vec2<i32>], variant: Variant, level: u32, cancel: Option<Arc<AtomicBool>> ) -> Result<Vec<MoveAnalysis>, String> {
+    let mut rules = Rules::new( variant );
+    let mut color = Color::BLACK;
+    let mut result = Vec::with_capacity( moves.len() );
+
+    for ( i, position ) in moves.iter().enumerate() {
+        //  置けなければパス
+        if !rules.there_is_enable_position( color ) {
+            color = rules::get_enemy_piece( color );
+            if !rules.there_is_enable_position( color ) {
+                return Err( format!( "move {}: the game is already over", i + 1 ) );
+            }
+        }
+
+        //  最善手も実際の手も同じ読みの値で比べる
+        let scores = ai::score_moves( &rules, color, level, cancel.clone() ).ok_or( "analysis cancelled".to_string() )?;
+        let played_score = match scores.iter().find( | ( p, _ ) | p == position ) {
+            Some( ( _, score ) ) => *score,
+            None => return Err( format!( "move {}: illegal move {}", i + 1, rules::to_notation( *position ) ) ),
+        };
+        let exact = ai::is_endgame( &rules, level );
+
+        //  同点なら実際の手を最善手とする
+        let ( best_position, best_score ) = match scores.first() {
+            Some( ( p, score ) ) if *score > played_score => ( *p, *score ),
+            _ => ( *position, played_score ),
+        };
+
+        let loss = ai::to_discs( best_score, exact ) - ai::to_discs( played_score, exact );
+        result.push( MoveAnalysis {
+            number: i + 1,
+            color: color_name( color ),
+            played: rules::to_notation( *position ),
+            best: rules::to_notation( best_position ),
+            played_score,
+            best_score,
+            exact,
+            loss,
+            mark: Mark::from_loss( loss ),
+        } );
+
+        rules.set_piece( position.x, position.y, color );
+        color = rules::get_enemy_piece( color );
+    }
+
+    return Ok( result );
+}
+
+//  悪手の記号を付けた棋譜 ( "f5 d6 c3?? d3 ..." )
+pub fn annotate( analysis: &[MoveAnalysis] ) -> String {
+    let moves: Vec<String> = analysis.iter()
+        .map( | iter | format!( "{}{}", iter.played, iter.mark.map_or( "", | m | m.symbol() ) ) )
+        .collect();
+    return moves.join( " " );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: u32 = 3;
+
+    //  AI 同士で最後まで打った手順
+    fn self_play() -> Vec<Vec2<i32>> {
+        let mut rules = Rules::new( Variant::STANDARD );
+        let mut color = Color::BLACK;
+        let mut moves = Vec::new();
+        loop {
+            if !rules.there_is_enable_position( color ) {
+                color = rules::get_enemy_piece( color );
+                if !rules.there_is_enable_position( color ) {
+                    return moves;
+                }
+            }
+
+            let position = ai::search( &rules, color, LEVEL ).position.unwrap();
+            rules.set_piece( position.x, position.y, color );
+            moves.push( position );
+            color = rules::get_enemy_piece( color );
+        }
+    }
+
+    #[test]
+    fn engine_moves_are_not_marked() {
+        let moves = self_play();
+        let analysis = analyze( &moves, Variant::STANDARD, LEVEL, None ).unwrap();
+
+        assert_eq!( analysis.len(), moves.len() );
+        for iter in analysis.iter() {
+            assert!( iter.mark.is_none(), "move {} {}: {} < {}", iter.number, iter.played, iter.played_score, iter.best_score );
+            assert_eq!( iter.played_score, iter.best_score );
+        }
+        assert!( analysis.last().unwrap().exact );
+    }
+
+    #[test]
+    fn illegal_and_late_moves_are_errors() {
+        let moves = rules::parse_transcript( "f5f5" ).unwrap();
+        assert_eq!( analyze( &moves, Variant::STANDARD, 1, None ).err().unwrap(), "move 2: illegal move f5" );
+
+        let mut moves = self_play();
+        moves.push( moves[0] );
+        assert!( analyze( &moves, Variant::STANDARD, 1, None ).err().unwrap().ends_with( "the game is already over" ) );
+    }
+}
@@ -0,0 +1,92 @@
+use ab_glyph::{ Font as _, FontVec };
+use std::collections::HashMap;
+use std::fs;
+use tetra::graphics::Texture;
+use tetra::graphics::text::Font;
+use tetra::Context;
+
+//  テクスチャ・フォントの共有キャッシュ
+//  Texture / Font は内部で参照カウントされているので、clone したハンドルを配る
+pub struct Assets {
+    textures: HashMap<String, Texture>,     //  パス -> テクスチャ
+    fonts: HashMap<(String, u32), Font>,    //  ( パス, サイズ ) -> フォント
+    font_paths: Vec<String>,                //  テキストUIで使うフォント ( 先頭から順に使える物を探す )
+    glyphs: HashMap<String, Option<FontVec>>,   //  グリフの有無を調べる用 ( 読めなかったフォントは None )
+}
+
+impl Assets {
+    pub fn new( font_path: &str, fallback: &[String] ) -> Assets {
+        let textures = HashMap::new();
+        let fonts = HashMap::new();
+        let mut font_paths = vec![ font_path.to_string() ];
+        font_paths.extend_from_slice( fallback );
+        let glyphs = HashMap::new();
+        Assets {
+            textures,
+            fonts,
+            font_paths,
+            glyphs,
+        }
+    }
+
+    //  フォントの優先順位を変更
+    pub fn set_font_paths( &mut self, font_path: &str, fallback: &[String] ) {
+        self.font_paths.clear();
+        self.font_paths.push( font_path.to_string() );
+        self.font_paths.extend_from_slice( fallback );
+    }
+
+    //  テクスチャ取得 ( 未ロードならロード )
+    pub fn texture( &mut self, ctx: &mut Context, path: &str ) -> tetra::Result<Texture> {
+        if let Some( texture ) = self.textures.get( path ) {
+            return Ok( texture.clone() );
+        }
+
+        let texture = Texture::new( ctx, path )?;
+        self.textures.insert( path.to_string(), texture.clone() );
+        return Ok( texture );
+    }
+
+    //  フォント取得 ( パスとサイズの組み合わせごとに一度だけロード )
+    pub fn font( &mut self, ctx: &mut Context, path: &str, size: f32 ) -> tetra::Result<Font> {
+        let key = ( path.to_string(), size.to_bits() );
+        if let Some( font ) = self.fonts.get( &key ) {
+            return Ok( font.clone() );
+        }
+
+        let font = Font::vector( ctx, path, size )?;
+        self.fonts.insert( key, font.clone() );
+        return Ok( font );
+    }
+
+    //  テキストUI用フォント取得
+    pub fn text_font( &mut self, ctx: &mut Context, size: f32 ) -> tetra::Result<Font> {
+        let path = self.font_paths[0].clone();
+        return self.font( ctx, &path, size );
+    }
+
+    //  文字列をすべて表示できるフォントを優先順に探して取得
+    //  どれも足りなければ先頭のフォント ( 表示できない文字は豆腐になる )
+    pub fn text_font_for( &mut self, ctx: &mut Context, size: f32, text: &str ) -> tetra::Result<Font> {
+        for i in 0 .. self.font_paths.len() {
+            let path = self.font_paths[i].clone();
+            if self.has_glyphs( &path, text ) {
+                return self.font( ctx, &path, size );
+            }
+        }
+
+        return self.text_font( ctx, size );
+    }
+
+    //  フォントに文字列のグリフが揃っているか
+    fn has_glyphs( &mut self, path: &str, text: &str ) -> bool {
+        let font = self.glyphs.entry( path.to_string() ).or_insert_with( || {
+            fs::read( path ).ok().and_then( | data | FontVec::try_from_vec( data ).ok() )
+        } );
+
+        match font {
+            Some( font ) => text.chars().filter( | c | !c.is_whitespace() ).all( | c | font.glyph_id( c ).0 != 0 ),
+            None => false,
+        }
+    }
+}
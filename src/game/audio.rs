@@ -0,0 +1,184 @@
+use std::f32::consts::PI;
+use std::path::Path;
+use tetra::audio::{ self, Sound, SoundInstance };
+use tetra::Context;
+
+const SOUND_DIR: &str = "./asset/sound";   //  ここに同名の wav があればそちらを使う
+const MUSIC_PATH: &str = "./asset/sound/music.ogg";    //  BGM ( 同梱していないので置かれていれば再生 )
+const SAMPLE_RATE: u32 = 44100;
+
+//  効果音の種類
+#[derive(Copy, Clone)]
+pub enum Se {
+    PLACE,      //  ピースを置いた
+    ILLEGAL,    //  置けないマスをクリックした
+    PASS,       //  パス
+    GAME_END,   //  終局
+}
+
+pub struct Audio {
+    place: Sound,
+    flip: Sound,
+    illegal: Sound,
+    pass: Sound,
+    game_end: Sound,
+    music: Option<Sound>,               //  BGM ( ファイルが無ければ None )
+    music_instance: Option<SoundInstance>,
+    volume: f32,                        //  音量 ( 0.0 ~ 1.0 )
+    mute: bool,                         //  消音
+}
+
+impl Audio {
+    pub fn new( ctx: &mut Context, volume: f32, mute: bool, music: bool ) -> Audio {
+        let place = load_se( "place", || synth( 0.09, | t | decay( t, 40.0 ) * sine( t, 330.0 ) ) );
+        let flip = load_se( "flip", || synth( 0.05, | t | decay( t, 70.0 ) * sine( t, 880.0 ) ) );
+        let illegal = load_se( "illegal", || synth( 0.18, | t | 0.5 * decay( t, 10.0 ) * square( t, 110.0 ) ) );
+        let pass = load_se( "pass", || synth( 0.3, | t | {
+            let freq = if t < 0.15 { 523.3 } else { 392.0 };
+            decay( t % 0.15, 15.0 ) * sine( t, freq )
+        } ) );
+        let game_end = load_se( "game_end", || synth( 0.8, | t | {
+            let notes = [ 523.3, 659.3, 784.0, 1046.5 ];
+            let i = ( ( t / 0.15 ) as usize ).min( notes.len() - 1 );
+            decay( t - 0.15 * i as f32, 6.0 ) * sine( t, notes[i] )
+        } ) );
+        let music_sound = if Path::new( MUSIC_PATH ).exists() {
+            Sound::new( MUSIC_PATH ).ok()
+        } else {
+            None
+        };
+
+        let mut audio = Audio {
+            place,
+            flip,
+            illegal,
+            pass,
+            game_end,
+            music: music_sound,
+            music_instance: None,
+            volume,
+            mute,
+        };
+        audio.apply_volume( ctx );
+        audio.set_music( ctx, music );
+        return audio;
+    }
+
+    //  効果音再生 ( 再生できない環境では何もしない )
+    pub fn play( &self, ctx: &mut Context, se: Se ) {
+        let sound = match se {
+            Se::PLACE => &self.place,
+            Se::ILLEGAL => &self.illegal,
+            Se::PASS => &self.pass,
+            Se::GAME_END => &self.game_end,
+        };
+        let _ = sound.play( ctx );
+    }
+
+    //  裏返し音 ( 枚数が多いほど大きく高く )
+    pub fn play_flip( &self, ctx: &mut Context, count: usize ) {
+        if count == 0 {
+            return;
+        }
+
+        let count = count.min( 10 ) as f32;
+        let _ = self.flip.play_with( ctx, 0.4 + 0.06 * count, 1.0 + 0.05 * count );
+    }
+
+    pub fn get_volume( &self ) -> f32 {
+        return self.volume;
+    }
+
+    pub fn set_volume( &mut self, ctx: &mut Context, volume: f32 ) {
+        self.volume = volume.max( 0.0 ).min( 1.0 );
+        self.apply_volume( ctx );
+    }
+
+    pub fn is_mute( &self ) -> bool {
+        return self.mute;
+    }
+
+    pub fn set_mute( &mut self, ctx: &mut Context, mute: bool ) {
+        self.mute = mute;
+        self.apply_volume( ctx );
+    }
+
+    //  BGM の再生・停止
+    pub fn set_music( &mut self, ctx: &mut Context, play: bool ) {
+        if let Some( instance ) = self.music_instance.take() {
+            instance.stop();
+        }
+
+        if !play {
+            return;
+        }
+
+        if let Some( music ) = &self.music {
+            self.music_instance = music.repeat_with( ctx, 0.5, 1.0 ).ok();
+        }
+    }
+
+    fn apply_volume( &self, ctx: &mut Context ) {
+        let volume = if self.mute { 0.0 } else { self.volume };
+        audio::set_master_volume( ctx, volume );
+    }
+}
+
+//  asset/sound/<name>.wav があれば読み込み、無ければ合成した音を使う
+fn load_se<F>( name: &str, synthesize: F ) -> Sound
+where
+    F: Fn() -> Vec<u8>,
+{
+    let path = format!( "{}/{}.wav", SOUND_DIR, name );
+    if Path::new( &path ).exists() {
+        if let Ok( sound ) = Sound::new( &path ) {
+            return sound;
+        }
+    }
+
+    return Sound::from_file_data( &synthesize() );
+}
+
+fn sine( t: f32, freq: f32 ) -> f32 {
+    return ( 2.0 * PI * freq * t ).sin();
+}
+
+fn square( t: f32, freq: f32 ) -> f32 {
+    return if sine( t, freq ) >= 0.0 { 1.0 } else { -1.0 };
+}
+
+fn decay( t: f32, rate: f32 ) -> f32 {
+    return ( -rate * t.max( 0.0 ) ).exp();
+}
+
+//  波形関数 ( 時間 -> -1.0 ~ 1.0 ) から 16bit モノラルの WAV データを作る
+fn synth<F>( duration: f32, wave: F ) -> Vec<u8>
+where
+    F: Fn( f32 ) -> f32,
+{
+    let sample_num = ( duration * SAMPLE_RATE as f32 ) as u32;
+    let data_size = sample_num * 2;
+    let mut wav: Vec<u8> = Vec::with_capacity( 44 + data_size as usize );
+
+    wav.extend_from_slice( b"RIFF" );
+    wav.extend_from_slice( &( 36 + data_size ).to_le_bytes() );
+    wav.extend_from_slice( b"WAVE" );
+    wav.extend_from_slice( b"fmt " );
+    wav.extend_from_slice( &16u32.to_le_bytes() );              //  fmt チャンクサイズ
+    wav.extend_from_slice( &1u16.to_le_bytes() );               //  PCM
+    wav.extend_from_slice( &1u16.to_le_bytes() );               //  モノラル
+    wav.extend_from_slice( &SAMPLE_RATE.to_le_bytes() );
+    wav.extend_from_slice( &( SAMPLE_RATE * 2 ).to_le_bytes() );    //  バイト/秒
+    wav.extend_from_slice( &2u16.to_le_bytes() );               //  ブロックサイズ
+    wav.extend_from_slice( &16u16.to_le_bytes() );              //  ビット数
+    wav.extend_from_slice( b"data" );
+    wav.extend_from_slice( &data_size.to_le_bytes() );
+
+    for i in 0 .. sample_num {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let sample = ( wave( t ).max( -1.0 ).min( 1.0 ) * 0.8 * i16::MAX as f32 ) as i16;
+        wav.extend_from_slice( &sample.to_le_bytes() );
+    }
+
+    return wav;
+}
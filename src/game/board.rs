@@ -1,41 +1,56 @@
 pub mod cell;
+mod shape;
 
 use tetra::graphics::{ self, Color, Texture, DrawParams };
+use tetra::graphics::text::Text;
 use tetra::input::{ self, Key };
 use tetra::math::Vec2;
 use tetra::{ Context, ContextBuilder, State };
 use std::mem::MaybeUninit;
+use super::assets::Assets;
+use super::rules::{ Rules, Variant };
+use super::theme::{ Style, Theme };
 
 pub const WIDTH_NUM: usize = 8;     //  横の数
 pub const HEIGHT_NUM: usize = 8;    //  縦の数
 pub const DIR_X_MAX: usize = 8;     //  X方向の数
 pub const DIR_Y_MAX: usize = 8;     //  Y方向の数
 
-struct EnableInstallation {
-    selected_position: Vec2<i32>,       //  ピース設置座標
-    //color: cell::Color,
-    reverse_position: Vec<Vec2<i32>>,   //  反転するマス座標
-}
+const LABEL_SIZE: f32 = 0.3;        //  座標ラベルの文字サイズ ( マスの大きさに対する比率 )
 
 pub struct Board {
     texture: Texture,   //  ボード画像
+    color: Color,       //  ボードの着色
     position: Vec2<f32>,    //  座標
+    cell_size: f32,         //  マスの大きさ
     cells: [[cell::Cell; WIDTH_NUM]; HEIGHT_NUM],   //  マス
-    can_select_cells: Vec<EnableInstallation>,  //  設置可能マス
+    rules: Rules,           //  盤面 ( 置けるかどうかの判定はこちら )
+    can_select_cells: Vec<Vec2<i32>>,   //  設置可能マス
+    shape: Option<shape::Shape>,    //  図形描画 ( テーマが shape の場合のみ )
+    labels: Vec<Text>,      //  座標ラベル ( a, b, c ... 1, 2, 3 ... )
+    label_color: Color,     //  座標ラベルの色
+    flipped: bool,          //  盤面を180度回転して表示 ( 白側から見る )
+    last_reverse_count: usize,  //  直前の設置で裏返した数
 }
     
 impl Board {
-    pub fn new( ctx: &mut Context ) -> tetra::Result<Board> {
-        let texture = Texture::new( ctx, "./asset/board.png" )?;
+    pub fn new( ctx: &mut Context, assets: &mut Assets, theme: &Theme ) -> tetra::Result<Board> {
+        let texture = assets.texture( ctx, &theme.textures.board )?;
+        let color = theme.board_color();
         let position = Vec2::new( 32.0, 32.0 );
+        let cell_size = cell::WIDTH;
         let mut cells: [[MaybeUninit<cell::Cell>; WIDTH_NUM]; HEIGHT_NUM] = unsafe { MaybeUninit::uninit().assume_init() };
+        let rules = Rules::new( Variant::STANDARD );
         let can_select_cells = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
+        let shape = Board::create_shape( ctx, theme, cell_size )?;
+        let labels = Board::create_labels( ctx, assets, cell_size )?;
+        let label_color = theme.text_color();
+        let flipped = false;
+        let last_reverse_count = 0;
 
         for i in 0 .. WIDTH_NUM {
             for k in 0 .. HEIGHT_NUM {
-                let x = cell::WIDTH * i as f32 + 8.0;
-                let y = cell::HEIGHT * k as f32 + 8.0;
-                cells[i][k] = MaybeUninit::new( cell::Cell::new(ctx, x, y, cell::Color::NONE)? );
+                cells[i][k] = MaybeUninit::new( cell::Cell::new(ctx, assets, theme, 8.0, 8.0, cell::Color::NONE)? );
             }
         }
         
@@ -43,23 +58,102 @@ impl Board {
 
         Ok( Board {
             texture,
+            color,
             position,
+            cell_size,
             cells,
+            rules,
             can_select_cells,
+            shape,
+            labels,
+            label_color,
+            flipped,
+            last_reverse_count,
         } )
     }
 
-    pub fn init( &mut self, ctx: &mut Context ) {
+    fn create_shape( ctx: &mut Context, theme: &Theme, cell_size: f32 ) -> tetra::Result<Option<shape::Shape>> {
+        match theme.style {
+            Style::TEXTURE => Ok( None ),
+            Style::SHAPE => Ok( Some( shape::Shape::new( ctx, theme, cell_size )? ) ),
+        }
+    }
+
+    fn create_labels( ctx: &mut Context, assets: &mut Assets, cell_size: f32 ) -> tetra::Result<Vec<Text>> {
+        let font = assets.text_font( ctx, ( cell_size * LABEL_SIZE ).max( 8.0 ).round() )?;
+        let mut labels = Vec::with_capacity( WIDTH_NUM + HEIGHT_NUM );
+
+        for i in 0 .. WIDTH_NUM {
+            labels.push( Text::new( ( ( b'a' + i as u8 ) as char ).to_string(), font.clone() ) );
+        }
+
+        for k in 0 .. HEIGHT_NUM {
+            labels.push( Text::new( ( k + 1 ).to_string(), font.clone() ) );
+        }
+
+        return Ok( labels );
+    }
+
+    //  配置の変更 ( 図形描画の場合は新しい大きさで作り直す )
+    pub fn set_layout( &mut self, ctx: &mut Context, assets: &mut Assets, theme: &Theme, position: Vec2<f32>, cell_size: f32 ) -> tetra::Result {
+        self.position = position;
+        self.cell_size = cell_size;
+        self.shape = Board::create_shape( ctx, theme, cell_size )?;
+        self.labels = Board::create_labels( ctx, assets, cell_size )?;
+        Ok(())
+    }
+
+    //  盤面の向き
+    pub fn set_flipped( &mut self, flipped: bool ) {
+        self.flipped = flipped;
+    }
+
+    pub fn is_flipped( &self ) -> bool {
+        return self.flipped;
+    }
+
+    //  盤面のマス座標 <-> 表示上のマス座標 ( 180度回転なのでどちら向きも同じ変換 )
+    fn to_view( &self, x: usize, y: usize ) -> ( usize, usize ) {
+        if self.flipped {
+            return ( WIDTH_NUM - 1 - x, HEIGHT_NUM - 1 - y );
+        }
+
+        return ( x, y );
+    }
+
+    //  マス左上の画面座標 ( 盤面の向きを考慮 )
+    pub fn get_cell_position( &self, x: usize, y: usize ) -> Vec2<f32> {
+        let ( view_x, view_y ) = self.to_view( x, y );
+        return self.position + Vec2::new( view_x as f32, view_y as f32 ) * self.cell_size;
+    }
+
+    //  マス中心の画面座標
+    pub fn get_cell_center( &self, x: usize, y: usize ) -> Vec2<f32> {
+        return self.get_cell_position( x, y ) + Vec2::new( self.cell_size, self.cell_size ) / 2.0;
+    }
+
+    //  テーマ変更
+    pub fn set_theme( &mut self, ctx: &mut Context, assets: &mut Assets, theme: &Theme ) -> tetra::Result {
+        self.texture = assets.texture( ctx, &theme.textures.board )?;
+        self.color = theme.board_color();
+        self.shape = Board::create_shape( ctx, theme, self.cell_size )?;
+        self.labels = Board::create_labels( ctx, assets, self.cell_size )?;
+        self.label_color = theme.text_color();
+
         for i in 0 .. WIDTH_NUM {
             for k in 0 .. HEIGHT_NUM {
-                self.cells[i][k].set_piece( cell::Color::NONE );
+                self.cells[i][k].set_theme( ctx, assets, theme )?;
             }
         }
 
-        self.cells[3][3].set_piece( cell::Color::WHITE );
-        self.cells[4][4].set_piece( cell::Color::WHITE );
-        self.cells[3][4].set_piece( cell::Color::BLACK );
-        self.cells[4][3].set_piece( cell::Color::BLACK );
+        Ok(())
+    }
+
+    pub fn init( &mut self, ctx: &mut Context ) {
+        self.rules.init();
+        self.can_select_cells.clear();
+        self.last_reverse_count = 0;
+        self.sync_cells();
     }
 
     pub fn update( &mut self, ctx: &mut Context ) {
@@ -67,27 +161,74 @@ impl Board {
     }
 
     pub fn draw( &mut self, ctx: &mut Context ) {
-    self.texture.draw( ctx, self.position );
+        self.draw_labels( ctx );
+
+        if self.shape.is_some() {
+            self.shape.as_mut().unwrap().draw_board( ctx, self.position );
+
+            for i in 0 .. WIDTH_NUM {
+                for k in 0 .. HEIGHT_NUM {
+                    let center = self.get_cell_center( i, k );
+                    let color = self.cells[i][k].get_piece();
+                    let can_place = self.cells[i][k].get_can_place();
+                    self.shape.as_mut().unwrap().draw_cell( ctx, center, color, can_place );
+                }
+            }
+            return;
+        }
+
+        //  画像は基準のマスの大きさで作られているので拡大縮小して描画
+        let scale = self.cell_size / cell::WIDTH;
+        let mut param = DrawParams::new().position( self.position ).scale( Vec2::new( scale, scale ) ).color( self.color );
+        if self.flipped {
+            //  盤面画像も中心で180度回転
+            let size = Vec2::new( self.texture.width() as f32, self.texture.height() as f32 );
+            param = param.origin( size / 2.0 ).position( self.position + size * scale / 2.0 ).rotation( std::f32::consts::PI );
+        }
+        self.texture.draw( ctx, param );
 
         for i in 0 .. WIDTH_NUM {
             for k in 0 .. HEIGHT_NUM {
-                self.cells[i][k].draw( ctx, self.position );
+                let position = self.get_cell_position( i, k );
+                self.cells[i][k].draw( ctx, position, scale );
             }
         }
     }
 
-    //  ターン開始時の初期化
+    //  座標ラベル ( 上辺に列、左辺に行。盤面の向きに合わせて並びも反転 )
+    fn draw_labels( &mut self, ctx: &mut Context ) {
+        let margin = self.cell_size * LABEL_SIZE;
+
+        for i in 0 .. self.labels.len() {
+            let position = if i < WIDTH_NUM {
+                Vec2::new( self.get_cell_center( i, 0 ).x, self.position.y - margin )
+            } else {
+                Vec2::new( self.position.x - margin, self.get_cell_center( 0, i - WIDTH_NUM ).y )
+            };
+
+            let text = &mut self.labels[i];
+            let center = match text.get_bounds( ctx ) {
+                Some( rect ) => Vec2::new( rect.width / 2.0, rect.height / 2.0 ),
+                None => Vec2::zero(),
+            };
+            text.draw( ctx, DrawParams::new().position( position ).origin( center ).color( self.label_color ) );
+        }
+    }
+
+    //  ターン開始時の初期化 ( 設置可能マスを更新 )
     pub fn init_turn( &mut self, color: cell::Color ) -> bool {
-        //  TODO : 配置可能なセル
-        self.can_select_cells.clear();
+        self.can_select_cells = self.rules.get_enable_positions( color );
 
         for i in 0 .. WIDTH_NUM {
             for k in 0 .. HEIGHT_NUM {
-                let can_place = self.checkEnableInstallation( i as i32, k as i32, color );
-                self.cells[i][k].set_can_place( can_place );
+                self.cells[i][k].set_can_place( false );
             }
         }
 
+        for iter in self.can_select_cells.iter() {
+            self.cells[iter.x as usize][iter.y as usize].set_can_place( true );
+        }
+
         return true;
     }
 
@@ -100,176 +241,91 @@ impl Board {
         let mut cell_num = Vec2::new( -1, -1 );
         let target_position = position - self.position;
 
-        if target_position.x > 0.0 && target_position.x < self.texture.width() as f32 {
-            cell_num.x = target_position.x as i32 / cell::WIDTH as i32;
+        if target_position.x > 0.0 && target_position.x < self.cell_size * WIDTH_NUM as f32 {
+            cell_num.x = ( target_position.x / self.cell_size ) as i32;
         }
 
-        if target_position.y > 0.0 && target_position.y < self.texture.height() as f32 {
-            cell_num.y = target_position.y as i32 / cell::HEIGHT as i32;
+        if target_position.y > 0.0 && target_position.y < self.cell_size * HEIGHT_NUM as f32 {
+            cell_num.y = ( target_position.y / self.cell_size ) as i32;
         }
 
-        return cell_num;
-    }
-
-    //  指定したマスがいくつあるか
-    pub fn get_piece_count( &mut self, color: cell::Color ) -> i32 {
-        let mut count = 0;
-
-        for i in 0 .. WIDTH_NUM {
-            for k in 0 .. HEIGHT_NUM {
-                if color as i32 == self.cells[i][k].get_piece() as i32 {
-                    count += 1;
-                }
+        //  表示上のマス -> 盤面のマス
+        if self.flipped {
+            if cell_num.x >= 0 {
+                cell_num.x = WIDTH_NUM as i32 - 1 - cell_num.x;
+            }
+            if cell_num.y >= 0 {
+                cell_num.y = HEIGHT_NUM as i32 - 1 - cell_num.y;
             }
         }
 
-        return count;
+        return cell_num;
     }
 
-    fn get_enable_installation( &mut self, x: i32, y: i32 ) -> Option<&EnableInstallation> {
-        for iter in self.can_select_cells.iter() {
-            if iter.selected_position.x == x && iter.selected_position.y == y {
-                return Some( iter );
-            }
-        }
-
-        return None;
+    //  指定したマスがいくつあるか
+    pub fn get_piece_count( &mut self, color: cell::Color ) -> i32 {
+        return self.rules.get_piece_count( color );
     }
 
-    //  指定マスに置けるか
+    //  指定マスに置けるか ( init_turn で調べた設置可能マスから探す )
     pub fn can_piece( &mut self, x: i32, y: i32, self_piece: cell::Color ) -> bool {
-        if !self.is_inside( x, y ) {
-            return false;
-        }
-
-        if !self.is_open( x, y ) {
+        if !Rules::is_inside( x, y ) {
             return false;
         }
 
-        match self.get_enable_installation( x, y ) {
-            Some( enable_installation ) => {
-                return true;
-            },
-            None => {
-                return false;
-            }
-        }
+        return self.can_select_cells.iter().any( | iter | iter.x == x && iter.y == y )
+            && self.rules.can_piece( x, y, self_piece );
     }
 
     //  指定したマスに設置
     pub fn set_piece( &mut self, x: i32, y: i32, self_piece: cell::Color ) -> bool {
-        if self.can_piece( x, y, self_piece) {
-            match self.get_enable_installation( x, y ) {
-                Some( enable_installation ) => {
-                    let mut reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
-                    for iter in enable_installation.reverse_position.iter() {
-                        reverse_position.push( *iter );
-                    }
-
-                    for iter in reverse_position.iter() {
-                        self.cells[iter.x as usize][iter.y as usize].set_piece( self_piece );
-                    }
-
-                    self.cells[x as usize][y as usize].set_piece( self_piece );
-                    return true;
-                },
-                None => {
-                    return false;
-                }
-            }
-        }
-        
-        return false;
-    }
-
-    //  指定したマスから一番近い自分のマスをチェック
-    fn check_near_my_piece( &mut self, x: i32, y: i32, dir_x: i32, dir_y: i32, self_piece: cell::Color ) -> Vec<Vec2<i32>> {
-        let mut reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM );
-        let mut dir = 1;
-
-        while self.is_inside( x + dir_x * dir, y + dir_y * dir ) {
-            let offset_x = x + dir_x * dir;
-            let offset_y = y + dir_y * dir;
-            let offset_piece = self.cells[offset_x as usize][offset_y as usize].get_piece();
-
-            if offset_piece as i32 == cell::Color::NONE as i32 {
-                break;
-            }
-
-            if offset_piece as i32 == self_piece as i32 {
-                return reverse_position;
-            }
-
-            //  裏返せるピース座標
-            reverse_position.push( Vec2::new( offset_x, offset_y ) );
-            dir += 1;
-        }
-
-        reverse_position.clear();
-        return reverse_position;
-    }
-
-    //  指定したマスに設置可能かチェック
-    fn checkEnableInstallation( &mut self, x: i32, y: i32, self_piece: cell::Color ) -> bool {
-        if !self.is_inside( x, y ) {
+        if !self.can_piece( x, y, self_piece ) {
             return false;
         }
 
-        if !self.is_open( x, y ) {
-            return false;
+        match self.rules.set_piece( x, y, self_piece ) {
+            Some( count ) => {
+                self.last_reverse_count = count;
+                self.sync_cells();
+                return true;
+            },
+            None => return false,
         }
+    }
 
-        let dir_x = [ 0, 1, 1, 1, 0, -1, -1, -1 ];
-        let dir_y = [ -1, -1, 0, 1, 1, 1, 0, -1 ];
-        let reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
-        let selected_position = Vec2::new( -1, -1 );
-        //self.can_select_cells.push( EnableInstallation{ selected_position, reverse_position } );
-
-        let mut reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
-
-        for i in 0 .. dir_x.len() {
-            for it in self.check_near_my_piece( x, y, dir_x[i], dir_y[i], self_piece ).iter() {
-                reverse_position.push( *it );
+    //  指定した局面を表示 ( 振り返り用。設置可能マスは出さない )
+    pub fn show_position( &mut self, rules: &Rules ) {
+        self.rules = *rules;
+        self.can_select_cells.clear();
+        for i in 0 .. WIDTH_NUM {
+            for k in 0 .. HEIGHT_NUM {
+                self.cells[i][k].set_can_place( false );
             }
         }
-
-        if reverse_position.len() > 0 {
-            let selected_position = Vec2::new( x, y );
-            self.can_select_cells.push( EnableInstallation{ selected_position, reverse_position } );
-            return true;
-        }
-
-        return false;
+        self.sync_cells();
     }
 
-    //  指定したマスが内側かどうか
-    fn is_inside( &mut self, x: i32, y: i32 ) -> bool {
-        if x < 0 || y < 0 {
-            return false;
-        }
-
-        if x as usize >= WIDTH_NUM || y as usize >= HEIGHT_NUM {
-            return false;
-        }
+    //  直前の設置で裏返した数
+    pub fn get_last_reverse_count( &self ) -> usize {
+        return self.last_reverse_count;
+    }
 
-        return true;
+    //  初期配置の種類 ( 次の init から反映 )
+    pub fn set_variant( &mut self, variant: Variant ) {
+        self.rules.set_variant( variant );
     }
 
-    //  指定したマスが空かどうか
-    fn is_open( &mut self, x: i32, y: i32 ) -> bool {
-        match self.cells[x as usize][y as usize].get_piece() {
-            cell::Color::NONE => return true,
-            cell::Color::BLACK => return false,
-            cell::Color::WHITE => return false,
-        }
+    //  盤面 ( ルール側 )
+    pub fn get_rules( &self ) -> &Rules {
+        return &self.rules;
     }
 
-    //  相手のピース取得
-    fn get_enemy_piece( &mut self, self_piece: cell::Color ) -> cell::Color {
-        match self_piece {
-            cell::Color::NONE => return cell::Color::NONE,
-            cell::Color::BLACK => return cell::Color::WHITE,
-            cell::Color::WHITE => return cell::Color::BLACK,
+    //  ルール側の盤面を表示用のマスに反映
+    fn sync_cells( &mut self ) {
+        for i in 0 .. WIDTH_NUM {
+            for k in 0 .. HEIGHT_NUM {
+                self.cells[i][k].set_piece( self.rules.get_piece( i as i32, k as i32 ) );
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,12 +1,14 @@
-use tetra::graphics::{ self, Texture, DrawParams };
+use tetra::graphics::{ self, Color as DrawColor, Texture, DrawParams };
 use tetra::input::{ self, Key };
 use tetra::math::Vec2;
 use tetra::{ Context, ContextBuilder, State };
+use crate::game::assets::Assets;
+use crate::game::theme::Theme;
 
 pub const WIDTH: f32 = 80.0;
 pub const HEIGHT: f32 = 80.0;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum Color {
     NONE,
     BLACK,
@@ -18,17 +20,23 @@ pub struct Cell {
     texture_black: Texture,
     texture_white: Texture,
     texture_can_place: Texture,
+    color_black: DrawColor,     //  黒ピースの着色
+    color_white: DrawColor,     //  白ピースの着色
+    color_can_place: DrawColor, //  設置可能マスの着色
     canPlace: bool,
     //grid_x_num: i32,
     //grid_y_num: i32,
-    position: Vec2<f32>,
+    position: Vec2<f32>,    //  マス内での描画位置
 }
     
 impl Cell {
-    pub fn new( ctx: &mut Context, x: f32, y: f32, color: Color ) -> tetra::Result<Cell> {
-        let texture_black = Texture::new( ctx, "./asset/piece_black.png" )?;
-        let texture_white = Texture::new( ctx, "./asset/piece_white.png" )?;
-        let texture_can_place = Texture::new( ctx, "./asset/can_place_cell.png" )?;
+    pub fn new( ctx: &mut Context, assets: &mut Assets, theme: &Theme, x: f32, y: f32, color: Color ) -> tetra::Result<Cell> {
+        let texture_black = assets.texture( ctx, &theme.textures.piece_black )?;
+        let texture_white = assets.texture( ctx, &theme.textures.piece_white )?;
+        let texture_can_place = assets.texture( ctx, &theme.textures.can_place )?;
+        let color_black = theme.piece_black_color();
+        let color_white = theme.piece_white_color();
+        let color_can_place = theme.can_place_color();
         let canPlace = false;
         //let grid_x_num = grid_x;
         //let grid_y_num = grid_y;
@@ -38,6 +46,9 @@ impl Cell {
             texture_black,
             texture_white,
             texture_can_place,
+            color_black,
+            color_white,
+            color_can_place,
             canPlace,
             //grid_x_num,
             //grid_y_num,
@@ -45,6 +56,17 @@ impl Cell {
         } )
     }
 
+    //  テーマ変更
+    pub fn set_theme( &mut self, ctx: &mut Context, assets: &mut Assets, theme: &Theme ) -> tetra::Result {
+        self.texture_black = assets.texture( ctx, &theme.textures.piece_black )?;
+        self.texture_white = assets.texture( ctx, &theme.textures.piece_white )?;
+        self.texture_can_place = assets.texture( ctx, &theme.textures.can_place )?;
+        self.color_black = theme.piece_black_color();
+        self.color_white = theme.piece_white_color();
+        self.color_can_place = theme.can_place_color();
+        Ok(())
+    }
+
     pub fn init(&mut self) {
 
     }
@@ -53,15 +75,19 @@ impl Cell {
         
     }
 
-    pub fn draw( &mut self, ctx: &mut Context, oriin: Vec2<f32> ) {
+    //  oriin はマス左上の画面座標
+    pub fn draw( &mut self, ctx: &mut Context, oriin: Vec2<f32>, scale: f32 ) {
+        let param = DrawParams::new()
+            .position( self.position * scale + oriin )
+            .scale( Vec2::new( scale, scale ) );
         match self.color {
             Color::NONE => {
                 if self.canPlace {
-                    self.texture_can_place.draw( ctx, self.position + oriin );
+                    self.texture_can_place.draw( ctx, param.color( self.color_can_place ) );
                 }
             },
-            Color::BLACK => self.texture_black.draw(ctx, self.position + oriin),
-            Color::WHITE => self.texture_white.draw(ctx, self.position + oriin),
+            Color::BLACK => self.texture_black.draw( ctx, param.color( self.color_black ) ),
+            Color::WHITE => self.texture_white.draw( ctx, param.color( self.color_white ) ),
         }
     }
 
@@ -73,6 +99,10 @@ impl Cell {
         self.color = pieceColor;
     }
 
+    pub fn get_can_place( &mut self ) -> bool {
+        return self.canPlace;
+    }
+
     pub fn set_can_place( &mut self, can_place: bool ) {
         self.canPlace = can_place;
     }
@@ -0,0 +1,101 @@
+use tetra::graphics::mesh::{ GeometryBuilder, Mesh, ShapeStyle };
+use tetra::graphics::{ Color, Rectangle };
+use tetra::math::Vec2;
+use tetra::Context;
+use crate::game::theme::{ self, Theme };
+use super::{ cell, WIDTH_NUM, HEIGHT_NUM };
+
+const LINE_WIDTH: f32 = 0.025;      //  線の太さ ( マスの大きさに対する比率 )
+const STAR_RADIUS: f32 = 0.07;      //  星の半径
+const PIECE_RADIUS: f32 = 0.42;     //  ピースの半径
+const MARKER_RADIUS: f32 = 0.12;    //  設置可能マーカーの半径
+
+//  画像を使わずに図形でボードを描画する
+//  マスの大きさから毎回メッシュを作り直すので、どの解像度・盤面サイズでも崩れない
+pub struct Shape {
+    board: Mesh,                //  盤面 ( 背景・罫線・星 )
+    piece_black: Mesh,          //  黒ピース
+    piece_white: Mesh,          //  白ピース
+    can_place: Mesh,            //  設置可能マーカー
+}
+
+impl Shape {
+    pub fn new( ctx: &mut Context, theme: &Theme, cell_size: f32 ) -> tetra::Result<Shape> {
+        let colors = &theme.shape;
+        let width = cell_size * WIDTH_NUM as f32;
+        let height = cell_size * HEIGHT_NUM as f32;
+        let line_width = ( cell_size * LINE_WIDTH ).max( 1.0 );
+
+        //  盤面
+        let mut builder = GeometryBuilder::new();
+        builder.set_color( theme::parse_color( &colors.board ) );
+        builder.rectangle( ShapeStyle::Fill, Rectangle::new( 0.0, 0.0, width, height ) )?;
+        builder.set_color( theme::parse_color( &colors.line ) );
+
+        for i in 0 ..= WIDTH_NUM {
+            let x = cell_size * i as f32 - line_width / 2.0;
+            builder.rectangle( ShapeStyle::Fill, Rectangle::new( x, 0.0, line_width, height ) )?;
+        }
+
+        for k in 0 ..= HEIGHT_NUM {
+            let y = cell_size * k as f32 - line_width / 2.0;
+            builder.rectangle( ShapeStyle::Fill, Rectangle::new( 0.0, y, width, line_width ) )?;
+        }
+
+        //  星 ( 4分割した位置の交点、割り切れない盤面サイズでは描かない )
+        if WIDTH_NUM % 4 == 0 && HEIGHT_NUM % 4 == 0 {
+            for &sx in [ WIDTH_NUM / 4, WIDTH_NUM * 3 / 4 ].iter() {
+                for &sy in [ HEIGHT_NUM / 4, HEIGHT_NUM * 3 / 4 ].iter() {
+                    let center = Vec2::new( sx as f32, sy as f32 ) * cell_size;
+                    builder.circle( ShapeStyle::Fill, center, cell_size * STAR_RADIUS )?;
+                }
+            }
+        }
+
+        let board = builder.build_mesh( ctx )?;
+
+        //  ピース ( 原点中心 )
+        let outline = theme::parse_color( &colors.outline );
+        let piece_black = Shape::build_piece( ctx, theme::parse_color( &colors.piece_black ), outline, cell_size, line_width )?;
+        let piece_white = Shape::build_piece( ctx, theme::parse_color( &colors.piece_white ), outline, cell_size, line_width )?;
+
+        let mut builder = GeometryBuilder::new();
+        builder.set_color( theme::parse_color( &colors.can_place ) );
+        builder.circle( ShapeStyle::Fill, Vec2::zero(), cell_size * MARKER_RADIUS )?;
+        let can_place = builder.build_mesh( ctx )?;
+
+        Ok( Shape {
+            board,
+            piece_black,
+            piece_white,
+            can_place,
+        } )
+    }
+
+    fn build_piece( ctx: &mut Context, color: Color, outline: Color, cell_size: f32, line_width: f32 ) -> tetra::Result<Mesh> {
+        let mut builder = GeometryBuilder::new();
+        builder.set_color( color );
+        builder.circle( ShapeStyle::Fill, Vec2::zero(), cell_size * PIECE_RADIUS )?;
+        builder.set_color( outline );
+        builder.circle( ShapeStyle::Stroke( line_width ), Vec2::zero(), cell_size * PIECE_RADIUS )?;
+        return builder.build_mesh( ctx );
+    }
+
+    //  盤面 ( 星は点対称なので盤面の向きに関係なくそのまま描ける )
+    pub fn draw_board( &mut self, ctx: &mut Context, origin: Vec2<f32> ) {
+        self.board.draw( ctx, origin );
+    }
+
+    //  マスの中身 ( center はマスの中心の画面座標 )
+    pub fn draw_cell( &mut self, ctx: &mut Context, center: Vec2<f32>, color: cell::Color, can_place: bool ) {
+        match color {
+            cell::Color::NONE => {
+                if can_place {
+                    self.can_place.draw( ctx, center );
+                }
+            },
+            cell::Color::BLACK => self.piece_black.draw( ctx, center ),
+            cell::Color::WHITE => self.piece_white.draw( ctx, center ),
+        }
+    }
+}
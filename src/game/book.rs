@@ -0,0 +1,528 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver, Sender };
+use std::thread::{ self, JoinHandle };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use tetra::math::Vec2;
+use super::ai;
+use super::board::cell::Color;
+use super::rules::{ self, Variant };
+
+pub const BOOK_PATH: &str = "./asset/book/openings.txt";
+pub const LEARNED_PATH: &str = "./learned_book.txt";   //  対局から覚えた手順 ( 学習するたびに保存 )
+
+//  組み込みの定石 ( ファイルが無くても使えるように埋め込む )
+const BUILTIN: &str = include_str!( "../../asset/book/openings.txt" );
+
+//  定石の手順は初手 f5 にそろえて書く
+const FIRST_MOVE: Vec2<i32> = Vec2 { x: 5, y: 4 };
+const SYMMETRY_NUM: usize = 4;
+const LEARN_EMPTIES: i32 = 14;      //  覚える対局はこの空きマス数の局面を完全読みした値を葉にする
+const LEARNED_DEPTH: usize = 20;    //  覚えた手順を定石として使う手数
+
+//  名前の付いた手順 1つ
+pub struct Line {
+    pub moves: Vec<Vec2<i32>>,
+    pub name: String,
+    black_wins: u32,    //  覚えた対局の勝敗 ( 定石のファイルには書かない )
+    draws: u32,
+    white_wins: u32,
+}
+
+impl Line {
+    //  color の側がこの手順を選ぶ重み ( 覚えた対局で勝ちが多いほど選びやすく、記録が無くても選ばれる )
+    fn weight( &self, color: Color ) -> u32 {
+        let wins = if color == Color::BLACK { self.black_wins } else { self.white_wins };
+        return 1 + wins * 2 + self.draws;
+    }
+}
+
+//  対局から覚えた手順 ( 初手を f5 にそろえ、完全読みした局面まで )
+struct Learned {
+    moves: Vec<Vec2<i32>>,
+    colors: Vec<Color>,     //  各手を打った側 ( パスがあるので手順だけでは決まらない )
+    score: i32,             //  最後の局面を完全読みした石差 ( 黒から見た値 )
+}
+
+//  定石 ( 標準の初期配置のみ )
+//  覚えた手順は木として negamax でたどり、負けと分かった手は他に手があれば選ばない
+pub struct Book {
+    lines: Vec<Line>,
+    learned: Vec<Learned>,
+    seed: u64,      //  手を選ぶ乱数
+}
+
+impl Book {
+    //  asset/book/openings.txt があればそちらを、無ければ組み込みの定石を使う
+    pub fn load() -> Book {
+        let lines = match fs::read_to_string( BOOK_PATH ) {
+            Ok( source ) => Book::parse( &source ).unwrap_or_else( | e | {
+                eprintln!( "book {}: {}", BOOK_PATH, e );
+                Book::parse( BUILTIN ).expect( "built-in book is broken" )
+            } ),
+            Err( _ ) => Book::parse( BUILTIN ).expect( "built-in book is broken" ),
+        };
+
+        let seed = SystemTime::now().duration_since( UNIX_EPOCH ).map( | d | d.as_nanos() as u64 ).unwrap_or( 0 ) | 1;
+        let mut book = Book { lines, learned: Vec::new(), seed };
+
+        //  覚えた手順は名前付きの手順の勝敗にも数える
+        if let Ok( source ) = fs::read_to_string( LEARNED_PATH ) {
+            for ( i, text ) in source.lines().enumerate() {
+                let text = text.split( '#' ).next().unwrap_or( "" ).trim();
+                if text.is_empty() {
+                    continue;
+                }
+
+                match parse_learned( text ) {
+                    Ok( learned ) => book.add_learned( learned ),
+                    Err( e ) => eprintln!( "book {}: line {}: {}", LEARNED_PATH, i + 1, e ),
+                }
+            }
+        }
+
+        return book;
+    }
+
+    //  覚えた手順を保存
+    pub fn save( &self ) -> io::Result<()> {
+        let mut source = String::from( "# 対局から覚えた手順 ( 手順  最後の局面を完全読みした黒から見た石差 )\n" );
+        for learned in self.learned.iter() {
+            source += &format!( "{} {:+}\n", rules::to_transcript( &learned.moves ), learned.score );
+        }
+        return fs::write( LEARNED_PATH, source );
+    }
+
+    //  終わった対局を覚える ( 完全読みの石差を返す )
+    pub fn learn( &mut self, variant: Variant, moves: &[Vec2<i32>] ) -> Result<i32, String> {
+        let ( moves, score ) = solve_game( variant, moves, None )?;
+        self.add_solved( moves, score );
+        return Ok( score );
+    }
+
+    //  solve_game の結果を覚える
+    pub fn add_solved( &mut self, moves: Vec<Vec2<i32>>, score: i32 ) {
+        match self.learned.iter_mut().find( | l | l.moves == moves ) {
+            Some( learned ) => learned.score = score,
+            None => self.add_learned( Learned { colors: move_colors( &moves ), moves, score } ),
+        }
+    }
+
+    fn add_learned( &mut self, learned: Learned ) {
+        for line in self.lines.iter_mut() {
+            if !learned.moves.starts_with( &line.moves ) {
+                continue;
+            }
+            match learned.score {
+                s if s > 0 => line.black_wins += 1,
+                s if s < 0 => line.white_wins += 1,
+                _ => line.draws += 1,
+            }
+        }
+        self.learned.push( learned );
+    }
+
+    //  覚えた手順で prefix の次に打たれた手と、その先を negamax した値 ( 黒から見た石差 )
+    fn learned_children( &self, prefix: &[Vec2<i32>] ) -> Vec<( Vec2<i32>, i32 )> {
+        let lines: Vec<&Learned> = self.learned.iter()
+            .filter( | l | l.moves.len() > prefix.len() && l.moves.starts_with( prefix ) )
+            .collect();
+        return group_children( &lines, prefix.len() ).into_iter()
+            .map( | ( next, child ) | ( next, negamax( &child, prefix.len() + 1 ) ) )
+            .collect();
+    }
+
+    //  1行に「手順 名前」 ( # 以降はコメント )
+    pub fn parse( source: &str ) -> Result<Vec<Line>, String> {
+        let mut lines = Vec::new();
+        for ( i, text ) in source.lines().enumerate() {
+            let text = text.split( '#' ).next().unwrap_or( "" ).trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let line = parse_line( text ).map_err( | e | format!( "line {}: {}", i + 1, e ) )?;
+            lines.push( line );
+        }
+        return Ok( lines );
+    }
+
+    //  定石の中にいれば、これまでの手順に含まれる一番長い名前付きの手順の名前
+    pub fn get_name( &self, variant: Variant, moves: &[Vec2<i32>] ) -> Option<&str> {
+        let moves = canonical( variant, moves )?;
+        if moves.is_empty() || !self.lines.iter().any( | line | line.moves.starts_with( &moves ) ) {
+            return None;
+        }
+
+        return self.lines.iter()
+            .filter( | line | moves.starts_with( &line.moves ) )
+            .max_by_key( | line | line.moves.len() )
+            .map( | line | line.name.as_str() );
+    }
+
+    //  定石の次の手 ( 候補が複数あれば勝敗の記録で重みを付けてランダムに選ぶ。定石を外れていれば None )
+    pub fn choose( &mut self, variant: Variant, moves: &[Vec2<i32>] ) -> Option<Vec2<i32>> {
+        let canonical_moves = canonical( variant, moves )?;
+        //  パスがあると手数の偶奇では手番が決まらない
+        let ( _, color ) = rules::replay( variant, moves )?;
+
+        let mut candidates: Vec<( Vec2<i32>, u32 )> = Vec::new();
+        for line in self.lines.iter() {
+            if line.moves.len() <= canonical_moves.len() || !line.moves.starts_with( &canonical_moves ) {
+                continue;
+            }
+            let next = line.moves[canonical_moves.len()];
+            match candidates.iter_mut().find( | ( position, _ ) | *position == next ) {
+                Some( candidate ) => candidate.1 += line.weight( color ),
+                None => candidates.push( ( next, line.weight( color ) ) ),
+            }
+        }
+
+        //  覚えた手も候補に入れ、負けと分かった手は他に負けと分かっていない手があれば外す ( 全て負けなら一番ましな手 )
+        let learned = if canonical_moves.len() < LEARNED_DEPTH { self.learned_children( &canonical_moves ) } else { Vec::new() };
+        for ( next, _ ) in learned.iter() {
+            if !candidates.iter().any( | ( position, _ ) | position == next ) {
+                candidates.push( ( *next, 1 ) );
+            }
+        }
+        let value = | position: &Vec2<i32> | learned.iter().find( | ( next, _ ) | next == position )
+            .map( | ( _, score ) | if color == Color::BLACK { *score } else { -*score } );
+        if candidates.iter().any( | ( position, _ ) | value( position ).map_or( true, | v | v >= 0 ) ) {
+            candidates.retain( | ( position, _ ) | value( position ).map_or( true, | v | v >= 0 ) );
+        } else if let Some( best ) = candidates.iter().filter_map( | ( position, _ ) | value( position ) ).max() {
+            candidates.retain( | ( position, _ ) | value( position ) == Some( best ) );
+        }
+
+        let total: u32 = candidates.iter().map( | ( _, weight ) | weight ).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = ( self.next_random() % total as u64 ) as u32;
+        let mut next = candidates[0].0;
+        for ( position, weight ) in candidates.iter() {
+            if target < *weight {
+                next = *position;
+                break;
+            }
+            target -= weight;
+        }
+
+        //  初手は 4通りの対称な手からも選ぶ
+        let symmetry = match moves.first() {
+            Some( first ) => symmetry_of( *first )?,
+            None => ( self.next_random() % SYMMETRY_NUM as u64 ) as usize,
+        };
+        return Some( transform( next, symmetry ) );
+    }
+
+    //  xorshift
+    fn next_random( &mut self ) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        return self.seed;
+    }
+}
+
+//  「手順 石差」
+fn parse_learned( text: &str ) -> Result<Learned, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() != 2 {
+        return Err( format!( "expected moves and score: {}", text ) );
+    }
+
+    let moves = rules::parse_transcript( words[0] ).ok_or( format!( "invalid moves: {}", words[0] ) )?;
+    if moves.first() != Some( &FIRST_MOVE ) || rules::replay( Variant::STANDARD, &moves ).is_none() {
+        return Err( format!( "moves must start with f5 and be legal: {}", words[0] ) );
+    }
+
+    let score = words[1].parse::<i32>().map_err( | _ | format!( "invalid score: {}", words[1] ) )?;
+    return Ok( Learned { colors: move_colors( &moves ), moves, score } );
+}
+
+//  各手を打った側 ( 置けない側はパス )
+fn move_colors( moves: &[Vec2<i32>] ) -> Vec<Color> {
+    let mut rules = rules::Rules::new( Variant::STANDARD );
+    let mut color = Color::BLACK;
+    let mut colors = Vec::with_capacity( moves.len() );
+    for position in moves.iter() {
+        if !rules.there_is_enable_position( color ) {
+            color = rules::get_enemy_piece( color );
+        }
+        rules.set_piece( position.x, position.y, color );
+        colors.push( color );
+        color = rules::get_enemy_piece( color );
+    }
+    return colors;
+}
+
+//  終わった対局の葉にする局面を完全読みする ( 空きマスが LEARN_EMPTIES になった局面か終局。時間がかかるので別スレッドでも呼べる )
+//  正規化した手順と黒から見た石差を返す
+pub fn solve_game( variant: Variant, moves: &[Vec2<i32>], cancel: Option<Arc<AtomicBool>> ) -> Result<( Vec<Vec2<i32>>, i32 ), String> {
+    let moves = canonical( variant, moves ).ok_or( "only games from the standard start are learned".to_string() )?;
+    rules::replay( variant, &moves ).ok_or( "illegal moves".to_string() )?;
+
+    let is_leaf = | ( rules, _ ): &( rules::Rules, Color ) | {
+        rules.get_piece_count( Color::NONE ) <= LEARN_EMPTIES
+            || ( !rules.there_is_enable_position( Color::BLACK ) && !rules.there_is_enable_position( Color::WHITE ) )
+    };
+    let length = ( 0 ..= moves.len() ).find( | i | rules::replay( variant, &moves[.. *i] ).map_or( false, | r | is_leaf( &r ) ) )
+        .ok_or( "the game ended before the endgame".to_string() )?;
+
+    let ( rules, color ) = rules::replay( variant, &moves[.. length] ).unwrap();
+    let result = ai::solve_cancellable( &rules, color, cancel ).ok_or( "cancelled".to_string() )?;
+    let score = if color == Color::BLACK { result.score } else { -result.score };
+    return Ok( ( moves[.. length].to_vec(), score ) );
+}
+
+//  終わった対局を 1局ずつ順に完全読みするスレッド ( drop すると読みかけを打ち切って終わるのを待つ )
+pub struct Learner {
+    games: Option<Sender<( Variant, Vec<Vec2<i32>> )>>,
+    results: Receiver<Result<( Vec<Vec2<i32>>, i32 ), String>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Learner {
+    pub fn start() -> Learner {
+        let ( games, queue ) = mpsc::channel::<( Variant, Vec<Vec2<i32>> )>();
+        let ( sender, results ) = mpsc::channel();
+        let cancel = Arc::new( AtomicBool::new( false ) );
+        let stop = cancel.clone();
+        let worker = thread::spawn( move || {
+            for ( variant, moves ) in queue.iter() {
+                if stop.load( Ordering::Relaxed ) || sender.send( solve_game( variant, &moves, Some( stop.clone() ) ) ).is_err() {
+                    break;
+                }
+            }
+        } );
+
+        return Learner { games: Some( games ), results, cancel, worker: Some( worker ) };
+    }
+
+    //  読む対局を順番待ちに入れる
+    pub fn push( &self, variant: Variant, moves: &[Vec2<i32>] ) {
+        if let Some( games ) = self.games.as_ref() {
+            let _ = games.send( ( variant, moves.to_vec() ) );
+        }
+    }
+
+    //  読み終わった対局があれば 1つ取り出す
+    pub fn poll( &self ) -> Option<Result<( Vec<Vec2<i32>>, i32 ), String>> {
+        return self.results.try_recv().ok();
+    }
+}
+
+impl Drop for Learner {
+    fn drop( &mut self ) {
+        self.cancel.store( true, Ordering::Relaxed );
+        self.games = None;
+        if let Some( worker ) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+//  depth 手目 ( 0から ) で分けた手順 ( lines はどれも depth より長い )
+fn group_children<'a>( lines: &[&'a Learned], depth: usize ) -> Vec<( Vec2<i32>, Vec<&'a Learned> )> {
+    let mut children: Vec<( Vec2<i32>, Vec<&'a Learned> )> = Vec::new();
+    for line in lines.iter() {
+        let next = line.moves[depth];
+        match children.iter_mut().find( | ( position, _ ) | *position == next ) {
+            Some( child ) => child.1.push( line ),
+            None => children.push( ( next, vec![ line ] ) ),
+        }
+    }
+    return children;
+}
+
+//  depth 手目まで同じ手順の木の値 ( 黒から見た石差。手番側に一番良い子の値 )
+fn negamax( lines: &[&Learned], depth: usize ) -> i32 {
+    //  葉 ( 完全読みした局面 ) は空きマス数で決まるので、同じ手順なら同じ所で終わる
+    if let Some( leaf ) = lines.iter().find( | l | l.moves.len() <= depth ) {
+        return leaf.score;
+    }
+
+    let color = lines[0].colors[depth];
+    let values = group_children( lines, depth ).into_iter().map( | ( _, child ) | negamax( &child, depth + 1 ) );
+    let value = if color == Color::BLACK { values.max() } else { values.min() };
+    return value.unwrap_or( 0 );
+}
+
+fn parse_line( text: &str ) -> Result<Line, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() < 2 {
+        return Err( format!( "expected moves and name: {}", text ) );
+    }
+
+    let moves = rules::parse_transcript( words[0] ).ok_or( format!( "invalid moves: {}", words[0] ) )?;
+    if moves.first() != Some( &FIRST_MOVE ) || rules::replay( Variant::STANDARD, &moves ).is_none() {
+        return Err( format!( "moves must start with f5 and be legal: {}", words[0] ) );
+    }
+
+    return Ok( Line {
+        moves,
+        name: words[1 ..].join( " " ),
+        black_wins: 0,
+        draws: 0,
+        white_wins: 0,
+    } );
+}
+
+//  初手を f5 にそろえた手順 ( 標準の初期配置でなければ None )
+fn canonical( variant: Variant, moves: &[Vec2<i32>] ) -> Option<Vec<Vec2<i32>>> {
+    if variant != Variant::STANDARD {
+        return None;
+    }
+
+    let symmetry = match moves.first() {
+        Some( first ) => symmetry_of( *first )?,
+        None => 0,
+    };
+    return Some( moves.iter().map( | p | transform( *p, symmetry ) ).collect() );
+}
+
+//  初手を f5 に移す対称変換
+fn symmetry_of( first: Vec2<i32> ) -> Option<usize> {
+    return ( 0 .. SYMMETRY_NUM ).find( | s | transform( first, *s ) == FIRST_MOVE );
+}
+
+//  初期配置を変えない対称変換 ( 0: そのまま、1: 対角線で反転、2: 180度回転、3: 逆の対角線で反転。どれも 2回で元に戻る )
+fn transform( position: Vec2<i32>, symmetry: usize ) -> Vec2<i32> {
+    match symmetry {
+        1 => return Vec2::new( position.y, position.x ),
+        2 => return Vec2::new( 7 - position.x, 7 - position.y ),
+        3 => return Vec2::new( 7 - position.y, 7 - position.x ),
+        _ => return position,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin() -> Book {
+        return Book { lines: Book::parse( BUILTIN ).unwrap(), learned: Vec::new(), seed: 1 };
+    }
+
+    fn moves( transcript: &str ) -> Vec<Vec2<i32>> {
+        return rules::parse_transcript( transcript ).unwrap();
+    }
+
+    //  手順全体を対称変換 ( 初手 f5 の手順を他の初手に )
+    fn turned( transcript: &str, symmetry: usize ) -> Vec<Vec2<i32>> {
+        return moves( transcript ).iter().map( | p | transform( *p, symmetry ) ).collect();
+    }
+
+    #[test]
+    fn builtin_book_parses() {
+        let lines = Book::parse( BUILTIN ).unwrap();
+        assert_eq!( lines.len(), 8 );
+        assert!( lines.iter().any( | line | line.name == "Tiger" && line.moves == moves( "f5d6c3d3c4" ) ) );
+
+        let line = &Book::parse( "# comment\n\nf5d6 Two Words # trailing" ).unwrap()[0];
+        assert_eq!( ( line.name.as_str(), line.black_wins, line.draws, line.white_wins ), ( "Two Words", 0, 0, 0 ) );
+    }
+
+    #[test]
+    fn bad_lines_are_reported_with_their_number() {
+        for source in [ "f5d6", "d3c3 NotF5", "f5f5 Illegal", "f5d Odd" ].iter() {
+            let error = Book::parse( &format!( "f5f6 Fine\n{}", source ) ).err().unwrap();
+            assert!( error.starts_with( "line 2:" ), "{}", error );
+        }
+    }
+
+    #[test]
+    fn symmetries_keep_the_start_and_undo_themselves() {
+        let start = rules::Rules::new( Variant::STANDARD );
+        for symmetry in 0 .. SYMMETRY_NUM {
+            for y in 0 .. 8 {
+                for x in 0 .. 8 {
+                    let position = Vec2::new( x, y );
+                    let moved = transform( position, symmetry );
+                    assert_eq!( transform( moved, symmetry ), position );
+                    assert!( start.get_piece( x, y ) == start.get_piece( moved.x, moved.y ) );
+                }
+            }
+
+            //  どの初手も f5 にそろう
+            assert_eq!( canonical( Variant::STANDARD, &turned( "f5d6c3", symmetry ) ), Some( moves( "f5d6c3" ) ) );
+        }
+        assert_eq!( symmetry_of( Vec2::new( 0, 0 ) ), None );
+        assert_eq!( canonical( Variant::CROSS, &moves( "f5" ) ), None );
+    }
+
+    #[test]
+    fn name_of_the_longest_line_played() {
+        let book = builtin();
+        for symmetry in 0 .. SYMMETRY_NUM {
+            assert_eq!( book.get_name( Variant::STANDARD, &turned( "f5d6c3", symmetry ) ), Some( "Perpendicular" ) );
+            assert_eq!( book.get_name( Variant::STANDARD, &turned( "f5d6c3d3c4", symmetry ) ), Some( "Tiger" ) );
+        }
+        assert_eq!( book.get_name( Variant::STANDARD, &[] ), None );
+        assert_eq!( book.get_name( Variant::STANDARD, &moves( "f5d6c3d3c4f4" ) ), None );
+        assert_eq!( book.get_name( Variant::CROSS, &moves( "f5d6" ) ), None );
+    }
+
+    #[test]
+    fn choose_follows_the_book_in_every_symmetry() {
+        let mut book = builtin();
+        for _ in 0 .. 20 {
+            let first = book.choose( Variant::STANDARD, &[] ).unwrap();
+            assert!( symmetry_of( first ).is_some() );
+
+            let played = vec![ first, book.choose( Variant::STANDARD, &[ first ] ).unwrap() ];
+            let name = book.get_name( Variant::STANDARD, &played );
+            assert!( [ Some( "Perpendicular" ), Some( "Diagonal" ), Some( "Parallel" ) ].contains( &name ) );
+        }
+        assert_eq!( book.choose( Variant::STANDARD, &moves( "f5d6c3d3c4f4" ) ), None );
+    }
+
+    fn next_result( learner: &Learner ) -> Result<( Vec<Vec2<i32>>, i32 ), String> {
+        loop {
+            match learner.poll() {
+                Some( result ) => return result,
+                None => thread::sleep( std::time::Duration::from_millis( 10 ) ),
+            }
+        }
+    }
+
+    #[test]
+    fn learner_solves_games_in_turn() {
+        let learner = Learner::start();
+        let game = moves( "d3c3f5f4e3f6c4e2f3c2f1d2e6d7g4d6c5e1d1c1b1b6c6b4a6c7g5g6f2b5a5a3c8d8f7a4b3a7e8h5e7f8g8a2h4h3g3h2h7g2h1h6b2h8g7a1b7a8g1b8" );
+        learner.push( Variant::CROSS, &game );
+        learner.push( Variant::STANDARD, &moves( "f5d6" ) );
+        learner.push( Variant::STANDARD, &game );
+
+        assert_eq!( next_result( &learner ).err().unwrap(), "only games from the standard start are learned" );
+        assert_eq!( next_result( &learner ).err().unwrap(), "the game ended before the endgame" );
+        let ( solved, _ ) = next_result( &learner ).unwrap();
+        assert_eq!( solved.len(), 60 - LEARN_EMPTIES as usize );
+        assert_eq!( solved, canonical( Variant::STANDARD, &game[.. solved.len()] ).unwrap() );
+
+        //  残っている対局は読まずに終わる
+        for _ in 0 .. 10 {
+            learner.push( Variant::STANDARD, &game );
+        }
+        let start = std::time::Instant::now();
+        drop( learner );
+        assert!( start.elapsed() < std::time::Duration::from_secs( 5 ) );
+    }
+
+    #[test]
+    fn more_wins_are_chosen_more_often() {
+        let mut book = Book { lines: Book::parse( "f5d6 Often\nf5f6 Rarely" ).unwrap(), learned: Vec::new(), seed: 7 };
+        let won = moves( "f5d6c3" );
+        for _ in 0 .. 25 {
+            book.add_learned( Learned { colors: move_colors( &won ), moves: won.clone(), score: -4 } );
+        }
+        assert_eq!( ( book.lines[0].white_wins, book.lines[1].white_wins ), ( 25, 0 ) );
+
+        let often = ( 0 .. 200 ).filter( | _ | book.choose( Variant::STANDARD, &moves( "f5" ) ) == rules::parse_notation( "d6" ) ).count();
+        assert!( often > 150, "{}", often );
+    }
+}
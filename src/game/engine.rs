@@ -0,0 +1,209 @@
+use std::io::{ self, BufRead, BufReader, ErrorKind, Write };
+use std::process::{ Child, ChildStdin, Command, Stdio };
+use std::sync::mpsc::{ self, Receiver, TryRecvError };
+use std::thread;
+use std::time::{ Duration, Instant };
+use tetra::math::Vec2;
+use super::ggf::{ self, Record };
+use super::rules::{ self, Variant };
+
+//  外部のエンジンを子プロセスとして動かす
+//  NBOARD: NBoard プロトコル ( set game で棋譜を送り go で手を聞く )
+//  TEXT: 1行で局面 ( 64マス + 手番、Rules::to_position_string ) を送り、"d3" か "pass" の 1行を返してもらう
+
+const WAIT_INTERVAL: Duration = Duration::from_millis( 10 );
+pub const ENGINE_TIMEOUT: Duration = Duration::from_secs( 60 );   //  外部のエンジンの返事を待つ時間
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Protocol {
+    NBOARD,
+    TEXT,
+}
+
+impl Protocol {
+    pub fn parse( text: &str ) -> Option<Protocol> {
+        match text {
+            "nboard" => return Some( Protocol::NBOARD ),
+            "text" => return Some( Protocol::TEXT ),
+            _ => return None,
+        }
+    }
+}
+
+//  エンジンの起動設定 ( コマンドラインで指定 )
+#[derive(Clone)]
+pub struct EngineConfig {
+    pub command: String,    //  実行するコマンド ( 空白区切りで引数も書ける )
+    pub protocol: Protocol,
+}
+
+pub struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,    //  標準出力の行 ( 読み込みスレッドから届く )
+    protocol: Protocol,
+    name: String,               //  エンジンの名前 ( NBoard の set myname )
+    thinking: bool,             //  手を聞いて返事待ち
+    requested: Instant,         //  最後に手を聞いた時刻
+    ping: u32,                  //  NBoard: 最後に送った ping ( 古い返事を読み飛ばす )
+    synced: bool,               //  NBoard: 最後の ping に pong が返ってきた
+}
+
+impl Engine {
+    pub fn start( config: &EngineConfig, level: u32 ) -> io::Result<Engine> {
+        let mut args = config.command.split_whitespace();
+        let program = args.next().ok_or( io::Error::new( ErrorKind::InvalidInput, "empty engine command" ) )?;
+        let mut child = Command::new( program )
+            .args( args )
+            .stdin( Stdio::piped() )
+            .stdout( Stdio::piped() )
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or( io::Error::new( ErrorKind::BrokenPipe, "no stdin" ) )?;
+        let stdout = child.stdout.take().ok_or( io::Error::new( ErrorKind::BrokenPipe, "no stdout" ) )?;
+
+        //  読み込みはブロックするので別スレッドで行ごとに渡す ( 終了したら送信側が閉じる )
+        let ( sender, lines ) = mpsc::channel();
+        thread::spawn( move || {
+            for line in BufReader::new( stdout ).lines() {
+                match line {
+                    Ok( line ) => {
+                        if sender.send( line ).is_err() {
+                            break;
+                        }
+                    },
+                    Err( _ ) => break,
+                }
+            }
+        } );
+
+        let mut engine = Engine {
+            child,
+            stdin,
+            lines,
+            protocol: config.protocol,
+            name: program.to_string(),
+            thinking: false,
+            requested: Instant::now(),
+            ping: 0,
+            synced: true,
+        };
+
+        if engine.protocol == Protocol::NBOARD {
+            engine.send( "nboard 2" )?;
+            engine.send( &format!( "set depth {}", level ) )?;
+        }
+
+        return Ok( engine );
+    }
+
+    pub fn is_thinking( &self ) -> bool {
+        return self.thinking;
+    }
+
+    //  手番の手を聞く ( 返事は poll で受け取る )
+    pub fn request( &mut self, variant: Variant, moves: &[Vec2<i32>] ) -> io::Result<()> {
+        match self.protocol {
+            Protocol::NBOARD => {
+                self.ping += 1;
+                self.synced = false;
+                let record = Record::from_moves( variant, moves );
+                self.send( &format!( "ping {}", self.ping ) )?;
+                self.send( &format!( "set game {}", record.to_text() ) )?;
+                self.send( "go" )?;
+            },
+            Protocol::TEXT => {
+                let ( rules, color ) = rules::replay( variant, moves ).ok_or( io::Error::new( ErrorKind::InvalidInput, "invalid moves" ) )?;
+                self.send( &rules.to_position_string( color ) )?;
+            },
+        }
+
+        self.thinking = true;
+        self.requested = Instant::now();
+        return Ok(());
+    }
+
+    //  届いていれば返事を取り出す ( 手、パスなら None。エンジンが終了したか ENGINE_TIMEOUT 過ぎても返事が無ければ Err )
+    pub fn poll( &mut self ) -> Option<Result<Option<Vec2<i32>>, String>> {
+        if !self.thinking {
+            return None;
+        }
+
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok( line ) => line,
+                Err( TryRecvError::Empty ) => {
+                    if self.requested.elapsed() > ENGINE_TIMEOUT {
+                        self.thinking = false;
+                        return Some( Err( format!( "{}: no answer", self.name ) ) );
+                    }
+                    return None;
+                },
+                Err( TryRecvError::Disconnected ) => {
+                    self.thinking = false;
+                    return Some( Err( format!( "{} exited", self.name ) ) );
+                },
+            };
+
+            if let Some( answer ) = self.answer( line.trim() ) {
+                self.thinking = false;
+                return Some( ggf::parse_move( &answer ).ok_or( format!( "{}: invalid move: {}", self.name, answer ) ) );
+            }
+        }
+    }
+
+    //  返事が来るまで待つ ( TUI 用 )
+    pub fn wait( &mut self, timeout: Duration ) -> Result<Option<Vec2<i32>>, String> {
+        let start = Instant::now();
+        loop {
+            if let Some( answer ) = self.poll() {
+                return answer;
+            }
+            if start.elapsed() > timeout {
+                self.thinking = false;
+                return Err( format!( "{}: no answer", self.name ) );
+            }
+            thread::sleep( WAIT_INTERVAL );
+        }
+    }
+
+    //  手を返す行ならその手の部分
+    fn answer( &mut self, line: &str ) -> Option<String> {
+        match self.protocol {
+            Protocol::NBOARD => {
+                if let Some( name ) = line.strip_prefix( "set myname " ) {
+                    self.name = name.trim().to_string();
+                } else if let Some( ping ) = line.strip_prefix( "pong " ) {
+                    self.synced = ping.trim() == self.ping.to_string();
+                } else if let Some( answer ) = line.strip_prefix( "===" ) {
+                    //  前の局面への返事は読み飛ばす
+                    if self.synced {
+                        return Some( answer.trim().to_string() );
+                    }
+                }
+                return None;
+            },
+            Protocol::TEXT => {
+                if line.is_empty() {
+                    return None;
+                }
+                return Some( line.to_string() );
+            },
+        }
+    }
+
+    fn send( &mut self, line: &str ) -> io::Result<()> {
+        writeln!( self.stdin, "{}", line )?;
+        return self.stdin.flush();
+    }
+}
+
+impl Drop for Engine {
+    fn drop( &mut self ) {
+        if self.protocol == Protocol::NBOARD {
+            let _ = self.send( "quit" );
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,472 @@
+use std::fs;
+use std::io::{ self, ErrorKind };
+use std::path::Path;
+use tetra::math::Vec2;
+use super::board::cell::Color;
+use super::rules::{ self, Rules, Variant };
+use super::turn::Outcome;
+
+//  GGF ( Generic Game Format ) の棋譜
+//  (;GM[Othello]PC[場所]DT[日時]PB[黒]PW[白]RB[黒のレーティング]RW[白のレーティング]TI[持ち時間]TY[8]RE[+12.000]
+//    BO[8 <64マス> <手番>]B[F5//1.20]W[D6/-2.00/0.12];)
+//  マスは * が黒、O が白、- が空き。パスは PA。手は 手/評価値/消費時間 ( 秒 )
+//  RE は黒から見た石の差。:r は投了、:t は時間切れ、:s は合意
+
+const PLACE: &str = "Othello";  //  PC ( 対局場所 ) に書く名前
+
+//  1手 ( position が None ならパス )
+#[derive(Copy, Clone)]
+pub struct Move {
+    pub color: Color,
+    pub position: Option<Vec2<i32>>,
+    pub eval: Option<f32>,  //  打った側から見た評価値 ( 石の差 )
+    pub time: Option<f32>,  //  消費時間 ( 秒 )
+}
+
+impl Move {
+    pub fn new( color: Color, position: Option<Vec2<i32>> ) -> Move {
+        return Move { color, position, eval: None, time: None };
+    }
+}
+
+//  終局の理由
+#[derive(Copy, Clone, PartialEq)]
+pub enum Ending {
+    FINISHED,   //  打ち切った
+    RESIGN,     //  投了 ( :r )
+    TIME,       //  時間切れ ( :t )
+    AGREEMENT,  //  合意 ( :s )
+}
+
+//  対局結果 ( 黒から見た石の差 )
+#[derive(Copy, Clone)]
+pub struct GameResult {
+    pub score: f32,
+    pub ending: Ending,
+}
+
+impl GameResult {
+    pub fn outcome( &self ) -> Outcome {
+        if self.score > 0.0 {
+            return Outcome::BLACK_WIN;
+        } else if self.score < 0.0 {
+            return Outcome::WHITE_WIN;
+        }
+        return Outcome::DRAW;
+    }
+
+    fn parse( text: &str ) -> Option<GameResult> {
+        let mut parts = text.trim().splitn( 2, ':' );
+        let score: f32 = parts.next()?.trim().parse().ok()?;
+        let ending = match parts.next().map( | s | s.trim() ) {
+            None | Some( "" ) => Ending::FINISHED,
+            Some( "r" ) => Ending::RESIGN,
+            Some( "t" ) => Ending::TIME,
+            Some( "s" ) => Ending::AGREEMENT,
+            Some( _ ) => return None,
+        };
+        return Some( GameResult { score, ending } );
+    }
+
+    fn to_text( &self ) -> String {
+        let suffix = match self.ending {
+            Ending::FINISHED => "",
+            Ending::RESIGN => ":r",
+            Ending::TIME => ":t",
+            Ending::AGREEMENT => ":s",
+        };
+        return format!( "{:+.3}{}", self.score, suffix );
+    }
+}
+
+#[derive(Clone)]
+pub struct Record {
+    pub place: String,  //  PC
+    pub date: String,   //  DT
+    pub black: String,  //  PB
+    pub white: String,  //  PW
+    pub black_rating: Option<f32>,  //  RB
+    pub white_rating: Option<f32>,  //  RW
+    pub time: String,   //  TI ( 持ち時間。書式はそのまま )
+    pub result: Option<GameResult>, //  RE ( 対局中なら None )
+    pub board: Rules,   //  開始局面
+    pub color: Color,   //  開始局面の手番
+    pub moves: Vec<Move>,
+}
+
+impl Record {
+    //  初期配置からの空の棋譜
+    pub fn new( variant: Variant ) -> Record {
+        return Record {
+            place: PLACE.to_string(),
+            date: String::new(),
+            black: String::new(),
+            white: String::new(),
+            black_rating: None,
+            white_rating: None,
+            time: String::new(),
+            result: None,
+            board: Rules::new( variant ),
+            color: Color::BLACK,
+            moves: Vec::new(),
+        };
+    }
+
+    //  ファイルの全ての対局を読み込む
+    pub fn load( path: &Path ) -> io::Result<Vec<Record>> {
+        let records = Record::parse_all( &fs::read_to_string( path )? );
+        if records.is_empty() {
+            return Err( io::Error::new( ErrorKind::InvalidData, "no games" ) );
+        }
+        return Ok( records );
+    }
+
+    pub fn save( &self, path: &Path ) -> io::Result<()> {
+        return fs::write( path, self.to_text() + "\n" );
+    }
+
+    //  複数の対局が並んだ文字列 ( 読めない対局は飛ばす )
+    pub fn parse_all( text: &str ) -> Vec<Record> {
+        return text.match_indices( "(;" ).filter_map( | ( i, _ ) | Record::parse( &text[i ..] ) ).collect();
+    }
+
+    //  1局分を読み込む ( 盤の大きさは 8 のみ )
+    pub fn parse( text: &str ) -> Option<Record> {
+        let start = text.find( "(;" )?;
+        let mut record = Record::new( Variant::STANDARD );
+        record.place.clear();
+
+        for ( key, value ) in properties( &text[start + 2 ..] ) {
+            match key.as_str() {
+                "GM" => {
+                    if !value.trim().eq_ignore_ascii_case( "othello" ) {
+                        return None;
+                    }
+                },
+                "PC" => record.place = value,
+                "DT" => record.date = value,
+                "PB" => record.black = value,
+                "PW" => record.white = value,
+                "RB" => record.black_rating = value.trim().parse().ok(),
+                "RW" => record.white_rating = value.trim().parse().ok(),
+                "TI" => record.time = value,
+                "RE" => record.result = GameResult::parse( &value ),
+                "TY" => {
+                    if !value.trim().starts_with( '8' ) {
+                        return None;
+                    }
+                },
+                "BO" => {
+                    let value = value.trim();
+                    if !value.starts_with( "8 " ) {
+                        return None;
+                    }
+                    let ( board, color ) = Rules::parse_position( &value[2 ..] )?;
+                    record.board = board;
+                    record.color = color;
+                },
+                "B" | "W" => {
+                    let color = if key == "B" { Color::BLACK } else { Color::WHITE };
+                    record.moves.push( parse_record_move( color, &value )? );
+                },
+                _ => {},
+            }
+        }
+
+        return Some( record );
+    }
+
+    //  初期配置からの手順 ( 置けない側のパスを補う )
+    pub fn from_moves( variant: rules::Variant, moves: &[Vec2<i32>] ) -> Record {
+        let mut board = Rules::new( variant );
+        let mut color = Color::BLACK;
+        let mut record = Record::new( variant );
+        record.moves.reserve( moves.len() );
+
+        for position in moves.iter() {
+            if !board.there_is_enable_position( color ) {
+                record.moves.push( Move::new( color, None ) );
+                color = rules::get_enemy_piece( color );
+            }
+            board.set_piece( position.x, position.y, color );
+            record.moves.push( Move::new( color, Some( *position ) ) );
+            color = rules::get_enemy_piece( color );
+        }
+
+        return record;
+    }
+
+    //  GGF で書き出す ( 空の項目は省く )
+    pub fn to_text( &self ) -> String {
+        let mut text = "(;GM[Othello]".to_string();
+        let fields = [ ( "PC", &self.place ), ( "DT", &self.date ), ( "PB", &self.black ), ( "PW", &self.white ) ];
+        for ( key, value ) in fields.iter() {
+            if !value.is_empty() {
+                text += &format!( "{}[{}]", key, to_value( value ) );
+            }
+        }
+        if let Some( rating ) = self.black_rating {
+            text += &format!( "RB[{:.2}]", rating );
+        }
+        if let Some( rating ) = self.white_rating {
+            text += &format!( "RW[{:.2}]", rating );
+        }
+        if !self.time.is_empty() {
+            text += &format!( "TI[{}]", to_value( &self.time ) );
+        }
+        text += "TY[8]";
+        if let Some( result ) = self.result {
+            text += &format!( "RE[{}]", result.to_text() );
+        }
+        text += &format!( "BO[8 {}]", to_board( &self.board, self.color ) );
+
+        for iter in self.moves.iter() {
+            let key = if iter.color == Color::BLACK { "B" } else { "W" };
+            text += &format!( "{}[{}", key, to_move( iter.position ) );
+            if iter.eval.is_some() || iter.time.is_some() {
+                let eval = iter.eval.map( | e | format!( "{:.2}", e ) ).unwrap_or_default();
+                let time = iter.time.map( | t | format!( "{:.2}", t ) ).unwrap_or_default();
+                text += &format!( "/{}/{}", eval, time );
+            }
+            text += "]";
+        }
+        text += ";)";
+        return text;
+    }
+
+    //  開始局面が初期配置ならその種類
+    pub fn variant( &self ) -> Option<Variant> {
+        if self.color != Color::BLACK {
+            return None;
+        }
+        return Variant::ALL.iter().copied().find( | v | {
+            let start = Rules::new( *v );
+            ( 0 .. 8 ).all( | y | ( 0 .. 8 ).all( | x | start.get_piece( x, y ) == self.board.get_piece( x, y ) ) )
+        } );
+    }
+
+    //  パスを除いた手順 ( Game・TUI の手順と同じ形 )
+    pub fn positions( &self ) -> Vec<Vec2<i32>> {
+        return self.moves.iter().filter_map( | m | m.position ).collect();
+    }
+
+    //  全ての手を並べた局面と次の手番
+    pub fn position( &self ) -> Option<( Rules, Color )> {
+        let mut board = self.board;
+        let mut color = self.color;
+
+        for iter in self.moves.iter() {
+            if let Some( position ) = iter.position {
+                board.set_piece( position.x, position.y, iter.color )?;
+            }
+            color = rules::get_enemy_piece( iter.color );
+        }
+
+        return Some( ( board, color ) );
+    }
+}
+
+//  "F5" / "f5//0.12" / "PA" ( 評価値・時間は読み飛ばす。パスなら None )
+pub fn parse_move( text: &str ) -> Option<Option<Vec2<i32>>> {
+    let text = text.split( '/' ).next().unwrap_or( "" ).trim();
+    if text.eq_ignore_ascii_case( "PA" ) || text.eq_ignore_ascii_case( "pass" ) {
+        return Some( None );
+    }
+
+    return Some( Some( rules::parse_notation( text )? ) );
+}
+
+//  棋譜の 1手 ( 手/評価値/消費時間 )
+fn parse_record_move( color: Color, text: &str ) -> Option<Move> {
+    let mut parts = text.split( '/' );
+    let position = parse_move( parts.next()? )?;
+    let number = | part: Option<&str> | part.and_then( | s | s.trim().parse::<f32>().ok() );
+    let eval = number( parts.next() );
+    let time = number( parts.next() );
+    return Some( Move { color, position, eval, time } );
+}
+
+//  GGF での手の表記 ( 大文字、パスは PA )
+pub fn to_move( position: Option<Vec2<i32>> ) -> String {
+    match position {
+        Some( position ) => return rules::to_notation( position ).to_ascii_uppercase(),
+        None => return "PA".to_string(),
+    }
+}
+
+//  DT の日時 ( UNIX 時間の秒 -> "2024.01.02_03:04:05.GMT" )
+pub fn format_date( seconds: u64 ) -> String {
+    let days = ( seconds / 86400 ) as i64;
+    let time = seconds % 86400;
+
+    //  1970-01-01 からの日数を年月日へ ( 3月始まりで数えると閏日が年の最後になる )
+    let z = days + 719468;
+    let era = z.div_euclid( 146097 );
+    let day_of_era = z - era * 146097;
+    let year_of_era = ( day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096 ) / 365;
+    let day_of_year = day_of_era - ( 365 * year_of_era + year_of_era / 4 - year_of_era / 100 );
+    let mp = ( 5 * day_of_year + 2 ) / 153;
+    let day = day_of_year - ( 153 * mp + 2 ) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!( "{:04}.{:02}.{:02}_{:02}:{:02}:{:02}.GMT", year, month, day, time / 3600, time / 60 % 60, time % 60 );
+}
+
+//  BO の盤面 ( * が黒、O が白、- が空き。最後に手番 )
+fn to_board( board: &Rules, color: Color ) -> String {
+    let piece = | color: Color | match color {
+        Color::BLACK => '*',
+        Color::WHITE => 'O',
+        Color::NONE => '-',
+    };
+
+    let mut text = String::with_capacity( 66 );
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            text.push( piece( board.get_piece( x, y ) ) );
+        }
+    }
+    text.push( ' ' );
+    text.push( piece( color ) );
+    return text;
+}
+
+//  値に書けない ] を除く ( GGF にはエスケープが無い )
+fn to_value( text: &str ) -> String {
+    return text.replace( ']', "" );
+}
+
+//  KEY[value] を順に取り出す ( ;) で終わり )
+fn properties( text: &str ) -> Vec<( String, String )> {
+    let mut result = Vec::new();
+    let mut key = String::new();
+    let mut chars = text.chars();
+
+    while let Some( c ) = chars.next() {
+        match c {
+            'A' ..= 'Z' => key.push( c ),
+            '[' => {
+                //  閉じていない値 ( 途中で切れた棋譜 ) は捨てる
+                let rest = chars.as_str();
+                let end = match rest.find( ']' ) {
+                    Some( end ) => end,
+                    None => break,
+                };
+                result.push( ( key.clone(), rest[.. end].to_string() ) );
+                chars = rest[end + 1 ..].chars();
+                key.clear();
+            },
+            ';' => break,
+            _ => key.clear(),
+        }
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //  サーバーが保存するのと同じ形の棋譜
+    fn archived() -> Record {
+        let moves = rules::parse_transcript( "f5d6c3d3c4f4f6f3e6e7" ).unwrap();
+        let mut record = Record::from_moves( Variant::STANDARD, &moves );
+        record.date = format_date( 1704164645 );
+        record.black = "alice".to_string();
+        record.white = "bob".to_string();
+        record.black_rating = Some( 1520.0 );
+        record.time = "10:00".to_string();
+        record.result = Some( GameResult { score: -4.0, ending: Ending::RESIGN } );
+        record.moves[1].eval = Some( -1.5 );
+        record.moves[1].time = Some( 0.25 );
+        return record;
+    }
+
+    #[test]
+    fn archived_game_reads_back() {
+        let record = archived();
+        let text = record.to_text();
+        let parsed = Record::parse( &text ).unwrap();
+
+        assert_eq!( parsed.to_text(), text );
+        assert_eq!( parsed.positions(), record.positions() );
+        assert_eq!( ( parsed.black.as_str(), parsed.white.as_str() ), ( "alice", "bob" ) );
+        assert_eq!( ( parsed.black_rating, parsed.white_rating ), ( Some( 1520.0 ), None ) );
+        assert!( parsed.result.unwrap().outcome() == Outcome::WHITE_WIN );
+        assert_eq!( ( parsed.moves[1].eval, parsed.moves[1].time ), ( Some( -1.5 ), Some( 0.25 ) ) );
+        assert!( parsed.variant() == Some( Variant::STANDARD ) );
+    }
+
+    #[test]
+    fn pass_is_written_as_pa() {
+        let mut record = Record::new( Variant::STANDARD );
+        record.moves.push( Move::new( Color::BLACK, rules::parse_notation( "f5" ) ) );
+        record.moves.push( Move::new( Color::WHITE, None ) );
+        assert!( record.to_text().contains( "W[PA]" ) );
+
+        let parsed = Record::parse( &record.to_text() ).unwrap();
+        assert_eq!( parsed.moves[1].position, None );
+        assert!( parsed.position().unwrap().1 == Color::BLACK );
+    }
+
+    #[test]
+    fn other_games_in_a_file_are_skipped() {
+        let text = format!( "{}\n(;GM[Go]TY[19];)\n{}", archived().to_text(), Record::new( Variant::CROSS ).to_text() );
+        let records = Record::parse_all( &text );
+
+        assert_eq!( records.len(), 2 );
+        assert!( records[1].variant() == Some( Variant::CROSS ) );
+    }
+
+    #[test]
+    fn rejects_other_boards_and_bad_moves() {
+        for text in [
+            "", "GM[Othello]", "(;GM[Chess];)", "(;GM[Othello]TY[10];)", "(;GM[Othello]BO[10 ----];)",
+            "(;GM[Othello]BO[8 ---*O];)", "(;GM[Othello]B[Z9];)", "(;GM[Othello]B[];)",
+        ].iter() {
+            assert!( Record::parse( text ).is_none(), "{}", text );
+        }
+
+        //  読めない評価値・RE・レーティングは無いものとして扱う
+        let record = Record::parse( "(;GM[Othello]RE[+3:x]RB[abc]W[f5/x/y];)" ).unwrap();
+        assert!( record.result.is_none() && record.black_rating.is_none() && record.moves[0].eval.is_none() );
+    }
+
+    #[test]
+    fn cut_off_between_properties() {
+        let record = Record::parse( "(;GM[Othello]PB[alice]B[F5]W" ).unwrap();
+        assert_eq!( ( record.black.as_str(), record.moves.len() ), ( "alice", 1 ) );
+
+        //  閉じていない値は読まない
+        let record = Record::parse( "(;GM[Othello]PB[黒]PW[白" ).unwrap();
+        assert_eq!( ( record.black.as_str(), record.white.as_str() ), ( "黒", "" ) );
+        let record = Record::parse( "(;GM[Othello]B[F5]W[D6" ).unwrap();
+        assert_eq!( record.moves.len(), 1 );
+
+        //  どこで切れても panic しない
+        let text = archived().to_text();
+        for ( i, _ ) in text.char_indices() {
+            let _ = Record::parse( &text[.. i] );
+        }
+    }
+
+    #[test]
+    fn brackets_in_names_do_not_end_the_value() {
+        let mut record = archived();
+        record.black = "[al]ice]".to_string();
+        record.place = "x]y".to_string();
+        record.time = "10:00]".to_string();
+
+        let parsed = Record::parse( &record.to_text() ).unwrap();
+        assert_eq!( ( parsed.black.as_str(), parsed.place.as_str(), parsed.time.as_str() ), ( "[alice", "xy", "10:00" ) );
+        assert_eq!( parsed.positions(), record.positions() );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!( format_date( 0 ), "1970.01.01_00:00:00.GMT" );
+        assert_eq!( format_date( 951782400 ), "2000.02.29_00:00:00.GMT" );
+    }
+}
@@ -0,0 +1,124 @@
+use tetra::graphics::mesh::{ Mesh, ShapeStyle };
+use tetra::graphics::{ Color, DrawParams, Rectangle };
+use tetra::input::{ self, MouseButton };
+use tetra::math::Vec2;
+use tetra::Context;
+use super::layout::Layout;
+
+const MIN_RANGE: f32 = 8.0;         //  縦軸の最小の幅 ( 石差 )
+const MAX_RANGE: f32 = 64.0;        //  縦軸の最大の幅 ( 石差 )
+const LINE_WIDTH: f32 = 2.0;
+
+//  評価値のグラフ ( 各手の後の黒から見た石差を折れ線で表示し、クリックした手を返す )
+pub struct Graph {
+    position: Vec2<f32>,        //  左上座標 ( 基準座標 )
+    size: Vec2<f32>,            //  大きさ ( 基準 )
+    count: usize,               //  手数 ( 横軸の右端 )
+    index: usize,               //  表示中の手 ( 縦線 )
+    color: Color,
+    frame: Option<Mesh>,        //  枠と 0 の線
+    line: Option<Mesh>,         //  折れ線
+    cursor: Option<Mesh>,       //  表示中の手の縦線
+    layout: Option<Layout>,     //  画面配置
+}
+
+impl Graph {
+    pub fn new( position: Vec2<f32>, size: Vec2<f32> ) -> Graph {
+        Graph {
+            position,
+            size,
+            count: 0,
+            index: 0,
+            color: Color::WHITE,
+            frame: None,
+            line: None,
+            cursor: None,
+            layout: None,
+        }
+    }
+
+    //  values[i] が i手目の後の評価値 ( values[0] は初期配置 )
+    pub fn set( &mut self, ctx: &mut Context, values: &[f32], index: usize, color: Color, layout: &Layout ) -> tetra::Result {
+        self.clear();
+        if values.len() < 2 {
+            return Ok(());
+        }
+
+        self.count = values.len() - 1;
+        self.index = index.min( self.count );
+        self.color = color;
+        self.layout = Some( layout.clone() );
+
+        let range = values.iter().fold( MIN_RANGE, | range, v | range.max( v.abs() ) ).min( MAX_RANGE );
+        let middle = self.size.y / 2.0;
+        let points: Vec<Vec2<f32>> = values.iter().enumerate().map( | ( i, v ) | {
+            let x = self.size.x * i as f32 / self.count as f32;
+            let y = middle - v.max( -range ).min( range ) / range * middle;
+            Vec2::new( x, y )
+        } ).collect();
+
+        self.line = Some( Mesh::polyline( ctx, LINE_WIDTH, &points )? );
+        self.frame = Some( Mesh::polyline( ctx, 1.0, &[
+            Vec2::new( 0.0, middle ), Vec2::new( self.size.x, middle ),
+            Vec2::new( self.size.x, 0.0 ), Vec2::new( 0.0, 0.0 ),
+            Vec2::new( 0.0, self.size.y ), Vec2::new( self.size.x, self.size.y ), Vec2::new( self.size.x, middle ),
+        ] )? );
+        self.cursor = Some( Mesh::rectangle( ctx, ShapeStyle::Fill, Rectangle::new( -1.0, 0.0, 2.0, self.size.y ) )? );
+        Ok(())
+    }
+
+    pub fn clear( &mut self ) {
+        self.count = 0;
+        self.index = 0;
+        self.frame = None;
+        self.line = None;
+        self.cursor = None;
+    }
+
+    pub fn set_color( &mut self, color: Color ) {
+        self.color = color;
+    }
+
+    pub fn set_layout( &mut self, layout: &Layout ) {
+        if self.layout.is_some() {
+            self.layout = Some( layout.clone() );
+        }
+    }
+
+    //  クリックされた位置に一番近い手
+    pub fn get_clicked( &self, ctx: &mut Context ) -> Option<usize> {
+        let layout = self.layout.as_ref()?;
+        if self.line.is_none() || !input::is_mouse_button_released( ctx, MouseButton::Left ) {
+            return None;
+        }
+
+        let mouse = ( input::get_mouse_position( ctx ) - layout.point( self.position.x, self.position.y ) ) / layout.get_scale();
+        if mouse.x < 0.0 || mouse.y < 0.0 || mouse.x > self.size.x || mouse.y > self.size.y {
+            return None;
+        }
+
+        return Some( ( mouse.x / self.size.x * self.count as f32 ).round() as usize );
+    }
+
+    pub fn draw( &mut self, ctx: &mut Context ) {
+        let layout = match self.layout.as_ref() {
+            Some( layout ) => layout,
+            None => return,
+        };
+
+        let position = layout.point( self.position.x, self.position.y );
+        let scale = Vec2::new( layout.get_scale(), layout.get_scale() );
+        let dim = Color::rgba( self.color.r, self.color.g, self.color.b, 0.4 );
+
+        if let Some( frame ) = &self.frame {
+            frame.draw( ctx, DrawParams::new().position( position ).scale( scale ).color( dim ) );
+        }
+        if let ( Some( cursor ), true ) = ( &self.cursor, self.count > 0 ) {
+            let x = self.size.x * self.index as f32 / self.count as f32 * layout.get_scale();
+            cursor.draw( ctx, DrawParams::new().position( position + Vec2::new( x, 0.0 ) ).scale( scale ).color( dim ) );
+        }
+        if let Some( line ) = &self.line {
+            line.draw( ctx, DrawParams::new().position( position ).scale( scale ).color( self.color ) );
+        }
+    }
+}
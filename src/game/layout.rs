@@ -0,0 +1,64 @@
+use tetra::math::Vec2;
+
+//  基準の画面サイズ ( この大きさで配置を決めて、実際のウィンドウに合わせて拡大縮小する )
+pub const BASE_WIDTH: f32 = crate::WINDOW_WIDTH as f32;
+pub const BASE_HEIGHT: f32 = crate::WINDOW_HEIGHT as f32;
+
+const BOARD_X: f32 = 32.0;      //  ボード左上 ( 基準座標 )
+const BOARD_Y: f32 = 32.0;
+pub const PANEL_X: f32 = 720.0; //  サイドパネル左端 ( 基準座標 )
+const CELL_SIZE: f32 = 80.0;    //  マスの大きさ ( 基準 )
+
+//  画面配置
+//  基準サイズの画面をウィンドウに収まるよう等倍率で拡大縮小し、余白は中央寄せにする
+#[derive(Clone)]
+pub struct Layout {
+    width: f32,             //  ウィンドウ幅
+    height: f32,            //  ウィンドウ高さ
+    scale: f32,             //  拡大率
+    offset: Vec2<f32>,      //  中央寄せの余白
+}
+
+impl Layout {
+    pub fn new( width: i32, height: i32 ) -> Layout {
+        let width = width.max( 1 ) as f32;
+        let height = height.max( 1 ) as f32;
+        let scale = ( width / BASE_WIDTH ).min( height / BASE_HEIGHT );
+        let offset = Vec2::new( ( width - BASE_WIDTH * scale ) / 2.0, ( height - BASE_HEIGHT * scale ) / 2.0 );
+        Layout {
+            width,
+            height,
+            scale,
+            offset,
+        }
+    }
+
+    //  ウィンドウサイズが変わったか
+    pub fn is_changed( &self, width: i32, height: i32 ) -> bool {
+        return self.width != width.max( 1 ) as f32 || self.height != height.max( 1 ) as f32;
+    }
+
+    pub fn get_scale( &self ) -> f32 {
+        return self.scale;
+    }
+
+    //  基準座標 -> 画面座標
+    pub fn point( &self, x: f32, y: f32 ) -> Vec2<f32> {
+        return self.offset + Vec2::new( x, y ) * self.scale;
+    }
+
+    //  ボード左上
+    pub fn board_position( &self ) -> Vec2<f32> {
+        return self.point( BOARD_X, BOARD_Y );
+    }
+
+    //  マスの大きさ
+    pub fn cell_size( &self ) -> f32 {
+        return CELL_SIZE * self.scale;
+    }
+
+    //  文字サイズ
+    pub fn font_size( &self, size: f32 ) -> f32 {
+        return ( size * self.scale ).max( 8.0 ).round();
+    }
+}
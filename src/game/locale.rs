@@ -0,0 +1,185 @@
+use serde::{ Deserialize, Serialize };
+
+//  表示言語
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    ENGLISH,
+    JAPANESE,
+}
+
+impl Default for Language {
+    fn default() -> Language {
+        Language::ENGLISH
+    }
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [ Language::ENGLISH, Language::JAPANESE ];
+
+    //  言語名 ( その言語自身で表記 )
+    pub fn name( self ) -> &'static str {
+        match self {
+            Language::ENGLISH => "English",
+            Language::JAPANESE => "日本語",
+        }
+    }
+
+    //  次の言語 ( 設定メニューで順番に切り替える )
+    pub fn next( self ) -> Language {
+        let i = Language::ALL.iter().position( | l | *l == self ).unwrap_or( 0 );
+        return Language::ALL[( i + 1 ) % Language::ALL.len()];
+    }
+}
+
+//  メッセージID
+#[derive(Copy, Clone)]
+pub enum Message {
+    BLACK_TURN,
+    WHITE_TURN,
+    BLACK_WIN,
+    WHITE_WIN,
+    DRAW,
+    PASS,
+    ILLEGAL_MOVE,
+    VERSUS,
+    YOU_RESIGNED,
+    OPPONENT_RESIGNED,
+    DRAW_OFFER_SENT,
+    DRAW_OFFERED,
+    DRAW_DECLINED,
+    DISCONNECTED,
+    DESYNC,
+    TIME_UP,
+    SPECTATING,
+    ENGINE_ERROR,
+    REPLAYING,
+    RESIGNED,
+    RECORD_END,
+    REVIEW,
+    NEW_GAME,
+    ANALYZING,
+    BEST_MOVE,
+    BEST,
+    MISTAKE,
+    BLUNDER,
+    OPENING,
+    NETWORK_KEYS,
+    SETTINGS,
+    THEME,
+    FULLSCREEN,
+    VIEW_FROM,
+    LANGUAGE,
+    VOLUME,
+    SOUND,
+    MUSIC,
+    ON,
+    OFF,
+    BLACK,
+    WHITE,
+    CLOSE,
+}
+
+//  メッセージIDから表示文字列を取得
+pub fn text( language: Language, message: Message ) -> &'static str {
+    match language {
+        Language::ENGLISH => english( message ),
+        Language::JAPANESE => japanese( message ),
+    }
+}
+
+fn english( message: Message ) -> &'static str {
+    match message {
+        Message::BLACK_TURN => "Black Turn",
+        Message::WHITE_TURN => "White Turn",
+        Message::BLACK_WIN => "Black Win",
+        Message::WHITE_WIN => "White Win",
+        Message::DRAW => "Draw",
+        Message::PASS => "Pass",
+        Message::ILLEGAL_MOVE => "Illegal move",
+        Message::VERSUS => "vs",
+        Message::YOU_RESIGNED => "You resigned",
+        Message::OPPONENT_RESIGNED => "Opponent resigned",
+        Message::DRAW_OFFER_SENT => "Draw offered",
+        Message::DRAW_OFFERED => "Draw offer: Y / N",
+        Message::DRAW_DECLINED => "Draw declined",
+        Message::DISCONNECTED => "Disconnected",
+        Message::DESYNC => "Game out of sync",
+        Message::TIME_UP => "Time up",
+        Message::SPECTATING => "Watching",
+        Message::ENGINE_ERROR => "Engine error",
+        Message::REPLAYING => "Replaying a saved game",
+        Message::RESIGNED => "Resigned",
+        Message::RECORD_END => "End of the record",
+        Message::REVIEW => "Review",
+        Message::NEW_GAME => "New Game",
+        Message::ANALYZING => "Analyzing...",
+        Message::BEST_MOVE => "Best move",
+        Message::BEST => "Best",
+        Message::MISTAKE => "Mistake",
+        Message::BLUNDER => "Blunder",
+        Message::OPENING => "Opening",
+        Message::NETWORK_KEYS => "R: resign  D: draw  Enter: chat",
+        Message::SETTINGS => "Settings",
+        Message::THEME => "Theme",
+        Message::FULLSCREEN => "Fullscreen",
+        Message::VIEW_FROM => "View from",
+        Message::LANGUAGE => "Language",
+        Message::VOLUME => "Volume",
+        Message::SOUND => "Sound",
+        Message::MUSIC => "Music",
+        Message::ON => "On",
+        Message::OFF => "Off",
+        Message::BLACK => "Black",
+        Message::WHITE => "White",
+        Message::CLOSE => "Close",
+    }
+}
+
+fn japanese( message: Message ) -> &'static str {
+    match message {
+        Message::BLACK_TURN => "黒の番",
+        Message::WHITE_TURN => "白の番",
+        Message::BLACK_WIN => "黒の勝ち",
+        Message::WHITE_WIN => "白の勝ち",
+        Message::DRAW => "引き分け",
+        Message::PASS => "パス",
+        Message::ILLEGAL_MOVE => "そこには置けません",
+        Message::VERSUS => "対戦相手:",
+        Message::YOU_RESIGNED => "投了しました",
+        Message::OPPONENT_RESIGNED => "相手が投了しました",
+        Message::DRAW_OFFER_SENT => "引き分けを提案しました",
+        Message::DRAW_OFFERED => "引き分けの提案: Y / N",
+        Message::DRAW_DECLINED => "引き分けは断られました",
+        Message::DISCONNECTED => "切断されました",
+        Message::DESYNC => "盤面が一致しません",
+        Message::TIME_UP => "時間切れ",
+        Message::SPECTATING => "観戦中",
+        Message::ENGINE_ERROR => "エンジンのエラー",
+        Message::REPLAYING => "棋譜を再生中",
+        Message::RESIGNED => "投了",
+        Message::RECORD_END => "棋譜の終わり",
+        Message::REVIEW => "振り返り",
+        Message::NEW_GAME => "新しい対局",
+        Message::ANALYZING => "解析中...",
+        Message::BEST_MOVE => "最善手",
+        Message::BEST => "最善",
+        Message::MISTAKE => "悪手",
+        Message::BLUNDER => "大悪手",
+        Message::OPENING => "定石",
+        Message::NETWORK_KEYS => "R: 投了  D: 引き分け  Enter: チャット",
+        Message::SETTINGS => "設定",
+        Message::THEME => "テーマ",
+        Message::FULLSCREEN => "フルスクリーン",
+        Message::VIEW_FROM => "視点",
+        Message::LANGUAGE => "言語",
+        Message::VOLUME => "音量",
+        Message::SOUND => "効果音",
+        Message::MUSIC => "音楽",
+        Message::ON => "オン",
+        Message::OFF => "オフ",
+        Message::BLACK => "黒",
+        Message::WHITE => "白",
+        Message::CLOSE => "閉じる",
+    }
+}
@@ -0,0 +1,100 @@
+use tetra::graphics::mesh::{ Mesh, ShapeStyle };
+use tetra::graphics::{ Color, DrawParams, Rectangle };
+use tetra::math::Vec2;
+use tetra::Context;
+use super::assets::Assets;
+use super::layout::Layout;
+use super::ui;
+
+const FONT_SIZE: f32 = 32.0;        //  項目の文字サイズ
+const ITEM_HEIGHT: f32 = 48.0;      //  項目の間隔
+const PADDING: f32 = 24.0;          //  余白
+const WIDTH: f32 = 480.0;           //  メニュー幅
+
+//  設定メニュー ( 項目のキーと表示文字列を受け取って並べるだけ )
+pub struct Menu {
+    open: bool,
+    position: Vec2<f32>,        //  左上座標 ( 基準座標 )
+    background: Option<Mesh>,   //  背景
+    items: Vec<ui::ButtonUI>,   //  項目
+    layout: Option<Layout>,     //  画面配置
+}
+
+impl Menu {
+    pub fn new() -> Menu {
+        let open = false;
+        let position = Vec2::new( 120.0, 120.0 );
+        let background = None;
+        let items = Vec::with_capacity( 10 );
+        let layout = None;
+        Menu {
+            open,
+            position,
+            background,
+            items,
+            layout,
+        }
+    }
+
+    //  項目 ( キー, 表示文字列 ) を指定して開く
+    pub fn open( &mut self, items: &[(&str, String)], color: Color, layout: &Layout, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result {
+        self.items.clear();
+
+        for ( i, ( key, label ) ) in items.iter().enumerate() {
+            let position = self.position + Vec2::new( PADDING, PADDING + ITEM_HEIGHT * i as f32 );
+            let mut button = ui::ButtonUI::new( key.to_string(), label.clone(), FONT_SIZE,
+            DrawParams::new().position( position ).color( color ), | _ | {}, assets, ctx )?;
+            button.set_layout( layout, assets, ctx )?;
+            self.items.push( button );
+        }
+
+        let height = PADDING * 2.0 + ITEM_HEIGHT * items.len() as f32;
+        self.background = Some( Mesh::rectangle( ctx, ShapeStyle::Fill, Rectangle::new( 0.0, 0.0, WIDTH, height ) )? );
+        self.layout = Some( layout.clone() );
+        self.open = true;
+        Ok(())
+    }
+
+    pub fn close( &mut self ) {
+        self.open = false;
+        self.items.clear();
+    }
+
+    pub fn is_open( &self ) -> bool {
+        return self.open;
+    }
+
+    //  クリックされた項目のキーを返す
+    pub fn update( &mut self, ctx: &mut Context ) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        for i in 0 .. self.items.len() {
+            self.items[i].update( ctx );
+            if self.items[i].is_clicked() {
+                return Some( self.items[i].get_key().to_string() );
+            }
+        }
+
+        return None;
+    }
+
+    pub fn draw( &mut self, ctx: &mut Context ) {
+        if !self.open {
+            return;
+        }
+
+        if let ( Some( background ), Some( layout ) ) = ( &self.background, &self.layout ) {
+            let param = DrawParams::new()
+                .position( layout.point( self.position.x, self.position.y ) )
+                .scale( Vec2::new( layout.get_scale(), layout.get_scale() ) )
+                .color( Color::rgba( 0.0, 0.0, 0.0, 0.85 ) );
+            background.draw( ctx, param );
+        }
+
+        for i in 0 .. self.items.len() {
+            self.items[i].draw( ctx );
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::io::{ self, BufRead, Write };
+use std::time::Instant;
+use tetra::math::Vec2;
+use super::ai;
+use super::book::Book;
+use super::board::cell::Color;
+use super::ggf::{ self, Move, Record };
+use super::rules::{ self, Rules, Variant };
+
+//  NBoard プロトコル ( 外部の GUI・対局ソフトから標準入出力で使う )
+//  GUI -> エンジン: nboard / set depth / set game / set contempt / move / go / hint / ping / learn
+//  エンジン -> GUI: set myname / === / search / status / pong / learned
+
+const ENGINE_NAME: &str = "Othello";
+
+pub fn run( level: u32 ) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    return serve( &mut stdin.lock(), &mut stdout.lock(), level );
+}
+
+//  エンジンが覚えている対局
+struct Engine {
+    record: Record,
+    rules: Rules,   //  現在の局面
+    color: Color,   //  現在の手番
+    level: u32,     //  読みの深さ
+    book: Book,     //  定石
+}
+
+impl Engine {
+    fn new( level: u32 ) -> Engine {
+        return Engine {
+            record: Record::new( Variant::STANDARD ),
+            rules: Rules::new( Variant::STANDARD ),
+            color: Color::BLACK,
+            level,
+            book: Book::load(),
+        };
+    }
+
+    fn set_game( &mut self, record: Record ) -> bool {
+        match record.position() {
+            Some( ( rules, color ) ) => {
+                self.record = record;
+                self.rules = rules;
+                self.color = color;
+                return true;
+            },
+            None => return false,
+        }
+    }
+
+    //  手を進める ( 置けない手なら false )
+    fn play( &mut self, position: Option<Vec2<i32>> ) -> bool {
+        if let Some( position ) = position {
+            if self.rules.set_piece( position.x, position.y, self.color ).is_none() {
+                return false;
+            }
+        }
+
+        self.record.moves.push( Move::new( self.color, position ) );
+        self.color = rules::get_enemy_piece( self.color );
+        return true;
+    }
+}
+
+//  入出力を差し替えられるようにしておく ( パイプでの動作確認用 )
+pub fn serve<R: BufRead, W: Write>( input: &mut R, output: &mut W, level: u32 ) -> io::Result<()> {
+    let mut engine = Engine::new( level );
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if input.read_line( &mut line )? == 0 {
+            return Ok(());
+        }
+
+        let line = line.trim();
+        let ( command, argument ) = match line.find( ' ' ) {
+            Some( i ) => ( &line[.. i], line[i + 1 ..].trim() ),
+            None => ( line, "" ),
+        };
+
+        match command {
+            "nboard" => writeln!( output, "set myname {}", ENGINE_NAME )?,
+            "set" => set( output, &mut engine, argument )?,
+            "move" => {
+                let position = ggf::parse_move( argument );
+                if position.is_none() || !engine.play( position.unwrap() ) {
+                    writeln!( output, "status illegal move: {}", argument )?;
+                }
+            },
+            "go" => go( output, &mut engine )?,
+            "hint" => hint( output, &engine, argument.parse().unwrap_or( 1 ) )?,
+            "ping" => writeln!( output, "pong {}", argument )?,
+            "learn" => learn( output, &mut engine )?,
+            "quit" => return Ok(()),
+            _ => {},
+        }
+
+        output.flush()?;
+    }
+}
+
+fn set<W: Write>( output: &mut W, engine: &mut Engine, argument: &str ) -> io::Result<()> {
+    let ( name, value ) = match argument.find( ' ' ) {
+        Some( i ) => ( &argument[.. i], argument[i + 1 ..].trim() ),
+        None => ( argument, "" ),
+    };
+
+    match name {
+        "depth" => {
+            if let Ok( depth ) = value.parse::<u32>() {
+                engine.level = depth.max( 1 ).min( ai::MAX_LEVEL );
+            }
+        },
+        "game" => {
+            let ok = match Record::parse( value ) {
+                Some( record ) => engine.set_game( record ),
+                None => false,
+            };
+            if !ok {
+                writeln!( output, "status invalid game" )?;
+            }
+        },
+        _ => {},
+    }
+
+    return Ok(());
+}
+
+//  手番側の最善手を返す ( 盤面は進めない。GUI から move が届く )
+fn go<W: Write>( output: &mut W, engine: &mut Engine ) -> io::Result<()> {
+    //  定石の中なら読まずに返す ( 初期配置から始まった対局のみ )
+    let book_move = engine.record.variant()
+        .and_then( | variant | engine.book.choose( variant, &engine.record.positions() ) )
+        .filter( | p | engine.rules.can_piece( p.x, p.y, engine.color ) );
+    if book_move.is_some() {
+        writeln!( output, "=== {}/0.00/0.00", ggf::to_move( book_move ) )?;
+        return Ok(());
+    }
+
+    writeln!( output, "status thinking" )?;
+    output.flush()?;
+
+    let start = Instant::now();
+    let result = ai::search( &engine.rules, engine.color, engine.level );
+    let eval = ai::to_discs( result.score, result.exact );
+
+    writeln!( output, "status" )?;
+    writeln!( output, "=== {}/{:.2}/{:.2}", ggf::to_move( result.position ), eval, start.elapsed().as_secs_f32() )?;
+    return Ok(());
+}
+
+//  今の対局を定石に覚えて保存する
+fn learn<W: Write>( output: &mut W, engine: &mut Engine ) -> io::Result<()> {
+    let learned = match engine.record.variant() {
+        Some( variant ) => engine.book.learn( variant, &engine.record.positions() ),
+        None => Err( "only games from the standard start are learned".to_string() ),
+    };
+    if let Err( e ) = learned.and_then( | _ | engine.book.save().map_err( | e | e.to_string() ) ) {
+        writeln!( output, "status learn: {}", e )?;
+    }
+
+    writeln!( output, "learned" )?;
+    return Ok(());
+}
+
+//  評価の高い順に count 手を返す
+fn hint<W: Write>( output: &mut W, engine: &Engine, count: usize ) -> io::Result<()> {
+    writeln!( output, "status thinking" )?;
+    output.flush()?;
+
+    let exact = ai::is_endgame( &engine.rules, engine.level );
+    let scores = ai::score_moves( &engine.rules, engine.color, engine.level, None ).unwrap_or_default();
+
+    //  置けなければパスだけ返す
+    if scores.is_empty() {
+        writeln!( output, "search {} 0.00 0 {}", ggf::to_move( None ), engine.level )?;
+    }
+
+    for ( position, score ) in scores.iter().take( count ) {
+        writeln!( output, "search {} {:.2} 0 {}", ggf::to_move( Some( *position ) ), ai::to_discs( *score, exact ),
+            if exact { "100%".to_string() } else { engine.level.to_string() } )?;
+    }
+
+    writeln!( output, "status" )?;
+    return Ok(());
+}
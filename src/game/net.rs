@@ -0,0 +1,694 @@
+use std::collections::VecDeque;
+use std::io::{ self, ErrorKind, Read, Write };
+use std::net::{ TcpListener, TcpStream };
+use std::thread;
+use std::time::{ Duration, Instant };
+use tetra::math::Vec2;
+use super::board::cell::Color;
+use super::rules::{ self, Rules, Variant };
+use super::turn::Outcome;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7650;
+
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs( 10 );
+pub const POLL_INTERVAL: Duration = Duration::from_millis( 10 );
+const MAX_LINE: usize = 4096;   //  1行の最大長 ( これを超えたら切断 )
+const MAX_OUTGOING: usize = 256 * 1024;     //  送りきれずにたまった送信データの上限 ( 超えたら切断 )
+const SEND_TIMEOUT: Duration = Duration::from_secs( 10 );  //  送信が全く進まないまま待つ時間 ( 過ぎたら切断 )
+
+//  1行 1メッセージのテキストプロトコル
+//  HELLO <version> <name> / COLOR <black|white> / MOVE <d3> / PASS / RESIGN
+//  DRAW_OFFER / DRAW_ACCEPT / DRAW_DECLINE / CHAT <text> / ERROR <text> / BYE
+//  サーバー用: LOBBY <name:rating ...> / SEEK / CHALLENGE <name> / CLOCK <black_ms> <white_ms> / TIMEOUT <black|white>
+//  観戦用: WATCH [name] / GAME <variant> <black> <white> / HISTORY <moves> / BOARD <position> / RESULT <black|white|draw> <reason>
+#[derive(Clone, PartialEq)]
+pub enum Message {
+    HELLO( u32, String ),   //  接続時の挨拶 ( プロトコルのバージョン、名前 )
+    COLOR( Color ),         //  受け取った側の色 ( ホストが決める )
+    MOVE( Vec2<i32> ),
+    PASS,
+    RESIGN,
+    DRAW_OFFER,
+    DRAW_ACCEPT,
+    DRAW_DECLINE,
+    CHAT( String ),
+    ERROR( String ),
+    BYE,
+    LOBBY( Vec<( String, i32 )> ),  //  サーバーのロビーで待っているプレイヤー ( 名前、レーティング )
+    SEEK,                   //  レーティングの近い相手と対局したい
+    CHALLENGE( String ),    //  名前を指定して対局したい
+    CLOCK( u64, u64 ),      //  残り時間 ( 黒、白 ミリ秒 )
+    TIMEOUT( Color ),       //  時間切れになった色
+    WATCH( Option<String> ),            //  観戦したい ( プレイヤー名。None なら最新の対局 )
+    GAME( Variant, String, String ),    //  観戦する対局 ( 初期配置、黒、白の名前 )
+    HISTORY( Vec<Vec2<i32>> ),          //  ここまでの手順
+    BOARD( String ),                    //  現在の局面 ( Rules::to_position_string )
+    RESULT( Outcome, String ),          //  観戦中の対局の終局 ( 勝敗、理由 )
+}
+
+impl Message {
+    pub fn parse( line: &str ) -> Option<Message> {
+        let line = line.trim();
+        let ( command, argument ) = match line.find( ' ' ) {
+            Some( i ) => ( &line[.. i], line[i + 1 ..].trim() ),
+            None => ( line, "" ),
+        };
+
+        match command {
+            "HELLO" => {
+                let mut parts = argument.splitn( 2, ' ' );
+                let version = parts.next()?.parse().ok()?;
+                let name = parts.next().unwrap_or( "" ).trim().to_string();
+                return Some( Message::HELLO( version, name ) );
+            },
+            "COLOR" => return Some( Message::COLOR( parse_color( argument )? ) ),
+            "MOVE" => return Some( Message::MOVE( rules::parse_notation( argument )? ) ),
+            "PASS" => return Some( Message::PASS ),
+            "RESIGN" => return Some( Message::RESIGN ),
+            "DRAW_OFFER" => return Some( Message::DRAW_OFFER ),
+            "DRAW_ACCEPT" => return Some( Message::DRAW_ACCEPT ),
+            "DRAW_DECLINE" => return Some( Message::DRAW_DECLINE ),
+            "CHAT" => return Some( Message::CHAT( argument.to_string() ) ),
+            "ERROR" => return Some( Message::ERROR( argument.to_string() ) ),
+            "BYE" => return Some( Message::BYE ),
+            "LOBBY" => {
+                let mut players = Vec::new();
+                for iter in argument.split_whitespace() {
+                    let ( name, rating ) = iter.split_at( iter.rfind( ':' )? );
+                    players.push( ( name.to_string(), rating[1 ..].parse().ok()? ) );
+                }
+                return Some( Message::LOBBY( players ) );
+            },
+            "SEEK" => return Some( Message::SEEK ),
+            "CHALLENGE" if !argument.is_empty() => return Some( Message::CHALLENGE( argument.to_string() ) ),
+            "CLOCK" => {
+                let mut parts = argument.split_whitespace();
+                let black = parts.next()?.parse().ok()?;
+                let white = parts.next()?.parse().ok()?;
+                return Some( Message::CLOCK( black, white ) );
+            },
+            "TIMEOUT" => return Some( Message::TIMEOUT( parse_color( argument )? ) ),
+            "WATCH" => return Some( Message::WATCH( if argument.is_empty() { None } else { Some( argument.to_string() ) } ) ),
+            "GAME" => {
+                let mut parts = argument.split_whitespace();
+                let variant = Variant::parse( parts.next()? )?;
+                let black = parts.next()?.to_string();
+                let white = parts.next()?.to_string();
+                return Some( Message::GAME( variant, black, white ) );
+            },
+            "HISTORY" => return Some( Message::HISTORY( rules::parse_transcript( argument )? ) ),
+            "BOARD" => {
+                Rules::parse_position( argument )?;
+                return Some( Message::BOARD( argument.to_string() ) );
+            },
+            "RESULT" => {
+                let mut parts = argument.splitn( 2, ' ' );
+                let outcome = Outcome::parse( parts.next()? )?;
+                let reason = parts.next().unwrap_or( "" ).trim().to_string();
+                return Some( Message::RESULT( outcome, reason ) );
+            },
+            _ => return None,
+        }
+    }
+
+    pub fn to_line( &self ) -> String {
+        match self {
+            Message::HELLO( version, name ) => return format!( "HELLO {} {}", version, name ),
+            Message::COLOR( color ) => return format!( "COLOR {}", color_text( *color ) ),
+            Message::MOVE( position ) => return format!( "MOVE {}", rules::to_notation( *position ) ),
+            Message::PASS => return "PASS".to_string(),
+            Message::RESIGN => return "RESIGN".to_string(),
+            Message::DRAW_OFFER => return "DRAW_OFFER".to_string(),
+            Message::DRAW_ACCEPT => return "DRAW_ACCEPT".to_string(),
+            Message::DRAW_DECLINE => return "DRAW_DECLINE".to_string(),
+            //  改行が入るとメッセージが分かれてしまうので空白にする
+            Message::CHAT( text ) => return format!( "CHAT {}", text.replace( | c: char | c.is_control(), " " ) ),
+            Message::ERROR( text ) => return format!( "ERROR {}", text.replace( | c: char | c.is_control(), " " ) ),
+            Message::BYE => return "BYE".to_string(),
+            Message::LOBBY( players ) => {
+                let players: Vec<String> = players.iter().map( | ( name, rating ) | format!( "{}:{}", name, rating ) ).collect();
+                return format!( "LOBBY {}", players.join( " " ) ).trim_end().to_string();
+            },
+            Message::SEEK => return "SEEK".to_string(),
+            Message::CHALLENGE( name ) => return format!( "CHALLENGE {}", name ),
+            Message::CLOCK( black, white ) => return format!( "CLOCK {} {}", black, white ),
+            Message::TIMEOUT( color ) => return format!( "TIMEOUT {}", color_text( *color ) ),
+            Message::WATCH( name ) => return format!( "WATCH {}", name.as_deref().unwrap_or( "" ) ).trim_end().to_string(),
+            Message::GAME( variant, black, white ) => return format!( "GAME {} {} {}", variant.name(), black, white ),
+            Message::HISTORY( moves ) => return format!( "HISTORY {}", rules::to_transcript( moves ) ).trim_end().to_string(),
+            Message::BOARD( position ) => return format!( "BOARD {}", position ),
+            Message::RESULT( outcome, reason ) => return format!( "RESULT {} {}", outcome.name(), reason ).trim_end().to_string(),
+        }
+    }
+}
+
+fn parse_color( text: &str ) -> Option<Color> {
+    match text {
+        "black" => return Some( Color::BLACK ),
+        "white" => return Some( Color::WHITE ),
+        _ => return None,
+    }
+}
+
+fn color_text( color: Color ) -> &'static str {
+    if color == Color::WHITE { "white" } else { "black" }
+}
+
+//  残り時間の表示 ( 分:秒 )
+pub fn format_clock( ms: u64 ) -> String {
+    let seconds = ( ms + 999 ) / 1000;
+    return format!( "{}:{:02}", seconds / 60, seconds % 60 );
+}
+
+//  ノンブロッキングの TCP 接続 ( 毎フレーム receive で届いた分だけ読む )
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,    //  改行までたまっていない受信データ
+    pending: VecDeque<Message>, //  受信済みでまだ取り出していないメッセージ
+    outgoing: Vec<u8>,  //  まだ送れていない送信データ ( 相手が読まなくても待たずに後で送る )
+    sent: Instant,      //  最後に送信が進んだ時刻
+    closed: bool,
+}
+
+impl Connection {
+    pub fn new( stream: TcpStream ) -> io::Result<Connection> {
+        stream.set_nonblocking( true )?;
+        stream.set_nodelay( true )?;
+        Ok( Connection {
+            stream,
+            buffer: Vec::with_capacity( 256 ),
+            pending: VecDeque::new(),
+            outgoing: Vec::new(),
+            sent: Instant::now(),
+            closed: false,
+        } )
+    }
+
+    //  送れるだけ送り、残りは receive のたびに送る
+    pub fn send( &mut self, message: &Message ) -> io::Result<()> {
+        if self.closed {
+            return Err( io::Error::new( ErrorKind::NotConnected, "connection closed" ) );
+        }
+
+        if self.outgoing.is_empty() {
+            self.sent = Instant::now();
+        }
+        self.outgoing.extend_from_slice( ( message.to_line() + "\n" ).as_bytes() );
+        if self.outgoing.len() > MAX_OUTGOING {
+            return Err( self.disconnect( io::Error::new( ErrorKind::WriteZero, "peer is not reading" ) ) );
+        }
+        return self.flush();
+    }
+
+    //  たまっている送信データを待たずに送る ( 相手が読まないまま SEND_TIMEOUT 過ぎたら切断 )
+    fn flush( &mut self ) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write( &self.outgoing ) {
+                Ok( 0 ) => return Err( self.disconnect( io::Error::new( ErrorKind::WriteZero, "connection closed" ) ) ),
+                Ok( n ) => {
+                    self.outgoing.drain( .. n );
+                    self.sent = Instant::now();
+                },
+                Err( e ) if e.kind() == ErrorKind::WouldBlock => {
+                    if self.sent.elapsed() > SEND_TIMEOUT {
+                        return Err( self.disconnect( io::Error::new( ErrorKind::TimedOut, "peer is not reading" ) ) );
+                    }
+                    break;
+                },
+                Err( e ) if e.kind() == ErrorKind::Interrupted => {},
+                Err( e ) => return Err( self.disconnect( e ) ),
+            }
+        }
+
+        return Ok(());
+    }
+
+    //  受信側でも切断として扱えるように BYE を積んでおく
+    fn disconnect( &mut self, error: io::Error ) -> io::Error {
+        self.closed = true;
+        self.outgoing.clear();
+        self.pending.push_back( Message::BYE );
+        let _ = self.stream.shutdown( std::net::Shutdown::Both );
+        return error;
+    }
+
+    //  届いているメッセージを全て取得 ( 切断されたら最後に BYE を返す )
+    pub fn receive( &mut self ) -> Vec<Message> {
+        self.fill();
+        return self.pending.drain( .. ).collect();
+    }
+
+    //  1つ届くまで待つ ( 接続時のやり取り用 )
+    pub fn receive_wait( &mut self, timeout: Duration ) -> io::Result<Message> {
+        let start = Instant::now();
+
+        loop {
+            self.fill();
+            if let Some( message ) = self.pending.pop_front() {
+                return Ok( message );
+            }
+
+            if start.elapsed() > timeout {
+                return Err( io::Error::new( ErrorKind::TimedOut, "no response" ) );
+            }
+            thread::sleep( POLL_INTERVAL );
+        }
+    }
+
+    //  読めるだけ読んで行ごとにメッセージにする
+    fn fill( &mut self ) {
+        if self.closed {
+            return;
+        }
+        if self.flush().is_err() {
+            return;
+        }
+
+        let mut chunk = [0u8; 1024];
+        loop {
+            match self.stream.read( &mut chunk ) {
+                Ok( 0 ) => {
+                    self.closed = true;
+                    break;
+                },
+                Ok( n ) => {
+                    self.buffer.extend_from_slice( &chunk[.. n] );
+                    self.split_lines();
+
+                    //  改行が来ないまま長すぎる行は読むのをやめて切断
+                    if self.buffer.len() > MAX_LINE {
+                        self.buffer.clear();
+                        self.closed = true;
+                        break;
+                    }
+                },
+                Err( e ) if e.kind() == ErrorKind::WouldBlock => break,
+                Err( e ) if e.kind() == ErrorKind::Interrupted => {},
+                Err( _ ) => {
+                    self.closed = true;
+                    break;
+                },
+            }
+        }
+
+        if self.closed {
+            let _ = self.stream.shutdown( std::net::Shutdown::Both );
+            self.pending.push_back( Message::BYE );
+        }
+    }
+
+    //  届いた行をメッセージにする ( 知らない行は捨てる、ERROR は相手が送ったものだけ )
+    fn split_lines( &mut self ) {
+        while let Some( i ) = self.buffer.iter().position( | b | *b == b'\n' ) {
+            let line: Vec<u8> = self.buffer.drain( ..= i ).collect();
+            let line = String::from_utf8_lossy( &line );
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match Message::parse( &line ) {
+                Some( message ) => self.pending.push_back( message ),
+                None => eprintln!( "unknown message: {}", line.trim() ),
+            }
+        }
+    }
+
+    //  相手に知らせて切断
+    pub fn close( &mut self ) {
+        if !self.closed {
+            let _ = self.send( &Message::BYE );
+            let _ = self.stream.shutdown( std::net::Shutdown::Both );
+            self.closed = true;
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop( &mut self ) {
+        self.close();
+    }
+}
+
+//  接続済みの対局相手
+pub struct Session {
+    pub connection: Connection,
+    pub color: Color,       //  自分の色
+    pub name: String,       //  自分の名前
+    pub opponent: String,   //  相手の名前
+    pub clock: Option<( u64, u64 )>,    //  サーバーから届いた残り時間 ( 黒、白 ミリ秒 )
+}
+
+//  待ち受けて 1人目と対局 ( ホストが黒 )
+pub fn host( port: u16, name: &str ) -> io::Result<Session> {
+    let listener = TcpListener::bind( ( "0.0.0.0", port ) )?;
+    println!( "waiting for an opponent on port {} ...", port );
+
+    let ( stream, address ) = listener.accept()?;
+    println!( "connected: {}", address );
+
+    let mut connection = Connection::new( stream )?;
+    let opponent = match connection.receive_wait( HANDSHAKE_TIMEOUT )? {
+        Message::HELLO( version, opponent ) if version == PROTOCOL_VERSION => opponent,
+        Message::HELLO( version, _ ) => {
+            let _ = connection.send( &Message::ERROR( format!( "protocol version {} is not supported", version ) ) );
+            return Err( io::Error::new( ErrorKind::InvalidData, format!( "protocol version mismatch: {}", version ) ) );
+        },
+        _ => return Err( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) ),
+    };
+
+    connection.send( &Message::HELLO( PROTOCOL_VERSION, name.to_string() ) )?;
+    connection.send( &Message::COLOR( Color::WHITE ) )?;
+
+    return Ok( Session {
+        connection,
+        color: Color::BLACK,
+        name: name.to_string(),
+        opponent,
+        clock: None,
+    } );
+}
+
+//  ホストまたはサーバーに接続 ( サーバーならロビーで相手が決まるまで待つ )
+pub fn connect( address: &str, name: &str, challenge: Option<&str> ) -> io::Result<Session> {
+    let address = if address.contains( ':' ) { address.to_string() } else { format!( "{}:{}", address, DEFAULT_PORT ) };
+    let stream = TcpStream::connect( &address )?;
+    let mut connection = Connection::new( stream )?;
+    connection.send( &Message::HELLO( PROTOCOL_VERSION, name.to_string() ) )?;
+
+    let mut opponent = match connection.receive_wait( HANDSHAKE_TIMEOUT )? {
+        Message::HELLO( _, opponent ) => opponent,
+        Message::ERROR( text ) => return Err( io::Error::new( ErrorKind::InvalidData, text ) ),
+        _ => return Err( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) ),
+    };
+
+    let mut in_lobby = false;
+    let color = loop {
+        let message = match connection.receive_wait( HANDSHAKE_TIMEOUT ) {
+            Ok( message ) => message,
+            //  ロビーでは相手が来るまで待ち続ける
+            Err( e ) if in_lobby && e.kind() == ErrorKind::TimedOut => continue,
+            Err( e ) => return Err( e ),
+        };
+
+        match message {
+            Message::COLOR( color ) => break color,
+            Message::LOBBY( players ) => {
+                let players: Vec<String> = players.iter().map( | ( name, rating ) | format!( "{} ({})", name, rating ) ).collect();
+                println!( "lobby: {}", if players.is_empty() { "nobody is waiting".to_string() } else { players.join( ", " ) } );
+                match challenge {
+                    Some( opponent ) => connection.send( &Message::CHALLENGE( opponent.to_string() ) )?,
+                    None => connection.send( &Message::SEEK )?,
+                }
+                println!( "waiting for an opponent ..." );
+                in_lobby = true;
+            },
+            //  サーバーが決めた対局相手
+            Message::HELLO( _, name ) if in_lobby => opponent = name,
+            Message::ERROR( text ) => return Err( io::Error::new( ErrorKind::InvalidData, text ) ),
+            Message::BYE => return Err( io::Error::new( ErrorKind::ConnectionAborted, "disconnected" ) ),
+            _ => return Err( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) ),
+        }
+    };
+
+    return Ok( Session {
+        connection,
+        color,
+        name: name.to_string(),
+        opponent,
+        clock: None,
+    } );
+}
+
+//  観戦中の対局
+pub struct Watch {
+    pub connection: Connection,
+    pub variant: Variant,
+    pub black: String,
+    pub white: String,
+    pub moves: Vec<Vec2<i32>>,          //  観戦を始めるまでの手順
+    pub color: Color,                   //  観戦を始めた時点の手番
+    pub clock: Option<( u64, u64 )>,
+}
+
+//  サーバーまたは中継している対局に接続して観戦 ( player が None なら最新の対局 )
+pub fn watch( address: &str, name: &str, player: Option<&str> ) -> io::Result<Watch> {
+    let address = if address.contains( ':' ) { address.to_string() } else { format!( "{}:{}", address, DEFAULT_PORT ) };
+    let stream = TcpStream::connect( &address )?;
+    let mut connection = Connection::new( stream )?;
+    connection.send( &Message::HELLO( PROTOCOL_VERSION, name.to_string() ) )?;
+
+    match connection.receive_wait( HANDSHAKE_TIMEOUT )? {
+        Message::HELLO( _, _ ) => {},
+        Message::ERROR( text ) => return Err( io::Error::new( ErrorKind::InvalidData, text ) ),
+        _ => return Err( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) ),
+    }
+
+    let mut game = None;
+    let mut moves = None;
+    loop {
+        match connection.receive_wait( HANDSHAKE_TIMEOUT )? {
+            //  サーバーならどの対局を観戦するか伝える
+            Message::LOBBY( _ ) => connection.send( &Message::WATCH( player.map( | p | p.to_string() ) ) )?,
+            Message::GAME( variant, black, white ) => game = Some( ( variant, black, white ) ),
+            Message::HISTORY( history ) => moves = Some( history ),
+            Message::BOARD( position ) => {
+                let ( variant, black, white ) = game.ok_or( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) )?;
+                let moves = moves.unwrap_or_default();
+                let ( board, color ) = Rules::parse_position( &position ).ok_or( io::Error::new( ErrorKind::InvalidData, "invalid board" ) )?;
+
+                //  手順を並べた局面が送られてきた局面と一致するか確かめる
+                let replayed = rules::replay( variant, &moves ).map( | ( rules, _ ) | rules.to_position_string( color ) );
+                if replayed != Some( board.to_position_string( color ) ) {
+                    return Err( io::Error::new( ErrorKind::InvalidData, "history does not match the board" ) );
+                }
+
+                return Ok( Watch { connection, variant, black, white, moves, color, clock: None } );
+            },
+            Message::CLOCK( _, _ ) => {},
+            Message::ERROR( text ) => return Err( io::Error::new( ErrorKind::InvalidData, text ) ),
+            _ => return Err( io::Error::new( ErrorKind::InvalidData, "handshake failed" ) ),
+        }
+    }
+}
+
+//  観戦者への配信 ( 途中から来た観戦者にも最初からの手順を送る )
+pub struct Spectators {
+    listener: Option<TcpListener>,          //  ローカルの対局を中継する場合の待ち受け
+    name: String,                           //  HELLO で名乗る名前
+    waiting: Vec<( Connection, Instant )>,  //  HELLO 待ちの接続
+    watchers: Vec<Connection>,
+    game: Message,                          //  観戦者に送る GAME
+}
+
+impl Spectators {
+    pub fn new( variant: Variant, black: &str, white: &str ) -> Spectators {
+        return Spectators {
+            listener: None,
+            name: String::new(),
+            waiting: Vec::new(),
+            watchers: Vec::new(),
+            game: Message::GAME( variant, spectator_name( black ), spectator_name( white ) ),
+        };
+    }
+
+    //  ローカルの対局を port で中継
+    pub fn listen( port: u16, name: &str, variant: Variant, black: &str, white: &str ) -> io::Result<Spectators> {
+        let listener = TcpListener::bind( ( "0.0.0.0", port ) )?;
+        listener.set_nonblocking( true )?;
+        println!( "broadcasting on port {}", port );
+
+        let mut spectators = Spectators::new( variant, black, white );
+        spectators.listener = Some( listener );
+        spectators.name = name.to_string();
+        return Ok( spectators );
+    }
+
+    //  観戦者を加えて現在までの対局を送る
+    pub fn add( &mut self, mut connection: Connection, moves: &[Vec2<i32>], rules: &Rules, color: Color ) {
+        let game = self.game.clone();
+        let result = connection.send( &game )
+            .and_then( | _ | connection.send( &Message::HISTORY( moves.to_vec() ) ) )
+            .and_then( | _ | connection.send( &Message::BOARD( rules.to_position_string( color ) ) ) );
+
+        if result.is_ok() {
+            self.watchers.push( connection );
+        }
+    }
+
+    //  新しい観戦者の受け付けと切断した観戦者の削除 ( 毎フレーム・毎手呼ぶ )
+    pub fn update( &mut self, moves: &[Vec2<i32>], rules: &Rules, color: Color ) {
+        if let Some( listener ) = self.listener.as_ref() {
+            while let Ok( ( stream, _ ) ) = listener.accept() {
+                if let Ok( connection ) = Connection::new( stream ) {
+                    self.waiting.push( ( connection, Instant::now() ) );
+                }
+            }
+        }
+
+        let mut i = 0;
+        while i < self.waiting.len() {
+            let messages = self.waiting[i].0.receive();
+            if messages.iter().any( | m | matches!( m, Message::HELLO( _, _ ) ) ) {
+                let ( mut connection, _ ) = self.waiting.remove( i );
+                if connection.send( &Message::HELLO( PROTOCOL_VERSION, self.name.clone() ) ).is_ok() {
+                    self.add( connection, moves, rules, color );
+                }
+            } else if !messages.is_empty() || self.waiting[i].1.elapsed() > HANDSHAKE_TIMEOUT {
+                self.waiting.remove( i );
+            } else {
+                i += 1;
+            }
+        }
+
+        //  観戦者からは何も受け取らない ( 切断だけ確認 )
+        for connection in self.watchers.iter_mut() {
+            connection.receive();
+        }
+        self.watchers.retain( | c | !c.closed );
+    }
+
+    pub fn send( &mut self, message: &Message ) {
+        for connection in self.watchers.iter_mut() {
+            let _ = connection.send( message );
+        }
+    }
+
+    //  終局を知らせて観戦者を切断 ( 次の対局は改めて接続してもらう )
+    pub fn finish( &mut self, outcome: Outcome, reason: &str ) {
+        self.send( &Message::RESULT( outcome, reason.to_string() ) );
+        self.watchers.clear();
+    }
+}
+
+//  GAME で空白を区切りに使うので名前の空白は置き換える
+fn spectator_name( name: &str ) -> String {
+    let name: String = name.chars().map( | c | if c.is_whitespace() { '_' } else { c } ).collect();
+    if name.is_empty() { "-".to_string() } else { name }
+}
+
+//  通信での接続 ( 対局相手・観戦先・観戦者への中継 )
+#[derive(Default)]
+pub struct Network {
+    pub session: Option<Session>,
+    pub watch: Option<Watch>,
+    pub spectators: Option<Spectators>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_survives( message: Message ) {
+        let line = message.to_line();
+        assert!( Message::parse( &line ) == Some( message ), "{}", line );
+    }
+
+    //  つながった 2つのソケット ( こちらの Connection と相手の生の TcpStream )
+    fn connected() -> ( Connection, TcpStream ) {
+        let listener = TcpListener::bind( ( "127.0.0.1", 0 ) ).unwrap();
+        let peer = TcpStream::connect( listener.local_addr().unwrap() ).unwrap();
+        let ( stream, _ ) = listener.accept().unwrap();
+        return ( Connection::new( stream ).unwrap(), peer );
+    }
+
+    #[test]
+    fn game_messages_survive_to_line_and_parse() {
+        assert_survives( Message::HELLO( PROTOCOL_VERSION, "alice".to_string() ) );
+        assert_survives( Message::COLOR( Color::WHITE ) );
+        assert_survives( Message::MOVE( Vec2::new( 5, 4 ) ) );
+        assert_survives( Message::MOVE( Vec2::new( 7, 7 ) ) );
+        assert_survives( Message::PASS );
+        assert_survives( Message::RESIGN );
+        assert_survives( Message::DRAW_OFFER );
+        assert_survives( Message::DRAW_ACCEPT );
+        assert_survives( Message::DRAW_DECLINE );
+        assert_survives( Message::CHAT( "good game!".to_string() ) );
+        assert_survives( Message::ERROR( "protocol version 2 is not supported".to_string() ) );
+        assert_survives( Message::BYE );
+    }
+
+    #[test]
+    fn lobby_messages_survive_to_line_and_parse() {
+        assert_survives( Message::LOBBY( vec![ ( "alice".to_string(), 1520 ), ( "bob".to_string(), -30 ) ] ) );
+        assert_survives( Message::LOBBY( Vec::new() ) );
+        assert_survives( Message::SEEK );
+        assert_survives( Message::CHALLENGE( "bob".to_string() ) );
+        assert_survives( Message::CLOCK( 600000, 1234 ) );
+        assert_survives( Message::TIMEOUT( Color::BLACK ) );
+    }
+
+    #[test]
+    fn spectator_messages_survive_to_line_and_parse() {
+        assert_survives( Message::WATCH( None ) );
+        assert_survives( Message::WATCH( Some( "bob".to_string() ) ) );
+        assert_survives( Message::GAME( Variant::CROSS, "alice".to_string(), "bob".to_string() ) );
+        assert_survives( Message::HISTORY( rules::parse_transcript( "f5d6c3" ).unwrap() ) );
+        assert_survives( Message::HISTORY( Vec::new() ) );
+        assert_survives( Message::BOARD( Rules::new( Variant::STANDARD ).to_position_string( Color::WHITE ) ) );
+        assert_survives( Message::RESULT( Outcome::DRAW, "agreement".to_string() ) );
+        assert_survives( Message::RESULT( Outcome::BLACK_WIN, String::new() ) );
+    }
+
+    #[test]
+    fn chat_keeps_to_one_line() {
+        let line = Message::CHAT( "one\ntwo\r".to_string() ).to_line();
+        assert!( !line.contains( '\n' ) && !line.contains( '\r' ) );
+        assert!( Message::parse( &line ) == Some( Message::CHAT( "one two".to_string() ) ) );
+    }
+
+    #[test]
+    fn rejects_unknown_and_broken_lines() {
+        for line in [ "", "FOO", "move f5", "MOVE", "MOVE z9", "MOVE f55", "MOVE ｆ５", "HELLO", "HELLO x alice", "COLOR red",
+            "TIMEOUT", "CLOCK", "CLOCK 1", "CLOCK a b", "CLOCK -1 5", "LOBBY alice", "LOBBY alice:", "LOBBY alice:x", "CHALLENGE",
+            "GAME", "GAME chess a b", "GAME standard alice", "HISTORY f5d", "HISTORY f5z9", "BOARD", "BOARD xyz", "RESULT maybe" ].iter() {
+            assert!( Message::parse( line ).is_none(), "{}", line );
+        }
+    }
+
+    #[test]
+    fn receive_waits_for_the_newline() {
+        let ( mut connection, mut peer ) = connected();
+        peer.write_all( b"MOVE f5\nNOISE\nPASS\n\nCHAT par" ).unwrap();
+        assert!( connection.receive_wait( Duration::from_secs( 5 ) ).unwrap() == Message::MOVE( Vec2::new( 5, 4 ) ) );
+
+        peer.write_all( b"tial\n" ).unwrap();
+        drop( peer );
+        let mut messages = Vec::new();
+        while !messages.contains( &Message::BYE ) {
+            messages.push( connection.receive_wait( Duration::from_secs( 5 ) ).unwrap() );
+        }
+        assert!( messages == vec![ Message::PASS, Message::CHAT( "partial".to_string() ), Message::BYE ] );
+    }
+
+    #[test]
+    fn endless_line_is_cut_off() {
+        let ( mut connection, mut peer ) = connected();
+        peer.write_all( &[ b'x'; MAX_LINE * 4 ] ).unwrap();
+        let _ = peer.write_all( b"\nPASS\n" );
+
+        assert!( connection.receive_wait( Duration::from_secs( 5 ) ).unwrap() == Message::BYE );
+        assert!( connection.buffer.is_empty() );
+        assert!( connection.receive().is_empty() );
+    }
+
+    #[test]
+    fn peer_that_stops_reading_is_dropped() {
+        let ( mut connection, _peer ) = connected();
+        let chat = Message::CHAT( "x".repeat( MAX_LINE - 16 ) );
+        let start = Instant::now();
+
+        //  相手が読まなくても send は待たずに戻り、たまりすぎたら切断する
+        let mut result = Ok(());
+        for _ in 0 .. 100000 {
+            result = connection.send( &chat );
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!( result.is_err() );
+        assert!( start.elapsed() < Duration::from_secs( 5 ) );
+        assert!( connection.receive().contains( &Message::BYE ) );
+        assert!( connection.send( &Message::PASS ).is_err() );
+    }
+}
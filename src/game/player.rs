@@ -2,6 +2,10 @@ use tetra::graphics::{ self, Texture, DrawParams };
 use tetra::input::{ self, MouseButton };
 use tetra::math::Vec2;
 use tetra::{ Context, ContextBuilder, State };
+use super::assets::Assets;
+use super::engine::Engine;
+use super::theme::Theme;
+use super::turn::Kind;
 
 pub enum Color {
     BLACK,
@@ -10,28 +14,46 @@ pub enum Color {
     
 pub struct Player {
     color: Color,
+    kind: Kind,     //  人間 / AI
+    engine: Option<Engine>, //  外部のエンジン ( kind が ENGINE の場合 )
     param: DrawParams,
     selected: bool,
     texture: Texture,
 }
     
 impl Player {
-    pub fn new( color: Color, ctx: &mut Context ) -> tetra::Result<Player> {
+    pub fn new( color: Color, kind: Kind, assets: &mut Assets, theme: &Theme, ctx: &mut Context ) -> tetra::Result<Player> {
         //let color = Color::BLACK;
-        let texture = Texture::new( ctx, "./asset/cursor.png" )?;
+        let texture = assets.texture( ctx, &theme.textures.cursor )?;
         let param = DrawParams::new()
             .position( Vec2::new( 32.0, 32.0 ) )
             .scale( Vec2::new( 0.5, 0.5 ) )
-            .origin( Vec2::new( texture.width() as f32 / 2.0, texture.height() as f32 / 2.0 ) );
+            .origin( Vec2::new( texture.width() as f32 / 2.0, texture.height() as f32 / 2.0 ) )
+            .color( theme.cursor_color() );
         let selected = false;
         Ok( Player {
             color,
+            kind,
+            engine: None,
             param,
             selected,
             texture
         } )
     }
 
+    //  テーマ変更
+    pub fn set_theme( &mut self, ctx: &mut Context, assets: &mut Assets, theme: &Theme ) -> tetra::Result {
+        self.texture = assets.texture( ctx, &theme.textures.cursor )?;
+        self.param.origin = Vec2::new( self.texture.width() as f32 / 2.0, self.texture.height() as f32 / 2.0 );
+        self.param.color = theme.cursor_color();
+        Ok(())
+    }
+
+    //  画面の拡大率変更
+    pub fn set_scale( &mut self, scale: f32 ) {
+        self.param.scale = Vec2::new( 0.5, 0.5 ) * scale;
+    }
+
     pub fn init( &mut self, ctx: &mut Context ) {
         self.selected = false;
     }
@@ -47,7 +69,8 @@ impl Player {
         let param = DrawParams::new()
             .position( self.param.position )
             .scale( self.param.scale )
-            .origin( self.param.origin );
+            .origin( self.param.origin )
+            .color( self.param.color );
         self.texture.draw( ctx, param );
     }
 
@@ -59,6 +82,18 @@ impl Player {
         return self.selected;
     }
 
+    pub fn set_engine( &mut self, engine: Engine ) {
+        self.engine = Some( engine );
+    }
+
+    pub fn get_engine_mut( &mut self ) -> Option<&mut Engine> {
+        return self.engine.as_mut();
+    }
+
+    pub fn get_kind( &self ) -> Kind {
+        return self.kind;
+    }
+
     pub fn cancel( &mut self ) {
         self.selected = false;
     }
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver, TryRecvError };
+use std::thread;
+use tetra::math::Vec2;
+use super::ai;
+use super::analysis::{ self, MoveAnalysis };
+use super::board::cell::Color;
+use super::rules::{ self, Rules, Variant };
+
+//  終局後の振り返り ( 手順から各手の後の局面を作っておき、番号で行き来する )
+pub struct Review {
+    positions: Vec<Rules>,          //  0 が初期配置、i が i手目の後
+    moves: Vec<( Color, Vec2<i32> )>,   //  i + 1 手目を打った側と場所 ( パスは除く )
+    index: usize,                   //  表示中の局面
+    analysis: Vec<MoveAnalysis>,    //  各手の解析 ( 解析が終わるまで空 )
+    analyzing: Option<Receiver<Result<Vec<MoveAnalysis>, String>>>,    //  解析中のスレッドからの結果
+    cancel: Arc<AtomicBool>,        //  振り返りをやめたら解析のスレッドを止める
+}
+
+impl Review {
+    pub fn new( variant: Variant, moves: &[Vec2<i32>] ) -> Review {
+        let mut rules = Rules::new( variant );
+        let mut color = Color::BLACK;
+        let mut review = Review {
+            positions: Vec::with_capacity( moves.len() + 1 ),
+            moves: Vec::with_capacity( moves.len() ),
+            index: 0,
+            analysis: Vec::new(),
+            analyzing: None,
+            cancel: Arc::new( AtomicBool::new( false ) ),
+        };
+        review.positions.push( rules );
+
+        for position in moves.iter() {
+            //  置けない側はパス
+            if !rules.there_is_enable_position( color ) {
+                color = rules::get_enemy_piece( color );
+            }
+            rules.set_piece( position.x, position.y, color );
+            review.positions.push( rules );
+            review.moves.push( ( color, *position ) );
+            color = rules::get_enemy_piece( color );
+        }
+
+        //  最後の局面から始める
+        review.index = moves.len();
+        return review;
+    }
+
+    //  全ての手を AI で解析する ( 時間がかかるので別スレッドで )
+    pub fn start_analysis( &mut self, variant: Variant, moves: &[Vec2<i32>], level: u32 ) {
+        let ( sender, receiver ) = mpsc::channel();
+        let moves = moves.to_vec();
+        let cancel = self.cancel.clone();
+        thread::spawn( move || {
+            let _ = sender.send( analysis::analyze( &moves, variant, level, Some( cancel ) ) );
+        } );
+        self.analyzing = Some( receiver );
+    }
+
+    //  解析の結果が届いたら取り込む ( 届いたら true )
+    pub fn poll_analysis( &mut self ) -> bool {
+        let result = match self.analyzing.as_ref().map( | r | r.try_recv() ) {
+            Some( Ok( result ) ) => result,
+            Some( Err( TryRecvError::Empty ) ) | None => return false,
+            Some( Err( TryRecvError::Disconnected ) ) => Err( "analysis stopped".to_string() ),
+        };
+
+        self.analyzing = None;
+        match result {
+            Ok( analysis ) => self.analysis = analysis,
+            Err( e ) => eprintln!( "analysis: {}", e ),
+        }
+        return true;
+    }
+
+    pub fn is_analyzing( &self ) -> bool {
+        return self.analyzing.is_some();
+    }
+
+    //  n 手目 ( 1から ) の解析
+    pub fn get_analysis( &self, number: usize ) -> Option<&MoveAnalysis> {
+        if number == 0 {
+            return None;
+        }
+        return self.analysis.get( number - 1 );
+    }
+
+    //  各局面の黒から見た評価値 ( 石差。0 が初期配置、i が i手目の後。解析が済んでいなければ空 )
+    pub fn get_evaluations( &self ) -> Vec<f32> {
+        let first = match self.analysis.first() {
+            Some( first ) => first,
+            None => return Vec::new(),
+        };
+        let black = | color: Color, discs: f32 | if color == Color::BLACK { discs } else { -discs };
+
+        let mut values = Vec::with_capacity( self.analysis.len() + 1 );
+        values.push( black( self.moves[0].0, ai::to_discs( first.best_score, first.exact ) ) );
+        for ( analysis, ( color, _ ) ) in self.analysis.iter().zip( self.moves.iter() ) {
+            values.push( black( *color, ai::to_discs( analysis.played_score, analysis.exact ) ) );
+        }
+        return values;
+    }
+
+    pub fn get_index( &self ) -> usize {
+        return self.index;
+    }
+
+    //  手数
+    pub fn get_move_count( &self ) -> usize {
+        return self.moves.len();
+    }
+
+    //  表示中の局面
+    pub fn get_rules( &self ) -> &Rules {
+        return &self.positions[self.index];
+    }
+
+    //  n 手目 ( 1から ) を打った側と場所
+    pub fn get_move( &self, number: usize ) -> Option<( Color, Vec2<i32> )> {
+        if number == 0 {
+            return None;
+        }
+        return self.moves.get( number - 1 ).copied();
+    }
+
+    //  指定した手数の局面へ ( 範囲外は端に合わせる。変わったら true )
+    pub fn go( &mut self, index: usize ) -> bool {
+        let index = index.min( self.moves.len() );
+        if index == self.index {
+            return false;
+        }
+
+        self.index = index;
+        return true;
+    }
+
+    pub fn next( &mut self ) -> bool {
+        return self.go( self.index + 1 );
+    }
+
+    pub fn prev( &mut self ) -> bool {
+        return self.go( self.index.saturating_sub( 1 ) );
+    }
+}
+
+impl Drop for Review {
+    fn drop( &mut self ) {
+        self.cancel.store( true, Ordering::Relaxed );
+    }
+}
@@ -0,0 +1,351 @@
+use tetra::math::Vec2;
+use super::board::{ WIDTH_NUM, HEIGHT_NUM };
+use super::board::cell::Color;
+
+//  8方向
+const DIR_X: [i32; 8] = [ 0, 1, 1, 1, 0, -1, -1, -1 ];
+const DIR_Y: [i32; 8] = [ -1, -1, 0, 1, 1, 1, 0, -1 ];
+
+//  初期配置の種類
+#[derive(Copy, Clone, PartialEq)]
+pub enum Variant {
+    STANDARD,   //  対角配置 ( d4, e5 が白 )
+    CROSS,      //  平行配置 ( d4, d5 が白 )
+}
+
+impl Variant {
+    pub const ALL: [Variant; 2] = [ Variant::STANDARD, Variant::CROSS ];
+
+    pub fn name( self ) -> &'static str {
+        match self {
+            Variant::STANDARD => "standard",
+            Variant::CROSS => "cross",
+        }
+    }
+
+    pub fn parse( text: &str ) -> Option<Variant> {
+        return Variant::ALL.iter().copied().find( | v | v.name() == text );
+    }
+}
+
+//  盤面とルール ( 描画や入力に依存しないので TUI や AI からも使う )
+#[derive(Copy, Clone)]
+pub struct Rules {
+    cells: [[Color; HEIGHT_NUM]; WIDTH_NUM],    //  [x][y]
+    variant: Variant,
+}
+
+impl Rules {
+    pub fn new( variant: Variant ) -> Rules {
+        let mut rules = Rules {
+            cells: [[Color::NONE; HEIGHT_NUM]; WIDTH_NUM],
+            variant,
+        };
+        rules.init();
+        return rules;
+    }
+
+    //  初期配置
+    pub fn init( &mut self ) {
+        self.clear();
+
+        let cx = WIDTH_NUM / 2;
+        let cy = HEIGHT_NUM / 2;
+        match self.variant {
+            Variant::STANDARD => {
+                self.cells[cx - 1][cy - 1] = Color::WHITE;
+                self.cells[cx][cy] = Color::WHITE;
+                self.cells[cx - 1][cy] = Color::BLACK;
+                self.cells[cx][cy - 1] = Color::BLACK;
+            },
+            Variant::CROSS => {
+                self.cells[cx - 1][cy - 1] = Color::WHITE;
+                self.cells[cx - 1][cy] = Color::WHITE;
+                self.cells[cx][cy - 1] = Color::BLACK;
+                self.cells[cx][cy] = Color::BLACK;
+            },
+        }
+    }
+
+    pub fn get_variant( &self ) -> Variant {
+        return self.variant;
+    }
+
+    //  初期配置の種類を変更 ( 盤面も初期化 )
+    pub fn set_variant( &mut self, variant: Variant ) {
+        self.variant = variant;
+        self.init();
+    }
+
+    //  局面文字列から作成
+    //  a1, b1 .. h1, a2 .. h8 の順に 64 文字 ( X: 黒, O: 白, - : 空 ) + 手番 ( X / O、省略時は黒 )
+    pub fn parse_position( text: &str ) -> Option<( Rules, Color )> {
+        let text: Vec<char> = text.chars().filter( | c | !c.is_whitespace() ).collect();
+        let cell_num = WIDTH_NUM * HEIGHT_NUM;
+        if text.len() != cell_num && text.len() != cell_num + 1 {
+            return None;
+        }
+
+        let mut rules = Rules::new( Variant::STANDARD );
+        rules.clear();
+        for ( i, c ) in text[.. cell_num].iter().enumerate() {
+            rules.cells[i % WIDTH_NUM][i / WIDTH_NUM] = parse_piece( *c )?;
+        }
+
+        let color = match text.get( cell_num ) {
+            Some( c ) => parse_piece( *c )?,
+            None => Color::BLACK,
+        };
+        if color == Color::NONE {
+            return None;
+        }
+
+        return Some( ( rules, color ) );
+    }
+
+    //  局面文字列 ( parse_position の逆 )
+    pub fn to_position_string( &self, color: Color ) -> String {
+        let mut text = String::with_capacity( WIDTH_NUM * HEIGHT_NUM + 2 );
+        for k in 0 .. HEIGHT_NUM {
+            for i in 0 .. WIDTH_NUM {
+                text.push( piece_char( self.cells[i][k] ) );
+            }
+        }
+        text.push( ' ' );
+        text.push( piece_char( color ) );
+        return text;
+    }
+
+    //  全マスを空にする
+    pub fn clear( &mut self ) {
+        self.cells = [[Color::NONE; HEIGHT_NUM]; WIDTH_NUM];
+    }
+
+    pub fn get_piece( &self, x: i32, y: i32 ) -> Color {
+        if !Rules::is_inside( x, y ) {
+            return Color::NONE;
+        }
+
+        return self.cells[x as usize][y as usize];
+    }
+
+    //  指定したマスがいくつあるか
+    pub fn get_piece_count( &self, color: Color ) -> i32 {
+        let mut count = 0;
+
+        for i in 0 .. WIDTH_NUM {
+            for k in 0 .. HEIGHT_NUM {
+                if self.cells[i][k] == color {
+                    count += 1;
+                }
+            }
+        }
+
+        return count;
+    }
+
+    //  指定マスに置いたときに裏返るマス ( 置けなければ空 )
+    pub fn get_reverse_positions( &self, x: i32, y: i32, self_piece: Color ) -> Vec<Vec2<i32>> {
+        let mut reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
+
+        if !Rules::is_inside( x, y ) || !self.is_open( x, y ) || self_piece == Color::NONE {
+            return reverse_position;
+        }
+
+        for i in 0 .. DIR_X.len() {
+            self.check_near_my_piece( x, y, DIR_X[i], DIR_Y[i], self_piece, &mut reverse_position );
+        }
+
+        return reverse_position;
+    }
+
+    //  指定マスに置けるか
+    pub fn can_piece( &self, x: i32, y: i32, self_piece: Color ) -> bool {
+        if !Rules::is_inside( x, y ) || !self.is_open( x, y ) || self_piece == Color::NONE {
+            return false;
+        }
+
+        let mut reverse_position: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM );
+        for i in 0 .. DIR_X.len() {
+            self.check_near_my_piece( x, y, DIR_X[i], DIR_Y[i], self_piece, &mut reverse_position );
+            if reverse_position.len() > 0 {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    //  置けるマスの一覧
+    pub fn get_enable_positions( &self, self_piece: Color ) -> Vec<Vec2<i32>> {
+        let mut positions: Vec<Vec2<i32>> = Vec::with_capacity( WIDTH_NUM * HEIGHT_NUM );
+
+        for k in 0 .. HEIGHT_NUM as i32 {
+            for i in 0 .. WIDTH_NUM as i32 {
+                if self.can_piece( i, k, self_piece ) {
+                    positions.push( Vec2::new( i, k ) );
+                }
+            }
+        }
+
+        return positions;
+    }
+
+    pub fn there_is_enable_position( &self, self_piece: Color ) -> bool {
+        for i in 0 .. WIDTH_NUM as i32 {
+            for k in 0 .. HEIGHT_NUM as i32 {
+                if self.can_piece( i, k, self_piece ) {
+                    return true;
+                }
+            }
+        }
+
+        return false;
+    }
+
+    //  指定したマスに設置して裏返す ( 置けなければ None、置けたら裏返した数 )
+    pub fn set_piece( &mut self, x: i32, y: i32, self_piece: Color ) -> Option<usize> {
+        let reverse_position = self.get_reverse_positions( x, y, self_piece );
+        if reverse_position.len() == 0 {
+            return None;
+        }
+
+        for iter in reverse_position.iter() {
+            self.cells[iter.x as usize][iter.y as usize] = self_piece;
+        }
+
+        self.cells[x as usize][y as usize] = self_piece;
+        return Some( reverse_position.len() );
+    }
+
+    //  指定したマスから一番近い自分のマスまでに挟んだ相手のマスを追加
+    fn check_near_my_piece( &self, x: i32, y: i32, dir_x: i32, dir_y: i32, self_piece: Color, reverse_position: &mut Vec<Vec2<i32>> ) {
+        let start = reverse_position.len();
+        let mut dir = 1;
+
+        while Rules::is_inside( x + dir_x * dir, y + dir_y * dir ) {
+            let offset_x = x + dir_x * dir;
+            let offset_y = y + dir_y * dir;
+            let offset_piece = self.cells[offset_x as usize][offset_y as usize];
+
+            if offset_piece == Color::NONE {
+                break;
+            }
+
+            if offset_piece == self_piece {
+                return;
+            }
+
+            //  裏返せるピース座標
+            reverse_position.push( Vec2::new( offset_x, offset_y ) );
+            dir += 1;
+        }
+
+        //  自分のマスで挟めなかった
+        reverse_position.truncate( start );
+    }
+
+    //  指定したマスが内側かどうか
+    pub fn is_inside( x: i32, y: i32 ) -> bool {
+        if x < 0 || y < 0 {
+            return false;
+        }
+
+        if x as usize >= WIDTH_NUM || y as usize >= HEIGHT_NUM {
+            return false;
+        }
+
+        return true;
+    }
+
+    //  指定したマスが空かどうか
+    fn is_open( &self, x: i32, y: i32 ) -> bool {
+        return self.cells[x as usize][y as usize] == Color::NONE;
+    }
+}
+
+//  相手のピース取得
+pub fn get_enemy_piece( self_piece: Color ) -> Color {
+    match self_piece {
+        Color::NONE => return Color::NONE,
+        Color::BLACK => return Color::WHITE,
+        Color::WHITE => return Color::BLACK,
+    }
+}
+
+fn parse_piece( c: char ) -> Option<Color> {
+    match c {
+        'X' | 'x' | '*' | 'B' | 'b' => return Some( Color::BLACK ),
+        'O' | 'o' | 'W' | 'w' => return Some( Color::WHITE ),
+        '-' | '.' | '_' => return Some( Color::NONE ),
+        _ => return None,
+    }
+}
+
+fn piece_char( color: Color ) -> char {
+    match color {
+        Color::NONE => return '-',
+        Color::BLACK => return 'X',
+        Color::WHITE => return 'O',
+    }
+}
+
+//  マス座標 -> "d3" 形式
+pub fn to_notation( position: Vec2<i32> ) -> String {
+    return format!( "{}{}", ( b'a' + position.x as u8 ) as char, position.y + 1 );
+}
+
+//  "d3" 形式 -> マス座標 ( 大文字も可 )
+pub fn parse_notation( text: &str ) -> Option<Vec2<i32>> {
+    let text = text.trim().to_ascii_lowercase();
+    let mut chars = text.chars();
+    let column = chars.next()?;
+    let row: i32 = chars.as_str().parse().ok()?;
+
+    if !column.is_ascii_lowercase() {
+        return None;
+    }
+
+    let position = Vec2::new( column as i32 - 'a' as i32, row - 1 );
+    if !Rules::is_inside( position.x, position.y ) {
+        return None;
+    }
+
+    return Some( position );
+}
+
+//  棋譜 ( "f5d6c3..." 空白区切りも可 ) -> マス座標の列
+pub fn parse_transcript( text: &str ) -> Option<Vec<Vec2<i32>>> {
+    let text: String = text.chars().filter( | c | !c.is_whitespace() ).collect();
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return None;
+    }
+
+    let mut moves = Vec::with_capacity( text.len() / 2 );
+    for i in ( 0 .. text.len() ).step_by( 2 ) {
+        moves.push( parse_notation( &text[i .. i + 2] )? );
+    }
+
+    return Some( moves );
+}
+
+//  マス座標の列 -> 棋譜
+pub fn to_transcript( moves: &[Vec2<i32>] ) -> String {
+    return moves.iter().map( | p | to_notation( *p ) ).collect();
+}
+
+//  初期配置から棋譜を並べた局面と次の手番 ( 置けない側はパスとして飛ばす )
+pub fn replay( variant: Variant, moves: &[Vec2<i32>] ) -> Option<( Rules, Color )> {
+    let mut rules = Rules::new( variant );
+    let mut color = Color::BLACK;
+
+    for position in moves.iter() {
+        if !rules.there_is_enable_position( color ) {
+            color = get_enemy_piece( color );
+        }
+        rules.set_piece( position.x, position.y, color )?;
+        color = get_enemy_piece( color );
+    }
+
+    return Some( ( rules, color ) );
+}
@@ -0,0 +1,740 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ self, ErrorKind };
+use std::net::TcpListener;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::sync::mpsc::{ self, Receiver, Sender };
+use std::thread;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use tetra::math::Vec2;
+use super::board::cell::Color;
+use super::ggf::{ self, GameResult, Ending, Record };
+use super::net::{ self, Connection, Message, Spectators };
+use super::rules::{ self, Rules, Variant };
+use super::turn::{ self, Outcome, Phase, Turn, PLAYER };
+
+pub const DEFAULT_TIME: u64 = 600;      //  持ち時間 ( 秒 )
+pub const DEFAULT_DIRECTORY: &str = "./server";  //  レーティングと棋譜の保存先
+
+const SERVER_NAME: &str = "server";
+const RATINGS_FILE: &str = "ratings.toml";
+const INITIAL_RATING: i32 = 1500;
+const ELO_K: f64 = 32.0;            //  1局でのレーティング変動の大きさ
+const MAX_NAME_LENGTH: usize = 32;
+
+//  ロビーでの希望
+#[derive(Clone, PartialEq)]
+enum Request {
+    NONE,               //  HELLO 直後
+    SEEK,               //  誰とでも ( レーティングの近い相手 )
+    CHALLENGE( String ),//  名前を指定
+}
+
+//  ロビーにいる接続
+struct Client {
+    connection: Connection,
+    name: Option<String>,   //  HELLO を受け取るまでは None
+    request: Request,
+    joined: Instant,
+    leave: bool,            //  切断・エラーでロビーから外す
+    watch: Option<Sender<Connection>>,  //  観戦する対局 ( 対局のスレッドに接続を渡す )
+}
+
+//  進行中の対局 ( 観戦の受け付け用 )
+struct LiveGame {
+    id: u64,
+    black: String,
+    white: String,
+    watchers: Sender<Connection>,
+}
+
+//  レーティング ( 名前ごと。ratings.toml に保存 )
+struct Ratings {
+    path: PathBuf,
+    table: BTreeMap<String, i32>,
+}
+
+impl Ratings {
+    fn load( path: PathBuf ) -> Ratings {
+        let table = match fs::read_to_string( &path ) {
+            Ok( source ) => toml::from_str( &source ).unwrap_or_else( | e | {
+                eprintln!( "{}: {}", path.display(), e );
+                BTreeMap::new()
+            } ),
+            Err( _ ) => BTreeMap::new(),
+        };
+
+        return Ratings { path, table };
+    }
+
+    fn get( &self, name: &str ) -> i32 {
+        return *self.table.get( name ).unwrap_or( &INITIAL_RATING );
+    }
+
+    //  イロレーティングで更新して保存
+    fn update( &mut self, black: &str, white: &str, outcome: Outcome ) {
+        let black_rating = self.get( black );
+        let white_rating = self.get( white );
+        let expected = 1.0 / ( 1.0 + 10f64.powf( ( white_rating - black_rating ) as f64 / 400.0 ) );
+        let score = match outcome {
+            Outcome::BLACK_WIN => 1.0,
+            Outcome::WHITE_WIN => 0.0,
+            Outcome::DRAW => 0.5,
+        };
+        let delta = ( ELO_K * ( score - expected ) ).round() as i32;
+
+        self.table.insert( black.to_string(), black_rating + delta );
+        self.table.insert( white.to_string(), white_rating - delta );
+
+        let result = toml::to_string( &self.table )
+            .map_err( | e | e.to_string() )
+            .and_then( | source | fs::write( &self.path, source ).map_err( | e | e.to_string() ) );
+        if let Err( e ) = result {
+            eprintln!( "{}: {}", self.path.display(), e );
+        }
+    }
+}
+
+//  サーバーの設定
+#[derive(Clone)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub variant: Variant,
+    pub time: Duration,         //  1人あたりの持ち時間
+    pub directory: PathBuf,     //  レーティングと棋譜の保存先
+}
+
+//  サーバーを起動 ( ロビーはこのスレッド、対局は 1局ごとにスレッドを立てる )
+pub fn serve( config: &ServerConfig ) -> io::Result<()> {
+    fs::create_dir_all( &config.directory )?;
+    let ratings = Arc::new( Mutex::new( Ratings::load( config.directory.join( RATINGS_FILE ) ) ) );
+
+    let listener = TcpListener::bind( ( "0.0.0.0", config.port ) )?;
+    listener.set_nonblocking( true )?;
+    println!( "server listening on port {} ( {}, {} seconds each )", config.port, config.variant.name(), config.time.as_secs() );
+
+    let games: Arc<Mutex<Vec<LiveGame>>> = Arc::new( Mutex::new( Vec::new() ) );
+    let mut clients: Vec<Client> = Vec::new();
+    let mut game_id = 0;
+    loop {
+        accept( &listener, &mut clients );
+
+        for i in 0 .. clients.len() {
+            for message in clients[i].connection.receive() {
+                handle_lobby( &mut clients, i, message, &ratings, &games );
+            }
+
+            if clients[i].name.is_none() && clients[i].joined.elapsed() > net::HANDSHAKE_TIMEOUT {
+                clients[i].leave = true;
+            }
+        }
+
+        clients.retain( | c | {
+            if c.leave {
+                println!( "left: {}", c.name.as_deref().unwrap_or( "-" ) );
+            }
+            !c.leave
+        } );
+
+        //  観戦者は対局のスレッドへ
+        let mut i = 0;
+        while i < clients.len() {
+            if clients[i].watch.is_none() {
+                i += 1;
+                continue;
+            }
+
+            let client = clients.remove( i );
+            println!( "watching: {}", client.name.as_deref().unwrap_or( "-" ) );
+            if let Some( watchers ) = client.watch {
+                let _ = watchers.send( client.connection );
+            }
+        }
+
+        while let Some( ( black, white ) ) = find_pair( &clients, &ratings ) {
+            //  後ろから外して添字がずれないようにする
+            let ( black, white ) = if black > white {
+                let black = clients.remove( black );
+                ( black, clients.remove( white ) )
+            } else {
+                let white = clients.remove( white );
+                ( clients.remove( black ), white )
+            };
+            game_id += 1;
+            start_game( game_id, black, white, config, &ratings, &games );
+        }
+
+        thread::sleep( net::POLL_INTERVAL );
+    }
+}
+
+//  新しい接続をロビーに入れる
+fn accept( listener: &TcpListener, clients: &mut Vec<Client> ) {
+    loop {
+        match listener.accept() {
+            Ok( ( stream, address ) ) => {
+                match Connection::new( stream ) {
+                    Ok( connection ) => {
+                        println!( "connected: {}", address );
+                        clients.push( Client {
+                            connection,
+                            name: None,
+                            request: Request::NONE,
+                            joined: Instant::now(),
+                            leave: false,
+                            watch: None,
+                        } );
+                    },
+                    Err( e ) => eprintln!( "{}: {}", address, e ),
+                }
+            },
+            Err( e ) if e.kind() == ErrorKind::WouldBlock => return,
+            Err( e ) => {
+                eprintln!( "accept: {}", e );
+                return;
+            },
+        }
+    }
+}
+
+fn handle_lobby( clients: &mut Vec<Client>, index: usize, message: Message, ratings: &Arc<Mutex<Ratings>>, games: &Arc<Mutex<Vec<LiveGame>>> ) {
+    match message {
+        Message::HELLO( version, name ) if clients[index].name.is_none() => {
+            let error = if version != net::PROTOCOL_VERSION {
+                Some( format!( "protocol version {} is not supported", version ) )
+            } else if !is_valid_name( &name ) {
+                Some( format!( "invalid name: {}", name ) )
+            } else if clients.iter().any( | c | c.name.as_deref() == Some( name.as_str() ) )
+                || games.lock().unwrap().iter().any( | g | g.black == name || g.white == name ) {
+                //  対局中のプレイヤーとも重ならないように ( 同じレーティングを 2局で更新してしまう )
+                Some( format!( "name already in use: {}", name ) )
+            } else {
+                None
+            };
+
+            let client = &mut clients[index];
+            if let Some( text ) = error {
+                let _ = client.connection.send( &Message::ERROR( text ) );
+                client.leave = true;
+                return;
+            }
+
+            println!( "hello: {}", name );
+            client.name = Some( name );
+            let _ = client.connection.send( &Message::HELLO( net::PROTOCOL_VERSION, SERVER_NAME.to_string() ) );
+
+            let lobby = lobby_players( clients, ratings );
+            let _ = clients[index].connection.send( &Message::LOBBY( lobby ) );
+        },
+        Message::SEEK | Message::CHALLENGE( _ ) => {
+            let client = &mut clients[index];
+            if client.name.is_none() {
+                let _ = client.connection.send( &Message::ERROR( "send HELLO first".to_string() ) );
+                client.leave = true;
+                return;
+            }
+            if let Message::CHALLENGE( name ) = &message {
+                if client.name.as_ref() == Some( name ) {
+                    let _ = client.connection.send( &Message::ERROR( "cannot challenge yourself".to_string() ) );
+                    return;
+                }
+            }
+
+            client.request = match message {
+                Message::CHALLENGE( name ) => Request::CHALLENGE( name ),
+                _ => Request::SEEK,
+            };
+        },
+        //  プレイヤー名で対局を探す ( 指定が無ければ最新の対局 )
+        Message::WATCH( player ) => {
+            let client = &mut clients[index];
+            if client.name.is_none() {
+                let _ = client.connection.send( &Message::ERROR( "send HELLO first".to_string() ) );
+                client.leave = true;
+                return;
+            }
+
+            let games = games.lock().unwrap();
+            let game = match player.as_ref() {
+                Some( name ) => games.iter().find( | g | &g.black == name || &g.white == name ),
+                None => games.last(),
+            };
+            match game {
+                Some( game ) => client.watch = Some( game.watchers.clone() ),
+                None => {
+                    let _ = client.connection.send( &Message::ERROR( "no game to watch".to_string() ) );
+                    client.leave = true;
+                },
+            }
+        },
+        Message::BYE => clients[index].leave = true,
+        Message::ERROR( text ) => {
+            eprintln!( "{}: {}", clients[index].name.as_deref().unwrap_or( "-" ), text );
+            clients[index].leave = true;
+        },
+        _ => {},
+    }
+}
+
+//  名前は空白無し ( LOBBY で区切りに使う )
+fn is_valid_name( name: &str ) -> bool {
+    return !name.is_empty() && name.len() <= MAX_NAME_LENGTH && name != SERVER_NAME
+        && !name.chars().any( | c | c.is_whitespace() || c.is_control() || c == ':' );
+}
+
+//  対局相手を待っているプレイヤー
+fn lobby_players( clients: &[Client], ratings: &Arc<Mutex<Ratings>> ) -> Vec<( String, i32 )> {
+    let ratings = ratings.lock().unwrap();
+    return clients.iter()
+        .filter( | c | c.request != Request::NONE )
+        .filter_map( | c | c.name.as_ref() )
+        .map( | name | ( name.clone(), ratings.get( name ) ) )
+        .collect();
+}
+
+//  組み合わせを探す ( 名前の指定を優先、次にレーティングの近い相手。先に待っていた方が黒 )
+fn find_pair( clients: &[Client], ratings: &Arc<Mutex<Ratings>> ) -> Option<( usize, usize )> {
+    for ( i, client ) in clients.iter().enumerate() {
+        let ( name, target ) = match ( &client.name, &client.request ) {
+            ( Some( name ), Request::CHALLENGE( target ) ) => ( name, target ),
+            _ => continue,
+        };
+
+        let opponent = clients.iter().enumerate().position( | ( k, c ) | {
+            k != i && c.name.as_ref() == Some( target ) && match &c.request {
+                Request::SEEK => true,
+                Request::CHALLENGE( t ) => t == name,
+                Request::NONE => false,
+            }
+        } );
+        if let Some( k ) = opponent {
+            return Some( ( i, k ) );
+        }
+    }
+
+    let seekers: Vec<usize> = ( 0 .. clients.len() ).filter( | i | clients[*i].request == Request::SEEK ).collect();
+    if seekers.len() < 2 {
+        return None;
+    }
+
+    let ratings = ratings.lock().unwrap();
+    let rating = | i: usize | ratings.get( clients[i].name.as_deref().unwrap_or( "" ) );
+    let first = seekers[0];
+    let opponent = seekers[1 ..].iter().min_by_key( | k | ( rating( **k ) - rating( first ) ).abs() )?;
+    return Some( ( first, *opponent ) );
+}
+
+//  対局中のプレイヤー
+struct Seat {
+    connection: Connection,
+    name: String,
+}
+
+//  終局 ( 勝敗が None なら中断 )
+struct End {
+    outcome: Option<Outcome>,
+    reason: &'static str,
+}
+
+fn start_game( id: u64, black: Client, white: Client, config: &ServerConfig, ratings: &Arc<Mutex<Ratings>>, games: &Arc<Mutex<Vec<LiveGame>>> ) {
+    let mut seats = [
+        Seat { connection: black.connection, name: black.name.unwrap_or_default() },
+        Seat { connection: white.connection, name: white.name.unwrap_or_default() },
+    ];
+    println!( "game: {} vs {}", seats[0].name, seats[1].name );
+
+    //  相手の名前と色を知らせる ( クライアントは COLOR を受け取ったら対局開始 )
+    for ( i, color ) in [ Color::BLACK, Color::WHITE ].iter().enumerate() {
+        let opponent = seats[1 - i].name.clone();
+        let _ = seats[i].connection.send( &Message::HELLO( net::PROTOCOL_VERSION, opponent ) );
+        let _ = seats[i].connection.send( &Message::COLOR( *color ) );
+    }
+
+    let ( sender, receiver ) = mpsc::channel();
+    games.lock().unwrap().push( LiveGame {
+        id,
+        black: seats[0].name.clone(),
+        white: seats[1].name.clone(),
+        watchers: sender,
+    } );
+
+    let config = config.clone();
+    let ratings = Arc::clone( ratings );
+    let games = Arc::clone( games );
+    thread::spawn( move || {
+        let mut game = Match::new( seats, config.variant, config.time, receiver );
+        let end = game.run();
+        games.lock().unwrap().retain( | g | g.id != id );
+        game.finish( &end, &config, &ratings );
+    } );
+}
+
+//  1局分の進行 ( 手の正当性と持ち時間はサーバーで判定する )
+struct Match {
+    seats: [Seat; 2],   //  黒、白
+    rules: Rules,
+    turn: Turn,
+    phase: Phase,
+    moves: Vec<Vec2<i32>>,
+    history: Vec<ggf::Move>,    //  パスと消費時間を含めた手順 ( 棋譜用 )
+    remaining: [Duration; 2],
+    turn_start: Instant,
+    draw_offer: Option<usize>,  //  引き分けを提案している側
+    spectators: Spectators,
+    watchers: Receiver<Connection>, //  ロビーから届く観戦者
+}
+
+impl Match {
+    fn new( seats: [Seat; 2], variant: Variant, time: Duration, watchers: Receiver<Connection> ) -> Match {
+        let rules = Rules::new( variant );
+        let mut turn = Turn::new();
+        let phase = turn.start( &rules );
+        let spectators = Spectators::new( variant, &seats[0].name, &seats[1].name );
+
+        return Match {
+            seats,
+            rules,
+            turn,
+            phase,
+            moves: Vec::with_capacity( 60 ),
+            history: Vec::with_capacity( 64 ),
+            remaining: [ time, time ],
+            turn_start: Instant::now(),
+            draw_offer: None,
+            spectators,
+            watchers,
+        };
+    }
+
+    fn run( &mut self ) -> End {
+        self.send_clock();
+
+        loop {
+            let player = self.turn.get_player() as usize;
+            if self.turn_start.elapsed() >= self.remaining[player] {
+                self.remaining[player] = Duration::from_secs( 0 );
+                self.broadcast( &Message::TIMEOUT( self.turn.get_color() ) );
+                return End { outcome: Some( win_outcome( 1 - player ) ), reason: "time" };
+            }
+
+            while let Ok( connection ) = self.watchers.try_recv() {
+                self.spectators.add( connection, &self.moves, &self.rules, self.turn.get_color() );
+            }
+            self.spectators.update( &self.moves, &self.rules, self.turn.get_color() );
+
+            for seat in 0 .. 2 {
+                for message in self.seats[seat].connection.receive() {
+                    if let Some( end ) = self.handle( seat, message ) {
+                        return end;
+                    }
+                }
+            }
+
+            thread::sleep( net::POLL_INTERVAL );
+        }
+    }
+
+    fn handle( &mut self, seat: usize, message: Message ) -> Option<End> {
+        let other = 1 - seat;
+        let is_turn = self.turn.get_player() as usize == seat;
+
+        match message {
+            Message::MOVE( position ) => {
+                if !is_turn || self.phase != Phase::PLAY || self.rules.set_piece( position.x, position.y, self.turn.get_color() ).is_none() {
+                    return Some( self.forfeit( seat, format!( "illegal move {}", rules::to_notation( position ) ) ) );
+                }
+                self.moves.push( position );
+                self.push_history( Some( position ) );
+                self.send( other, &Message::MOVE( position ) );
+                self.spectators.send( &Message::MOVE( position ) );
+                return self.end_turn();
+            },
+            Message::PASS => {
+                if !is_turn || self.phase != Phase::PASS {
+                    return Some( self.forfeit( seat, "illegal pass".to_string() ) );
+                }
+                self.push_history( None );
+                self.send( other, &Message::PASS );
+                self.spectators.send( &Message::PASS );
+                return self.end_turn();
+            },
+            Message::RESIGN => {
+                self.send( other, &Message::RESIGN );
+                return Some( End { outcome: Some( win_outcome( other ) ), reason: "resign" } );
+            },
+            Message::DRAW_OFFER => {
+                self.draw_offer = Some( seat );
+                self.send( other, &Message::DRAW_OFFER );
+            },
+            Message::DRAW_ACCEPT | Message::DRAW_DECLINE => {
+                //  提案されていない返事は無視
+                if self.draw_offer != Some( other ) {
+                    return None;
+                }
+                self.draw_offer = None;
+                self.send( other, &message );
+                if message == Message::DRAW_ACCEPT {
+                    return Some( End { outcome: Some( Outcome::DRAW ), reason: "agreement" } );
+                }
+            },
+            Message::CHAT( text ) => self.send( other, &Message::CHAT( text ) ),
+            Message::ERROR( text ) => {
+                eprintln!( "{}: {}", self.seats[seat].name, text );
+                self.send( other, &Message::ERROR( text ) );
+                return Some( End { outcome: None, reason: "error" } );
+            },
+            //  切断は負け ( 相手には投了として知らせる )
+            Message::BYE => {
+                self.send( other, &Message::RESIGN );
+                return Some( End { outcome: Some( win_outcome( other ) ), reason: "disconnect" } );
+            },
+            _ => {},
+        }
+
+        return None;
+    }
+
+    //  棋譜に手と消費時間を残す
+    fn push_history( &mut self, position: Option<Vec2<i32>> ) {
+        let mut record = ggf::Move::new( self.turn.get_color(), position );
+        record.time = Some( self.turn_start.elapsed().as_secs_f32() );
+        self.history.push( record );
+    }
+
+    //  手番を終えて持ち時間を減らす
+    fn end_turn( &mut self ) -> Option<End> {
+        let player = self.turn.get_player() as usize;
+        self.remaining[player] = self.remaining[player].saturating_sub( self.turn_start.elapsed() );
+        self.turn_start = Instant::now();
+
+        if self.turn.finish( &self.rules ) == Phase::END {
+            return Some( End { outcome: Some( turn::get_outcome( &self.rules ) ), reason: "finished" } );
+        }
+
+        self.phase = self.turn.start( &self.rules );
+        if self.phase == Phase::END {
+            return Some( End { outcome: Some( turn::get_outcome( &self.rules ) ), reason: "finished" } );
+        }
+
+        self.send_clock();
+        return None;
+    }
+
+    //  不正な手は負け
+    fn forfeit( &mut self, seat: usize, text: String ) -> End {
+        eprintln!( "{}: {}", self.seats[seat].name, text );
+        self.send( seat, &Message::ERROR( text ) );
+        self.send( 1 - seat, &Message::RESIGN );
+        return End { outcome: Some( win_outcome( 1 - seat ) ), reason: "forfeit" };
+    }
+
+    fn send_clock( &mut self ) {
+        let black = self.remaining[PLAYER::ONE as usize].as_millis() as u64;
+        let white = self.remaining[PLAYER::TWO as usize].as_millis() as u64;
+        self.broadcast( &Message::CLOCK( black, white ) );
+        self.spectators.send( &Message::CLOCK( black, white ) );
+    }
+
+    fn send( &mut self, seat: usize, message: &Message ) {
+        let _ = self.seats[seat].connection.send( message );
+    }
+
+    fn broadcast( &mut self, message: &Message ) {
+        self.send( 0, message );
+        self.send( 1, message );
+    }
+
+    //  レーティングの更新と棋譜の保存 ( 中断した対局は残さない )
+    fn finish( &mut self, end: &End, config: &ServerConfig, ratings: &Arc<Mutex<Ratings>> ) {
+        let black = self.seats[0].name.clone();
+        let white = self.seats[1].name.clone();
+        let outcome = match end.outcome {
+            Some( outcome ) => outcome,
+            None => {
+                println!( "aborted: {} vs {} ( {} )", black, white, end.reason );
+                return;
+            },
+        };
+
+        self.spectators.finish( outcome, end.reason );
+
+        let ( black_rating, white_rating ) = {
+            let mut ratings = ratings.lock().unwrap();
+            let before = ( ratings.get( &black ), ratings.get( &white ) );
+            ratings.update( &black, &white, outcome );
+            before
+        };
+        println!( "result: {} vs {}: {} ( {} )", black, white, outcome.name(), end.reason );
+
+        let path = record_path( &config.directory, &black, &white );
+        let record = self.record( end, outcome, config, black_rating, white_rating );
+        if let Err( e ) = record.save( &path ) {
+            eprintln!( "{}: {}", path.display(), e );
+        }
+    }
+
+    //  GGF の棋譜 ( 途中で終わった対局は勝った側が空きマスを取った石の差にする )
+    fn record( &self, end: &End, outcome: Outcome, config: &ServerConfig, black_rating: i32, white_rating: i32 ) -> Record {
+        let mut record = Record::new( config.variant );
+        let seconds = SystemTime::now().duration_since( UNIX_EPOCH ).map( | d | d.as_secs() ).unwrap_or( 0 );
+        record.date = ggf::format_date( seconds );
+        record.black = self.seats[0].name.clone();
+        record.white = self.seats[1].name.clone();
+        record.black_rating = Some( black_rating as f32 );
+        record.white_rating = Some( white_rating as f32 );
+        record.time = format!( "{}:{:02}//", config.time.as_secs() / 60, config.time.as_secs() % 60 );
+        record.moves = self.history.clone();
+
+        let black_count = self.rules.get_piece_count( Color::BLACK );
+        let white_count = self.rules.get_piece_count( Color::WHITE );
+        let empty_count = self.rules.get_piece_count( Color::NONE );
+        let ending = match end.reason {
+            "time" => Ending::TIME,
+            "agreement" => Ending::AGREEMENT,
+            "finished" => Ending::FINISHED,
+            _ => Ending::RESIGN,
+        };
+        let score = match ( ending, outcome ) {
+            ( _, Outcome::DRAW ) => 0,
+            ( Ending::FINISHED, _ ) => black_count - white_count,
+            ( _, Outcome::BLACK_WIN ) => ( black_count + empty_count - white_count ).max( 1 ),
+            ( _, Outcome::WHITE_WIN ) => ( black_count - white_count - empty_count ).min( -1 ),
+        };
+        record.result = Some( GameResult { score: score as f32, ending } );
+        return record;
+    }
+}
+
+fn win_outcome( seat: usize ) -> Outcome {
+    if seat == PLAYER::ONE as usize { Outcome::BLACK_WIN } else { Outcome::WHITE_WIN }
+}
+
+//  棋譜のファイル名 ( 終局時刻-黒-白.ggf )
+fn record_path( directory: &Path, black: &str, white: &str ) -> PathBuf {
+    let time = SystemTime::now().duration_since( UNIX_EPOCH ).map( | d | d.as_millis() ).unwrap_or( 0 );
+    let safe = | name: &str | -> String {
+        name.chars().map( | c | if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' } ).collect()
+    };
+    return directory.join( format!( "{}-{}-{}.ggf", time, safe( black ), safe( white ) ) );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    //  テストごとに別の保存先 ( 前回の結果が残らないように消しておく )
+    fn empty_ratings( test: &str ) -> Ratings {
+        let path = std::env::temp_dir().join( format!( "othello_ratings_{}_{}.toml", test, std::process::id() ) );
+        let _ = fs::remove_file( &path );
+        return Ratings::load( path );
+    }
+
+    //  ロビーの接続 ( 相手側のソケットも返して切断されないようにする )
+    fn lobby_client( name: &str, request: Request ) -> ( Client, TcpStream ) {
+        let listener = TcpListener::bind( ( "127.0.0.1", 0 ) ).unwrap();
+        let peer = TcpStream::connect( listener.local_addr().unwrap() ).unwrap();
+        let ( stream, _ ) = listener.accept().unwrap();
+        let client = Client {
+            connection: Connection::new( stream ).unwrap(),
+            name: Some( name.to_string() ),
+            request,
+            joined: Instant::now(),
+            leave: false,
+            watch: None,
+        };
+        return ( client, peer );
+    }
+
+    #[test]
+    fn elo_update() {
+        let mut ratings = empty_ratings( "elo" );
+        ratings.update( "alice", "bob", Outcome::BLACK_WIN );
+        assert_eq!( ( ratings.get( "alice" ), ratings.get( "bob" ) ), ( 1516, 1484 ) );
+
+        //  勝っていた側が負けると期待値の分だけ多く動く
+        ratings.update( "alice", "bob", Outcome::WHITE_WIN );
+        assert_eq!( ( ratings.get( "alice" ), ratings.get( "bob" ) ), ( 1499, 1501 ) );
+
+        //  格上との引き分けは格下が上がる
+        ratings.table.insert( "carol".to_string(), 1700 );
+        ratings.update( "dave", "carol", Outcome::DRAW );
+        assert_eq!( ( ratings.get( "dave" ), ratings.get( "carol" ) ), ( 1508, 1692 ) );
+
+        let saved = Ratings::load( ratings.path.clone() );
+        assert_eq!( saved.get( "carol" ), 1692 );
+        let _ = fs::remove_file( &ratings.path );
+    }
+
+    #[test]
+    fn lobby_names() {
+        assert!( is_valid_name( "alice" ) && is_valid_name( "Bob_2" ) && is_valid_name( &"x".repeat( MAX_NAME_LENGTH ) ) );
+        for name in [ "", "al ice", "al:ice", "tab\t", SERVER_NAME ].iter() {
+            assert!( !is_valid_name( name ), "{:?}", name );
+        }
+        assert!( !is_valid_name( &"x".repeat( MAX_NAME_LENGTH + 1 ) ) );
+    }
+
+    #[test]
+    fn named_challenge_pairs_first_then_closest_rating() {
+        let mut ratings = empty_ratings( "pair" );
+        ratings.table.insert( "dave".to_string(), 1900 );
+        ratings.table.insert( "erin".to_string(), 1540 );
+        let ratings = Arc::new( Mutex::new( ratings ) );
+
+        let ( mut clients, _peers ): ( Vec<Client>, Vec<TcpStream> ) = vec![
+            lobby_client( "carol", Request::SEEK ),
+            lobby_client( "dave", Request::SEEK ),
+            lobby_client( "alice", Request::CHALLENGE( "bob".to_string() ) ),
+            lobby_client( "erin", Request::SEEK ),
+            lobby_client( "bob", Request::SEEK ),
+            lobby_client( "frank", Request::CHALLENGE( "nobody".to_string() ) ),
+        ].into_iter().unzip();
+
+        assert_eq!( find_pair( &clients, &ratings ), Some( ( 2, 4 ) ) );
+        clients.remove( 4 );
+        clients.remove( 2 );
+
+        //  carol ( 1500 ) には dave ( 1900 ) より erin ( 1540 )
+        assert_eq!( find_pair( &clients, &ratings ), Some( ( 0, 2 ) ) );
+        clients.remove( 2 );
+        clients.remove( 0 );
+
+        //  残りは dave と来ない相手を待つ frank
+        assert_eq!( find_pair( &clients, &ratings ), None );
+    }
+
+    #[test]
+    fn no_pairing_with_yourself() {
+        let ratings = Arc::new( Mutex::new( empty_ratings( "self" ) ) );
+        let ( clients, _peers ): ( Vec<Client>, Vec<TcpStream> ) = vec![
+            lobby_client( "alice", Request::CHALLENGE( "alice".to_string() ) ),
+            lobby_client( "bob", Request::SEEK ),
+        ].into_iter().unzip();
+        assert_eq!( find_pair( &clients, &ratings ), None );
+
+        //  ロビーでも受け付けない ( 切断はせずに待たせる )
+        let games = Arc::new( Mutex::new( Vec::new() ) );
+        let ( alice, _peer ) = lobby_client( "alice", Request::NONE );
+        let mut clients = vec![ alice ];
+        handle_lobby( &mut clients, 0, Message::CHALLENGE( "alice".to_string() ), &ratings, &games );
+        assert!( clients[0].request == Request::NONE && !clients[0].leave );
+    }
+
+    #[test]
+    fn name_taken_by_a_running_game() {
+        let ratings = Arc::new( Mutex::new( empty_ratings( "taken" ) ) );
+        let games = Arc::new( Mutex::new( vec![ LiveGame {
+            id: 1,
+            black: "alice".to_string(),
+            white: "bob".to_string(),
+            watchers: std::sync::mpsc::channel().0,
+        } ] ) );
+
+        let ( client, _peer ) = lobby_client( "-", Request::NONE );
+        let mut clients = vec![ Client { name: None, ..client } ];
+        handle_lobby( &mut clients, 0, Message::HELLO( net::PROTOCOL_VERSION, "alice".to_string() ), &ratings, &games );
+        assert!( clients[0].leave && clients[0].name.is_none() );
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{ Deserialize, Serialize };
+use std::fs;
+use super::locale::Language;
+
+const SETTINGS_PATH: &str = "./settings.toml";
+
+//  ユーザー設定 ( settings.toml に保存 )
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: String,      //  テーマ名
+    pub fullscreen: bool,   //  フルスクリーン
+    pub flip_board: bool,   //  盤面を白側から見る
+    pub language: Language, //  表示言語
+    pub volume: f32,        //  音量 ( 0.0 ~ 1.0 )
+    pub mute: bool,         //  消音
+    pub music: bool,        //  BGM を流す
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            theme: "Classic".to_string(),
+            fullscreen: false,
+            flip_board: false,
+            language: Language::ENGLISH,
+            volume: 0.8,
+            mute: false,
+            music: true,
+        }
+    }
+}
+
+impl Settings {
+    //  読み込み ( 無い・壊れている場合はデフォルト )
+    pub fn load() -> Settings {
+        match fs::read_to_string( SETTINGS_PATH ) {
+            Ok( source ) => {
+                return toml::from_str( &source ).unwrap_or_else( | e | {
+                    eprintln!( "{}: {}", SETTINGS_PATH, e );
+                    Settings::default()
+                } );
+            },
+            Err( _ ) => return Settings::default(),
+        }
+    }
+
+    pub fn save( &self ) {
+        let result = toml::to_string( self )
+            .map_err( | e | e.to_string() )
+            .and_then( | source | fs::write( SETTINGS_PATH, source ).map_err( | e | e.to_string() ) );
+
+        if let Err( e ) = result {
+            eprintln!( "{}: {}", SETTINGS_PATH, e );
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Deserialize;
+use std::fs;
+use tetra::graphics::Color;
+
+const THEME_DIR: &str = "./asset/theme";
+
+//  組み込みテーマ ( ファイルが無くても選べるように埋め込む )
+const BUILTIN: [&str; 4] = [
+    include_str!( "../../asset/theme/classic.toml" ),
+    include_str!( "../../asset/theme/high_contrast.toml" ),
+    include_str!( "../../asset/theme/dark.toml" ),
+    include_str!( "../../asset/theme/flat.toml" ),
+];
+
+//  描画方式
+#[derive(Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    TEXTURE,    //  画像を描画
+    SHAPE,      //  図形で描画 ( 画像不要・解像度非依存 )
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style::TEXTURE
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Textures {
+    pub board: String,
+    pub piece_black: String,
+    pub piece_white: String,
+    pub can_place: String,
+    pub cursor: String,
+}
+
+//  色はすべて "#RRGGBB" または "#RRGGBBAA"
+#[derive(Clone, Deserialize)]
+pub struct Colors {
+    pub background: String,
+    pub text: String,
+    pub board: String,
+    pub piece_black: String,
+    pub piece_white: String,
+    pub can_place: String,
+    pub cursor: String,
+}
+
+//  図形描画時の色
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct ShapeColors {
+    pub board: String,
+    pub line: String,
+    pub piece_black: String,
+    pub piece_white: String,
+    pub outline: String,
+    pub can_place: String,
+}
+
+impl Default for ShapeColors {
+    fn default() -> ShapeColors {
+        ShapeColors {
+            board: "#1E7A3C".to_string(),
+            line: "#0B3D1C".to_string(),
+            piece_black: "#141414".to_string(),
+            piece_white: "#F0F0F0".to_string(),
+            outline: "#000000".to_string(),
+            can_place: "#FFFFFF60".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct FontDesc {
+    pub path: String,
+    #[serde(default = "default_fallback")]
+    pub fallback: Vec<String>,  //  path に無い文字 ( 日本語など ) を表示するフォント。先頭から順に探す
+}
+
+//  CJK フォントの候補 ( 同梱していないので各OSの標準的な場所を探す )
+fn default_fallback() -> Vec<String> {
+    return [
+        "./asset/NotoSansJP-Regular.otf",
+        "./asset/NotoSansCJKjp-Regular.otf",
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/truetype/fonts-japanese-gothic.ttf",
+        "/usr/share/fonts/opentype/ipafont-gothic/ipag.ttf",
+        "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc",
+        "/System/Library/Fonts/Hiragino Sans GB.ttc",
+        "C:/Windows/Fonts/meiryo.ttc",
+        "C:/Windows/Fonts/msgothic.ttc",
+    ].iter().map( | path | path.to_string() ).collect();
+}
+
+//  テーマ ( テクスチャ・色・フォントの組み合わせ )
+#[derive(Clone, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    #[serde(default)]
+    pub style: Style,
+    #[serde(default)]
+    pub shape: ShapeColors,
+    pub textures: Textures,
+    pub colors: Colors,
+    pub font: FontDesc,
+}
+
+impl Theme {
+    pub fn parse( source: &str ) -> Result<Theme, String> {
+        return toml::from_str( source ).map_err( | e | e.to_string() );
+    }
+
+    //  組み込みテーマ + asset/theme 以下の追加テーマを読み込む
+    pub fn load_all() -> Vec<Theme> {
+        let mut themes: Vec<Theme> = Vec::with_capacity( BUILTIN.len() );
+
+        for source in BUILTIN.iter() {
+            themes.push( Theme::parse( source ).expect( "built-in theme is broken" ) );
+        }
+
+        let entries = match fs::read_dir( THEME_DIR ) {
+            Ok( entries ) => entries,
+            Err( _ ) => return themes,
+        };
+
+        let mut paths: Vec<_> = entries.filter_map( | e | e.ok() ).map( | e | e.path() ).collect();
+        paths.sort();
+
+        for path in paths.iter() {
+            if path.extension().map_or( true, | ext | ext != "toml" ) {
+                continue;
+            }
+
+            let theme = match fs::read_to_string( path ) {
+                Ok( source ) => Theme::parse( &source ),
+                Err( e ) => Err( e.to_string() ),
+            };
+
+            match theme {
+                Ok( theme ) => {
+                    //  同名のテーマはファイル側で上書き
+                    match themes.iter().position( | t | t.name == theme.name ) {
+                        Some( i ) => themes[i] = theme,
+                        None => themes.push( theme ),
+                    }
+                },
+                Err( e ) => eprintln!( "theme {}: {}", path.display(), e ),
+            }
+        }
+
+        return themes;
+    }
+
+    pub fn background_color( &self ) -> Color {
+        return parse_color( &self.colors.background );
+    }
+
+    pub fn text_color( &self ) -> Color {
+        return parse_color( &self.colors.text );
+    }
+
+    pub fn board_color( &self ) -> Color {
+        return parse_color( &self.colors.board );
+    }
+
+    pub fn piece_black_color( &self ) -> Color {
+        return parse_color( &self.colors.piece_black );
+    }
+
+    pub fn piece_white_color( &self ) -> Color {
+        return parse_color( &self.colors.piece_white );
+    }
+
+    pub fn can_place_color( &self ) -> Color {
+        return parse_color( &self.colors.can_place );
+    }
+
+    pub fn cursor_color( &self ) -> Color {
+        return parse_color( &self.colors.cursor );
+    }
+}
+
+//  不正な色指定は白 ( = 着色なし ) として扱う
+pub fn parse_color( hex: &str ) -> Color {
+    return Color::try_hex( hex ).unwrap_or( Color::WHITE );
+}
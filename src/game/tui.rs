@@ -0,0 +1,473 @@
+use std::io::{ self, BufRead, Write };
+use std::time::Duration;
+use tetra::math::Vec2;
+use super::Config;
+use super::ai;
+use super::board::{ cell, WIDTH_NUM, HEIGHT_NUM };
+use super::book::Book;
+use super::engine::{ Engine, ENGINE_TIMEOUT };
+use super::ggf;
+use super::locale::{ self, Language, Message };
+use super::net::{ self, Message as NetMessage, Network, Session, Spectators, Watch };
+use super::rules::{ self, Rules };
+use super::settings::Settings;
+use super::turn::{ self, Kind, Outcome, Phase, Turn, PLAYER };
+
+const HELP: &str = "moves: a1 .. h8 (e.g. d3)   help: show this   quit: exit";
+const NETWORK_HELP: &str = "resign: give up   draw: offer a draw   say <text>: chat";
+const WAIT_INTERVAL: Duration = Duration::from_secs( 1 );
+
+//  端末で対局 ( GPU が無い環境や SSH 越しでの確認用 )
+pub fn run( unicode: bool, config: &Config, network: Network ) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let language = Settings::load().language;
+    if let Some( watch ) = network.watch {
+        return spectate( &mut stdout.lock(), unicode, language, watch );
+    }
+    return play( &mut stdin.lock(), &mut stdout.lock(), unicode, language, config, network.session, network.spectators );
+}
+
+//  通信相手とのやり取りの結果
+enum RemoteEvent {
+    MOVE( Vec2<i32> ),
+    PASS,
+    END( Option<Outcome>, Message ),    //  終局 ( 勝敗、理由。勝敗が None なら中断 )
+    CONTINUE,                           //  引き分けの提案が断られた
+}
+
+//  入出力を差し替えられるようにしておく ( パイプでの動作確認用 )
+pub fn play<R: BufRead, W: Write>( input: &mut R, output: &mut W, unicode: bool, language: Language, config: &Config, mut remote: Option<Session>, mut spectators: Option<Spectators> ) -> io::Result<()> {
+    let mut rules = Rules::new( config.variant );
+    let mut turn = Turn::new();
+    let mut moves = Vec::with_capacity( 60 );
+    let mut forced_outcome: Option<Outcome> = None;    //  投了・合意の引き分け
+    let mut reason = "finished";                        //  観戦者に知らせる終局の理由
+    let mut book = Book::load();
+    let mut opening: Option<String> = None;             //  表示した定石の名前
+
+    //  外部のエンジンを起動
+    let mut engines: [Option<Engine>; 2] = [ None, None ];
+    for ( i, kind ) in config.players.iter().enumerate() {
+        if let ( Kind::ENGINE, Some( engine_config ) ) = ( kind, config.engine.as_ref() ) {
+            engines[i] = Some( Engine::start( engine_config, config.level )? );
+        }
+    }
+
+    writeln!( output, "{}", HELP )?;
+    if let Some( session ) = remote.as_ref() {
+        writeln!( output, "{}", NETWORK_HELP )?;
+        writeln!( output, "{} {}", locale::text( language, Message::VERSUS ), session.opponent )?;
+    }
+    if let Some( record ) = config.record.as_ref() {
+        writeln!( output, "{} vs {}", record.black, record.white )?;
+    }
+
+    loop {
+        if let Some( spectators ) = spectators.as_mut() {
+            spectators.update( &moves, &rules, turn.get_color() );
+        }
+
+        let phase = turn.start( &rules );
+        if phase == Phase::END {
+            break;
+        }
+
+        let color = turn.get_color();
+        let kind = config.players[turn.get_player() as usize];
+        let event = match kind {
+            Kind::REMOTE => {
+                //  相手の手は盤面と照らし合わせてから反映
+                let session = remote.as_mut().unwrap();
+                match wait_remote( input, output, session, language, false )? {
+                    RemoteEvent::MOVE( position ) if phase == Phase::PLAY && rules.can_piece( position.x, position.y, color ) => RemoteEvent::MOVE( position ),
+                    RemoteEvent::MOVE( position ) => reject( session, format!( "illegal move {}", rules::to_notation( position ) ) ),
+                    RemoteEvent::PASS if phase == Phase::PASS => RemoteEvent::PASS,
+                    RemoteEvent::PASS => reject( session, "illegal pass".to_string() ),
+                    event => event,
+                }
+            },
+            _ if phase == Phase::PASS => RemoteEvent::PASS,
+            Kind::AI => {
+                let clock = remote.as_ref().and_then( | s | s.clock )
+                    .map( | ( black, white ) | if color == cell::Color::BLACK { black } else { white } );
+                let budget = ai::time_budget( &rules, config.level, clock );
+                match book.choose( rules.get_variant(), &moves ).filter( | p | rules.can_piece( p.x, p.y, color ) )
+                    .or_else( || ai::search_timed( &rules, color, config.level, Some( budget ) ).position ) {
+                    Some( position ) => RemoteEvent::MOVE( position ),
+                    None => RemoteEvent::PASS,
+                }
+            },
+            Kind::ENGINE => {
+                //  正しい手を返さなければ負け
+                let engine = engines[turn.get_player() as usize].as_mut().unwrap();
+                let answer = engine.request( rules.get_variant(), &moves ).map_err( | e | e.to_string() )
+                    .and_then( | _ | engine.wait( ENGINE_TIMEOUT ) );
+                match answer {
+                    Ok( Some( position ) ) if rules.can_piece( position.x, position.y, color ) => RemoteEvent::MOVE( position ),
+                    answer => {
+                        match answer {
+                            Ok( Some( position ) ) => eprintln!( "engine: illegal move {}", rules::to_notation( position ) ),
+                            Ok( None ) => eprintln!( "engine: illegal pass" ),
+                            Err( e ) => eprintln!( "engine: {}", e ),
+                        }
+                        let outcome = match turn.get_player() {
+                            PLAYER::ONE => Outcome::WHITE_WIN,
+                            PLAYER::TWO => Outcome::BLACK_WIN,
+                        };
+                        send( &mut remote, NetMessage::RESIGN );
+                        RemoteEvent::END( Some( outcome ), Message::ENGINE_ERROR )
+                    },
+                }
+            },
+            Kind::REPLAY => {
+                let record = config.record.as_ref().unwrap();
+                match record.positions().get( moves.len() ).copied() {
+                    Some( position ) if rules.can_piece( position.x, position.y, color ) => RemoteEvent::MOVE( position ),
+                    Some( position ) => {
+                        eprintln!( "record: illegal move {}", rules::to_notation( position ) );
+                        RemoteEvent::END( None, Message::DESYNC )
+                    },
+                    //  投了・時間切れで終わった棋譜
+                    None => {
+                        let message = match record.result.map( | r | r.ending ) {
+                            Some( ggf::Ending::TIME ) => Message::TIME_UP,
+                            Some( ggf::Ending::AGREEMENT ) => Message::DRAW,
+                            Some( _ ) => Message::RESIGNED,
+                            None => Message::RECORD_END,
+                        };
+                        RemoteEvent::END( record.result.map( | r | r.outcome() ), message )
+                    },
+                }
+            },
+            Kind::HUMAN => {
+                print_board( output, &rules, color, unicode )?;
+                if let Some( ( black, white ) ) = remote.as_ref().and_then( | s | s.clock ) {
+                    writeln!( output, "{} {}  {} {}", locale::text( language, Message::BLACK ), net::format_clock( black ),
+                        locale::text( language, Message::WHITE ), net::format_clock( white ) )?;
+                }
+                writeln!( output, "{}", turn_text( language, turn.get_player() ) )?;
+                match read_move( input, output, &rules, color, language, &mut remote )? {
+                    Some( event ) => event,
+                    //  入力終了・quit
+                    None => return Ok(()),
+                }
+            },
+        };
+
+        match event {
+            RemoteEvent::MOVE( position ) => {
+                rules.set_piece( position.x, position.y, color );
+                moves.push( position );
+                writeln!( output, "{}: {}", player_text( language, turn.get_player() ), rules::to_notation( position ) )?;
+                //  定石の名前が変わったら表示
+                let name = book.get_name( rules.get_variant(), &moves ).map( | n | n.to_string() );
+                if name.is_some() && name != opening {
+                    writeln!( output, "{}: {}", locale::text( language, Message::OPENING ), name.as_ref().unwrap() )?;
+                }
+                opening = name;
+                if kind != Kind::REMOTE {
+                    send( &mut remote, NetMessage::MOVE( position ) );
+                }
+                if let Some( spectators ) = spectators.as_mut() {
+                    spectators.send( &NetMessage::MOVE( position ) );
+                }
+            },
+            RemoteEvent::PASS => {
+                writeln!( output, "{}: {}", player_text( language, turn.get_player() ), locale::text( language, Message::PASS ) )?;
+                if kind != Kind::REMOTE {
+                    send( &mut remote, NetMessage::PASS );
+                }
+                if let Some( spectators ) = spectators.as_mut() {
+                    spectators.send( &NetMessage::PASS );
+                }
+            },
+            RemoteEvent::END( outcome, message ) => {
+                writeln!( output, "{}", locale::text( language, message ) )?;
+                if outcome.is_none() {
+                    return Ok(());
+                }
+                forced_outcome = outcome;
+                reason = match message {
+                    Message::TIME_UP => "time",
+                    Message::DRAW => "agreement",
+                    _ => "resign",
+                };
+                break;
+            },
+            RemoteEvent::CONTINUE => continue,
+        }
+
+        if turn.finish( &rules ) == Phase::END {
+            break;
+        }
+    }
+
+    let outcome = forced_outcome.unwrap_or( turn::get_outcome( &rules ) );
+    if let Some( spectators ) = spectators.as_mut() {
+        spectators.finish( outcome, reason );
+    }
+
+    print_board( output, &rules, cell::Color::NONE, unicode )?;
+    writeln!( output, "{}", locale::text( language, outcome_message( outcome ) ) )?;
+    return Ok(());
+}
+
+//  観戦 ( 届いた手を並べて表示するだけ )
+fn spectate<W: Write>( output: &mut W, unicode: bool, language: Language, mut watch: Watch ) -> io::Result<()> {
+    let ( mut rules, _ ) = rules::replay( watch.variant, &watch.moves ).unwrap_or( ( Rules::new( watch.variant ), cell::Color::BLACK ) );
+    let mut color = watch.color;
+
+    writeln!( output, "{}: {} vs {}", locale::text( language, Message::SPECTATING ), watch.black, watch.white )?;
+    print_board( output, &rules, color, unicode )?;
+
+    loop {
+        let message = match watch.connection.receive_wait( WAIT_INTERVAL ) {
+            Ok( message ) => message,
+            Err( e ) if e.kind() == io::ErrorKind::TimedOut => continue,
+            Err( e ) => return Err( e ),
+        };
+
+        let player_num = if color == cell::Color::BLACK { PLAYER::ONE } else { PLAYER::TWO };
+        match message {
+            NetMessage::MOVE( position ) => {
+                if rules.set_piece( position.x, position.y, color ).is_none() {
+                    writeln!( output, "{}", locale::text( language, Message::DESYNC ) )?;
+                    return Ok(());
+                }
+                writeln!( output, "{}: {}", player_text( language, player_num ), rules::to_notation( position ) )?;
+                color = rules::get_enemy_piece( color );
+                print_board( output, &rules, color, unicode )?;
+            },
+            NetMessage::PASS => {
+                writeln!( output, "{}: {}", player_text( language, player_num ), locale::text( language, Message::PASS ) )?;
+                color = rules::get_enemy_piece( color );
+            },
+            NetMessage::CLOCK( black, white ) => {
+                watch.clock = Some( ( black, white ) );
+                writeln!( output, "{} {}  {} {}", locale::text( language, Message::BLACK ), net::format_clock( black ),
+                    locale::text( language, Message::WHITE ), net::format_clock( white ) )?;
+            },
+            NetMessage::RESULT( outcome, reason ) => {
+                print_board( output, &rules, cell::Color::NONE, unicode )?;
+                writeln!( output, "{} ({})", locale::text( language, outcome_message( outcome ) ), reason )?;
+                return Ok(());
+            },
+            NetMessage::ERROR( text ) => {
+                writeln!( output, "{}", text )?;
+                return Ok(());
+            },
+            NetMessage::BYE => {
+                writeln!( output, "{}", locale::text( language, Message::DISCONNECTED ) )?;
+                return Ok(());
+            },
+            _ => {},
+        }
+    }
+}
+
+fn outcome_message( outcome: Outcome ) -> Message {
+    match outcome {
+        Outcome::BLACK_WIN => return Message::BLACK_WIN,
+        Outcome::WHITE_WIN => return Message::WHITE_WIN,
+        Outcome::DRAW => return Message::DRAW,
+    }
+}
+
+//  人間の手番 ( 合法手が入力されるまで繰り返す。入力終了・quit なら None )
+fn read_move<R: BufRead, W: Write>( input: &mut R, output: &mut W, rules: &Rules, color: cell::Color, language: Language, remote: &mut Option<Session> ) -> io::Result<Option<RemoteEvent>> {
+    loop {
+        //  入力待ちの間に届いたメッセージ
+        if let Some( session ) = remote.as_mut() {
+            for message in session.connection.receive() {
+                match handle_remote( input, output, session, language, message )? {
+                    Some( RemoteEvent::MOVE( _ ) ) | Some( RemoteEvent::PASS ) => {
+                        return Ok( Some( reject( session, "not your turn".to_string() ) ) );
+                    },
+                    Some( RemoteEvent::END( outcome, reason ) ) => return Ok( Some( RemoteEvent::END( outcome, reason ) ) ),
+                    _ => {},
+                }
+            }
+        }
+
+        write!( output, "> " )?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line( &mut line )? == 0 {
+            //  入力終了 ( EOF ) はそのまま終わる
+            writeln!( output )?;
+            return Ok( None );
+        }
+
+        let line = line.trim();
+        match line {
+            "" => continue,
+            "quit" | "q" | "exit" => return Ok( None ),
+            "help" | "?" => {
+                writeln!( output, "{}", HELP )?;
+                if remote.is_some() {
+                    writeln!( output, "{}", NETWORK_HELP )?;
+                }
+                continue;
+            },
+            _ => {},
+        }
+
+        //  通信対戦のコマンド
+        if let Some( session ) = remote.as_mut() {
+            if line == "resign" {
+                let _ = session.connection.send( &NetMessage::RESIGN );
+                let outcome = if session.color == cell::Color::BLACK { Outcome::WHITE_WIN } else { Outcome::BLACK_WIN };
+                return Ok( Some( RemoteEvent::END( Some( outcome ), Message::YOU_RESIGNED ) ) );
+            }
+
+            if line == "draw" {
+                let _ = session.connection.send( &NetMessage::DRAW_OFFER );
+                writeln!( output, "{}", locale::text( language, Message::DRAW_OFFER_SENT ) )?;
+                match wait_remote( input, output, session, language, true )? {
+                    RemoteEvent::CONTINUE => continue,
+                    RemoteEvent::END( outcome, reason ) => return Ok( Some( RemoteEvent::END( outcome, reason ) ) ),
+                    _ => return Ok( Some( reject( session, "not your turn".to_string() ) ) ),
+                }
+            }
+
+            if let Some( text ) = line.strip_prefix( "say " ) {
+                let _ = session.connection.send( &NetMessage::CHAT( text.to_string() ) );
+                continue;
+            }
+        }
+
+        match rules::parse_notation( line ) {
+            Some( position ) if rules.can_piece( position.x, position.y, color ) => return Ok( Some( RemoteEvent::MOVE( position ) ) ),
+            Some( _ ) => writeln!( output, "{}: {}", locale::text( language, Message::ILLEGAL_MOVE ), line )?,
+            None => writeln!( output, "{}", HELP )?,
+        }
+    }
+}
+
+//  相手からの手・終局を待つ ( reply なら引き分けの提案への返事でも戻る )
+fn wait_remote<R: BufRead, W: Write>( input: &mut R, output: &mut W, session: &mut Session, language: Language, reply: bool ) -> io::Result<RemoteEvent> {
+    loop {
+        let message = match session.connection.receive_wait( WAIT_INTERVAL ) {
+            Ok( message ) => message,
+            Err( e ) if e.kind() == io::ErrorKind::TimedOut => continue,
+            Err( e ) => return Err( e ),
+        };
+
+        match handle_remote( input, output, session, language, message )? {
+            Some( RemoteEvent::CONTINUE ) if !reply => {},
+            Some( event ) => return Ok( event ),
+            None => {},
+        }
+    }
+}
+
+//  手以外のメッセージはここで処理 ( チャット表示・引き分けの提案への返事 )
+fn handle_remote<R: BufRead, W: Write>( input: &mut R, output: &mut W, session: &mut Session, language: Language, message: NetMessage ) -> io::Result<Option<RemoteEvent>> {
+    match message {
+        NetMessage::MOVE( position ) => return Ok( Some( RemoteEvent::MOVE( position ) ) ),
+        NetMessage::PASS => return Ok( Some( RemoteEvent::PASS ) ),
+        NetMessage::RESIGN => {
+            let outcome = if session.color == cell::Color::BLACK { Outcome::BLACK_WIN } else { Outcome::WHITE_WIN };
+            return Ok( Some( RemoteEvent::END( Some( outcome ), Message::OPPONENT_RESIGNED ) ) );
+        },
+        NetMessage::DRAW_OFFER => {
+            write!( output, "{} > ", locale::text( language, Message::DRAW_OFFERED ) )?;
+            output.flush()?;
+            let mut line = String::new();
+            input.read_line( &mut line )?;
+            if line.trim().eq_ignore_ascii_case( "y" ) {
+                let _ = session.connection.send( &NetMessage::DRAW_ACCEPT );
+                return Ok( Some( RemoteEvent::END( Some( Outcome::DRAW ), Message::DRAW ) ) );
+            }
+            let _ = session.connection.send( &NetMessage::DRAW_DECLINE );
+            return Ok( None );
+        },
+        NetMessage::DRAW_ACCEPT => return Ok( Some( RemoteEvent::END( Some( Outcome::DRAW ), Message::DRAW ) ) ),
+        NetMessage::DRAW_DECLINE => {
+            writeln!( output, "{}", locale::text( language, Message::DRAW_DECLINED ) )?;
+            return Ok( Some( RemoteEvent::CONTINUE ) );
+        },
+        NetMessage::CHAT( text ) => {
+            writeln!( output, "{}: {}", session.opponent, text )?;
+            return Ok( None );
+        },
+        NetMessage::ERROR( text ) => {
+            writeln!( output, "{}", text )?;
+            return Ok( Some( RemoteEvent::END( None, Message::DESYNC ) ) );
+        },
+        NetMessage::BYE => return Ok( Some( RemoteEvent::END( None, Message::DISCONNECTED ) ) ),
+        //  サーバーの持ち時間
+        NetMessage::CLOCK( black, white ) => {
+            session.clock = Some( ( black, white ) );
+            return Ok( None );
+        },
+        NetMessage::TIMEOUT( color ) => {
+            let outcome = if color == cell::Color::BLACK { Outcome::WHITE_WIN } else { Outcome::BLACK_WIN };
+            return Ok( Some( RemoteEvent::END( Some( outcome ), Message::TIME_UP ) ) );
+        },
+        _ => return Ok( None ),
+    }
+}
+
+//  盤面が合わない手は相手に知らせて中断
+fn reject( session: &mut Session, text: String ) -> RemoteEvent {
+    let _ = session.connection.send( &NetMessage::ERROR( text ) );
+    return RemoteEvent::END( None, Message::DESYNC );
+}
+
+fn send( remote: &mut Option<Session>, message: NetMessage ) {
+    if let Some( session ) = remote.as_mut() {
+        if let Err( e ) = session.connection.send( &message ) {
+            eprintln!( "network: {}", e );
+        }
+    }
+}
+
+fn turn_text( language: Language, player_num: PLAYER ) -> &'static str {
+    match player_num {
+        PLAYER::ONE => return locale::text( language, Message::BLACK_TURN ),
+        PLAYER::TWO => return locale::text( language, Message::WHITE_TURN ),
+    }
+}
+
+fn player_text( language: Language, player_num: PLAYER ) -> &'static str {
+    match player_num {
+        PLAYER::ONE => return locale::text( language, Message::BLACK ),
+        PLAYER::TWO => return locale::text( language, Message::WHITE ),
+    }
+}
+
+//  盤面表示 ( color の設置可能マスに印を付ける。NONE なら印なし )
+pub fn print_board<W: Write>( output: &mut W, rules: &Rules, color: cell::Color, unicode: bool ) -> io::Result<()> {
+    let ( black, white, empty, can_place ) = if unicode {
+        ( "●", "○", "·", "*" )
+    } else {
+        ( "X", "O", ".", "*" )
+    };
+
+    write!( output, "  " )?;
+    for i in 0 .. WIDTH_NUM {
+        write!( output, " {}", ( b'a' + i as u8 ) as char )?;
+    }
+    writeln!( output )?;
+
+    for k in 0 .. HEIGHT_NUM as i32 {
+        write!( output, "{:>2}", k + 1 )?;
+        for i in 0 .. WIDTH_NUM as i32 {
+            let mark = match rules.get_piece( i, k ) {
+                cell::Color::BLACK => black,
+                cell::Color::WHITE => white,
+                cell::Color::NONE => if rules.can_piece( i, k, color ) { can_place } else { empty },
+            };
+            write!( output, " {}", mark )?;
+        }
+        writeln!( output )?;
+    }
+
+    writeln!( output, "{} {}  {} {}",
+        black, rules.get_piece_count( cell::Color::BLACK ),
+        white, rules.get_piece_count( cell::Color::WHITE ) )?;
+    return Ok(());
+}
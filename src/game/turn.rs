@@ -0,0 +1,145 @@
+use super::board::cell;
+use super::rules::Rules;
+
+pub const PLAYER_NUM: usize = 2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PLAYER {
+    ONE = 0,
+    TWO = 1,
+}
+
+//  プレイヤーの種類
+#[derive(Copy, Clone, PartialEq)]
+pub enum Kind {
+    HUMAN,  //  マウス・キーボードで操作
+    AI,     //  コンピューター
+    REMOTE, //  通信相手
+    ENGINE, //  外部のエンジン ( 子プロセス )
+    REPLAY, //  棋譜の再生
+}
+
+impl Kind {
+    pub fn parse( text: &str ) -> Option<Kind> {
+        match text {
+            "human" => return Some( Kind::HUMAN ),
+            "ai" => return Some( Kind::AI ),
+            "engine" => return Some( Kind::ENGINE ),
+            _ => return None,
+        }
+    }
+}
+
+//  ターン開始・終了時の進行
+#[derive(Copy, Clone, PartialEq)]
+pub enum Phase {
+    PLAY,   //  置けるマスがある
+    PASS,   //  置けるマスが無いのでパス
+    END,    //  終局
+}
+
+//  勝敗
+#[derive(Copy, Clone, PartialEq)]
+pub enum Outcome {
+    BLACK_WIN,
+    WHITE_WIN,
+    DRAW,
+}
+
+impl Outcome {
+    pub fn name( self ) -> &'static str {
+        match self {
+            Outcome::BLACK_WIN => "black",
+            Outcome::WHITE_WIN => "white",
+            Outcome::DRAW => "draw",
+        }
+    }
+
+    pub fn parse( text: &str ) -> Option<Outcome> {
+        match text {
+            "black" => return Some( Outcome::BLACK_WIN ),
+            "white" => return Some( Outcome::WHITE_WIN ),
+            "draw" => return Some( Outcome::DRAW ),
+            _ => return None,
+        }
+    }
+}
+
+//  手番とパスの管理 ( GUI と TUI で共通 )
+pub struct Turn {
+    player_num: PLAYER,
+    skip_count: i32,
+}
+
+impl Turn {
+    pub fn new() -> Turn {
+        Turn {
+            player_num: PLAYER::ONE,
+            skip_count: 0,
+        }
+    }
+
+    pub fn init( &mut self ) {
+        self.player_num = PLAYER::ONE;
+        self.skip_count = 0;
+    }
+
+    pub fn get_player( &self ) -> PLAYER {
+        return self.player_num;
+    }
+
+    //  手番の色
+    pub fn get_color( &self ) -> cell::Color {
+        return player_color( self.player_num );
+    }
+
+    //  ターン開始 ( 置けなければパス、2回連続パスは終局 )
+    pub fn start( &mut self, rules: &Rules ) -> Phase {
+        if rules.there_is_enable_position( self.get_color() ) {
+            self.skip_count = 0;
+            return Phase::PLAY;
+        }
+
+        self.skip_count += 1;
+        if self.skip_count >= 2 {
+            return Phase::END;
+        }
+
+        return Phase::PASS;
+    }
+
+    //  ターン終了 ( 空きが無ければ終局、それ以外はプレイヤー交代 )
+    pub fn finish( &mut self, rules: &Rules ) -> Phase {
+        if rules.get_piece_count( cell::Color::NONE ) == 0 {
+            return Phase::END;
+        }
+
+        self.player_num = match self.player_num {
+            PLAYER::ONE => PLAYER::TWO,
+            PLAYER::TWO => PLAYER::ONE,
+        };
+
+        return Phase::PLAY;
+    }
+}
+
+pub fn player_color( player_num: PLAYER ) -> cell::Color {
+    match player_num {
+        PLAYER::ONE => return cell::Color::BLACK,
+        PLAYER::TWO => return cell::Color::WHITE,
+    }
+}
+
+//  石数で勝敗判定
+pub fn get_outcome( rules: &Rules ) -> Outcome {
+    let black_piece_num = rules.get_piece_count( cell::Color::BLACK );
+    let white_piece_num = rules.get_piece_count( cell::Color::WHITE );
+
+    if black_piece_num == white_piece_num {
+        return Outcome::DRAW;
+    } else if black_piece_num < white_piece_num {
+        return Outcome::WHITE_WIN;
+    }
+
+    return Outcome::BLACK_WIN;
+}
@@ -1,8 +1,10 @@
-use tetra::graphics::{ self, DrawParams, Rectangle, Texture };
-use tetra::graphics::text::{ Font, Text };
+use tetra::graphics::{ self, Color, DrawParams, Rectangle, Texture };
+use tetra::graphics::text::Text;
 use tetra::input::{ self, Key, MouseButton };
 use tetra::math::Vec2;
 use tetra::{ Context, ContextBuilder, State };
+use super::assets::Assets;
+use super::layout::Layout;
 
 struct ParamUI {
     piece_black_piece_num: i32, //  ���s�[�X�̐�
@@ -14,20 +16,28 @@ pub struct TextureUI {
     key: String,
     texture: Texture,
     param: DrawParams,
+    base: DrawParams,   //  基準座標での描画パラメータ
 }
 
 impl TextureUI {
-    pub fn new( _key: String, path: String, _param: DrawParams, ctx: &mut Context ) -> tetra::Result<TextureUI> {
+    pub fn new( _key: String, path: String, _param: DrawParams, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result<TextureUI> {
         let key = _key;
-        let texture = Texture::new( ctx, path )?;
-        let param = _param;
+        let texture = assets.texture( ctx, &path )?;
+        let param = _param.clone();
+        let base = _param;
         Ok( TextureUI {
             key,
             texture,
             param,
+            base,
         } )
     }
 
+    pub fn set_layout( &mut self, layout: &Layout ) {
+        self.param.position = layout.point( self.base.position.x, self.base.position.y );
+        self.param.scale = self.base.scale * layout.get_scale();
+    }
+
     pub fn draw( &mut self, ctx: &mut Context ) {
         let param = DrawParams::new()
             .position( self.param.position )
@@ -41,25 +51,40 @@ pub struct TextUI {
     key: String,
     text: Text,
     param: DrawParams,
+    base_position: Vec2<f32>,   //  基準座標
+    font_size: f32,             //  基準の文字サイズ
 }
 
 impl TextUI {
-    pub fn new( _key: String, _text: String, font_size: f32, _param: DrawParams, ctx: &mut Context ) -> tetra::Result<TextUI> {
+    pub fn new( _key: String, _text: String, font_size: f32, _param: DrawParams, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result<TextUI> {
         let key = _key;
-        let text = Text::new( _text, Font::vector( ctx, "asset/DejaVuSansMono.ttf", font_size )? );
+        let font = assets.text_font_for( ctx, font_size, &_text )?;
+        let text = Text::new( _text, font );
+        let base_position = _param.position;
         let param = _param;
         Ok( TextUI {
             key,
             text,
             param,
+            base_position,
+            font_size,
         } )
     }
 
+    //  配置の反映 ( 拡大率に合わせてフォントを作り直す )
+    pub fn set_layout( &mut self, layout: &Layout, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result {
+        self.param.position = layout.point( self.base_position.x, self.base_position.y );
+        let font = assets.text_font_for( ctx, layout.font_size( self.font_size ), self.text.content() )?;
+        self.text.set_font( font );
+        Ok(())
+    }
+
     pub fn draw( &mut self, ctx: &mut Context ) {
         let param = DrawParams::new()
             .position( self.param.position )
             .scale( self.param.scale )
-            .origin( self.param.origin );
+            .origin( self.param.origin )
+            .color( self.param.color );
         self.text.draw( ctx, param );
     }
 }
@@ -69,42 +94,73 @@ pub struct ButtonUI {
     text: Text,
     param: DrawParams,
     callback: fn( &mut Context ),
+    clicked: bool,
+    base_position: Vec2<f32>,   //  基準座標
+    font_size: f32,             //  基準の文字サイズ
 }
 
 impl ButtonUI {
-    pub fn new( _key: String, _text: String, font_size: f32, _param: DrawParams, _func: fn( &mut Context ), ctx: &mut Context ) -> tetra::Result<ButtonUI> {
+    pub fn new( _key: String, _text: String, font_size: f32, _param: DrawParams, _func: fn( &mut Context ), assets: &mut Assets, ctx: &mut Context ) -> tetra::Result<ButtonUI> {
         let key = _key;
-        let text = Text::new( _text, Font::vector( ctx, "asset/DejaVuSansMono.ttf", font_size )? );
+        let font = assets.text_font_for( ctx, font_size, &_text )?;
+        let text = Text::new( _text, font );
+        let base_position = _param.position;
         let param = _param;
         let callback = _func;
+        let clicked = false;
         Ok( ButtonUI {
             key,
             text,
             param,
             callback,
+            clicked,
+            base_position,
+            font_size,
         } )
     }
 
+    //  配置の反映 ( 拡大率に合わせてフォントを作り直す )
+    pub fn set_layout( &mut self, layout: &Layout, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result {
+        self.param.position = layout.point( self.base_position.x, self.base_position.y );
+        let font = assets.text_font_for( ctx, layout.font_size( self.font_size ), self.text.content() )?;
+        self.text.set_font( font );
+        Ok(())
+    }
+
     pub fn update( &mut self, ctx: &mut Context ) {
+        self.clicked = false;
+
         if input::is_mouse_button_released( ctx, MouseButton::Left ) {
-            let mouse_position = input::get_mouse_position( ctx );
+            //  get_bounds はテキスト原点からの相対座標なので描画位置を引く
+            let mouse_position = input::get_mouse_position( ctx ) - self.param.position + self.param.origin;
             match self.text.get_bounds( ctx ) {
                 Some(rect) => {
                     if mouse_position.x > rect.x && mouse_position.y > rect.y && 
-                    mouse_position.x < rect.width && mouse_position.y < rect.height {
+                    mouse_position.x < rect.x + rect.width && mouse_position.y < rect.y + rect.height {
+                        self.clicked = true;
                         ( self.callback )( ctx );
                     }
                 },
-                NONE => {},
+                None => {},
             }
         }
     }
 
+    //  このフレームでクリックされたか
+    pub fn is_clicked( &self ) -> bool {
+        return self.clicked;
+    }
+
+    pub fn get_key( &self ) -> &str {
+        return &self.key;
+    }
+
     pub fn draw( &mut self, ctx: &mut Context ) {
         let param = DrawParams::new()
             .position( self.param.position )
             .scale( self.param.scale )
-            .origin( self.param.origin );
+            .origin( self.param.origin )
+            .color( self.param.color );
         self.text.draw( ctx, param );
     }
 }
@@ -113,6 +169,8 @@ pub struct UI {
     textureUI: Vec<TextureUI>,
     textUI: Vec<TextUI>,
     buttonUI: Vec<ButtonUI>,
+    color: Color,   //  テキスト色
+    layout: Layout, //  画面配置
     //reset: ButtonUI,    //  ���Z�b�g�e�L�X�g
     //win: TextUI,        //  �����e�L�X�g
 }
@@ -122,11 +180,15 @@ impl UI {
         let textureUI = Vec::with_capacity( 10 );
         let textUI = Vec::with_capacity( 10 );
         let buttonUI = Vec::with_capacity( 10 );
+        let color = Color::WHITE;
+        let layout = Layout::new( crate::WINDOW_WIDTH, crate::WINDOW_HEIGHT );
         
         Ok( UI {
             textureUI,
             textUI,
             buttonUI,
+            color,
+            layout,
         } )
     }
 
@@ -134,15 +196,42 @@ impl UI {
 
     }
 
-    pub fn init( &mut self, ctx: &mut Context ) {
+    //  画面配置の変更 ( 表示中のUIにも反映 )
+    pub fn set_layout( &mut self, layout: &Layout, assets: &mut Assets, ctx: &mut Context ) -> tetra::Result {
+        self.layout = layout.clone();
+
+        for i in 0 .. self.textureUI.len() {
+            self.textureUI[i].set_layout( layout );
+        }
+
+        for i in 0 .. self.textUI.len() {
+            self.textUI[i].set_layout( layout, assets, ctx )?;
+        }
+
+        for i in 0 .. self.buttonUI.len() {
+            self.buttonUI[i].set_layout( layout, assets, ctx )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn init( &mut self ) {
         self.textureUI.clear();
         self.textUI.clear();
         self.buttonUI.clear();
+    }
+
+    //  テキスト色の変更 ( 表示中のテキストにも反映 )
+    pub fn set_color( &mut self, color: Color ) {
+        self.color = color;
+
+        for i in 0 .. self.textUI.len() {
+            self.textUI[i].param.color = color;
+        }
 
-        let turn_text = TextUI::new( "turn".to_string(), 
-        "1Pのターン".to_string(), 32.0, DrawParams::new().position( Vec2::new( 720.0, 80.0 ) ), ctx );
-        //turn_text.unwrap().text.push_str( &"test".to_string() );        
-        self.textUI.push( turn_text.unwrap() );
+        for i in 0 .. self.buttonUI.len() {
+            self.buttonUI[i].param.color = color;
+        }
     }
 
     pub fn update( &mut self, ctx: &mut Context ) {
@@ -165,18 +254,35 @@ impl UI {
         }
     }
 
-    pub fn push_textureUI( &mut self, textureUI: TextureUI ) {
+    //  追加時に現在の画面配置を反映する ( 生成時の座標・文字サイズは基準サイズの画面でのもの )
+    pub fn push_textureUI( &mut self, mut textureUI: TextureUI ) {
+        textureUI.set_layout( &self.layout );
         self.textureUI.push( textureUI );
     }
 
-    pub fn push_textUI( &mut self, textUI: TextUI ) {
+    pub fn push_textUI( &mut self, mut textUI: TextUI, assets: &mut Assets, ctx: &mut Context ) {
+        textUI.param.color = self.color;
+        textUI.set_layout( &self.layout, assets, ctx ).unwrap();
         self.textUI.push( textUI );
     }
 
-    pub fn push_buttonUI( &mut self, buttonUI: ButtonUI ) {
+    pub fn push_buttonUI( &mut self, mut buttonUI: ButtonUI, assets: &mut Assets, ctx: &mut Context ) {
+        buttonUI.param.color = self.color;
+        buttonUI.set_layout( &self.layout, assets, ctx ).unwrap();
         self.buttonUI.push( buttonUI );
     }
 
+    //  指定したボタンがこのフレームでクリックされたか
+    pub fn is_clicked( &self, key: &str ) -> bool {
+        for i in 0 .. self.buttonUI.len() {
+            if self.buttonUI[i].key == key {
+                return self.buttonUI[i].is_clicked();
+            }
+        }
+
+        return false;
+    }
+
     pub fn pop_textureUI( &mut self, key: String ) -> Option<TextureUI> {
         for i in 0 .. self.textureUI.len() {
             if self.textureUI[i].key == key {
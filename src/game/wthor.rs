@@ -0,0 +1,327 @@
+use std::fs;
+use std::io::{ self, ErrorKind };
+use std::path::Path;
+use tetra::math::Vec2;
+use super::board::cell::Color;
+use super::ggf::{ Ending, GameResult, Record };
+use super::rules::{ self, Variant };
+
+//  WTHOR ( フランスオセロ連盟の棋譜データベース )
+//  ヘッダ 16 バイト: 作成日 4、レコード数 u32 ( .wtb )、レコード数 u16 ( .jou / .trn )、対局の年 u16、盤の大きさ、種類、深さ、予備
+//  .wtb: 68 バイトずつ。大会 u16、黒 u16、白 u16、黒の石数、黒の理論値、手 60 バイト ( 10 * 行 + 列、0 は打っていない )
+//  .jou: 20 バイトずつのプレイヤー名、.trn: 26 バイトずつの大会名 ( 0 終端の Latin-1 )
+//  数値はリトルエンディアン
+
+pub const PLAYERS_FILE: &str = "WTHOR.JOU";
+pub const TOURNAMENTS_FILE: &str = "WTHOR.TRN";
+
+const HEADER_SIZE: usize = 16;
+const GAME_SIZE: usize = 68;
+const PLAYER_SIZE: usize = 20;
+const TOURNAMENT_SIZE: usize = 26;
+const MOVE_NUM: usize = 60;
+const CELL_NUM: i32 = 64;
+
+//  1局
+pub struct Game {
+    pub year: u16,
+    pub tournament: String,
+    pub black: String,
+    pub white: String,
+    pub black_score: u8,        //  終局時の黒の石数
+    pub theoretical_score: u8,  //  完全読みでの黒の石数
+    pub moves: Vec<Vec2<i32>>,  //  パスを除いた手順
+}
+
+impl Game {
+    pub fn white_score( &self ) -> u8 {
+        return ( CELL_NUM as u8 ).saturating_sub( self.black_score );
+    }
+
+    //  GGF の棋譜にする ( 盤が埋まる前に終わっていれば投了 )
+    pub fn to_record( &self ) -> Record {
+        let mut record = Record::from_moves( Variant::STANDARD, &self.moves );
+        record.place = self.tournament.clone();
+        record.date = self.year.to_string();
+        record.black = self.black.clone();
+        record.white = self.white.clone();
+
+        let finished = match rules::replay( Variant::STANDARD, &self.moves ) {
+            Some( ( board, _ ) ) => !board.there_is_enable_position( Color::BLACK ) && !board.there_is_enable_position( Color::WHITE ),
+            None => false,
+        };
+        let ending = if finished { Ending::FINISHED } else { Ending::RESIGN };
+        let score = self.black_score as i32 * 2 - CELL_NUM;
+        record.result = Some( GameResult { score: score as f32, ending } );
+        return record;
+    }
+}
+
+//  検索条件 ( None・空なら絞り込まない )
+#[derive(Default)]
+pub struct Filter {
+    pub player: Option<String>,     //  黒か白の名前の一部 ( 大文字・小文字は区別しない )
+    pub year: Option<u16>,
+    pub opening: Vec<Vec2<i32>>,    //  この手順で始まる対局
+}
+
+impl Filter {
+    fn matches( &self, game: &Game ) -> bool {
+        if let Some( player ) = &self.player {
+            let player = player.to_lowercase();
+            if !game.black.to_lowercase().contains( &player ) && !game.white.to_lowercase().contains( &player ) {
+                return false;
+            }
+        }
+
+        if let Some( year ) = self.year {
+            if game.year != year {
+                return false;
+            }
+        }
+
+        return game.moves.starts_with( &self.opening );
+    }
+}
+
+pub struct Database {
+    pub games: Vec<Game>,
+    pub skipped: usize,     //  手順がルールに合わないので読み飛ばした対局
+}
+
+impl Database {
+    //  ディレクトリの WTHOR.JOU・WTHOR.TRN と全ての .wtb を読み込む
+    pub fn load( directory: &Path ) -> io::Result<Database> {
+        let players = read_names( &directory.join( PLAYERS_FILE ), PLAYER_SIZE )?;
+        let tournaments = read_names( &directory.join( TOURNAMENTS_FILE ), TOURNAMENT_SIZE )?;
+
+        let mut paths: Vec<_> = fs::read_dir( directory )?
+            .filter_map( | entry | entry.ok().map( | e | e.path() ) )
+            .filter( | path | path.extension().map_or( false, | e | e.eq_ignore_ascii_case( "wtb" ) ) )
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            return Err( io::Error::new( ErrorKind::NotFound, "no .wtb files" ) );
+        }
+
+        let mut database = Database { games: Vec::new(), skipped: 0 };
+        for path in paths.iter() {
+            database.read_games( path, &players, &tournaments )
+                .map_err( | e | io::Error::new( e.kind(), format!( "{}: {}", path.display(), e ) ) )?;
+        }
+
+        return Ok( database );
+    }
+
+    pub fn search( &self, filter: &Filter ) -> Vec<&Game> {
+        return self.games.iter().filter( | game | filter.matches( game ) ).collect();
+    }
+
+    fn read_games( &mut self, path: &Path, players: &[String], tournaments: &[String] ) -> io::Result<()> {
+        let data = fs::read( path )?;
+        let header = read_header( &data )?;
+        //  10x10 の盤は扱わない
+        if header.board_size != 0 && header.board_size != 8 {
+            return Err( io::Error::new( ErrorKind::InvalidData, format!( "unsupported board size {}", header.board_size ) ) );
+        }
+
+        //  途中で切れたファイルは、ヘッダーの対局数に足りない分を読めなかった対局として数える
+        let records = data[HEADER_SIZE ..].chunks_exact( GAME_SIZE );
+        self.skipped += ( header.count as usize ).saturating_sub( records.len() );
+
+        let name = | names: &[String], number: u16 | names.get( number as usize ).cloned().unwrap_or( format!( "#{}", number ) );
+        for chunk in records.take( header.count as usize ) {
+            let moves = match read_moves( &chunk[8 ..] ) {
+                Some( moves ) => moves,
+                None => {
+                    self.skipped += 1;
+                    continue;
+                },
+            };
+
+            self.games.push( Game {
+                year: header.year,
+                tournament: name( tournaments, read_u16( &chunk[0 ..] ) ),
+                black: name( players, read_u16( &chunk[2 ..] ) ),
+                white: name( players, read_u16( &chunk[4 ..] ) ),
+                black_score: chunk[6],
+                theoretical_score: chunk[7],
+                moves,
+            } );
+        }
+
+        return Ok(());
+    }
+}
+
+struct Header {
+    count: u32,         //  .wtb の対局数
+    names_count: u16,   //  .jou・.trn の名前の数
+    year: u16,
+    board_size: u8,
+}
+
+fn read_header( data: &[u8] ) -> io::Result<Header> {
+    if data.len() < HEADER_SIZE {
+        return Err( io::Error::new( ErrorKind::InvalidData, "too short" ) );
+    }
+
+    return Ok( Header {
+        count: u32::from_le_bytes( [ data[4], data[5], data[6], data[7] ] ),
+        names_count: read_u16( &data[8 ..] ),
+        year: read_u16( &data[10 ..] ),
+        board_size: data[12],
+    } );
+}
+
+fn read_u16( data: &[u8] ) -> u16 {
+    return u16::from_le_bytes( [ data[0], data[1] ] );
+}
+
+//  プレイヤー名・大会名 ( 番号順 )
+fn read_names( path: &Path, size: usize ) -> io::Result<Vec<String>> {
+    let data = fs::read( path ).map_err( | e | io::Error::new( e.kind(), format!( "{}: {}", path.display(), e ) ) )?;
+    let header = read_header( &data )?;
+
+    return Ok( data[HEADER_SIZE ..].chunks_exact( size ).take( header.names_count as usize )
+        .map( | chunk | chunk.iter().take_while( | b | **b != 0 ).map( | b | *b as char ).collect::<String>().trim().to_string() )
+        .collect() );
+}
+
+//  手順 ( 11 が a1、88 が h8。ルールに合わなければ None )
+fn read_moves( data: &[u8] ) -> Option<Vec<Vec2<i32>>> {
+    let mut moves = Vec::with_capacity( MOVE_NUM );
+    for code in data.iter().take( MOVE_NUM ).take_while( | c | **c != 0 ) {
+        let position = Vec2::new( ( code % 10 ) as i32 - 1, ( code / 10 ) as i32 - 1 );
+        if !rules::Rules::is_inside( position.x, position.y ) {
+            return None;
+        }
+        moves.push( position );
+    }
+
+    rules::replay( Variant::STANDARD, &moves )?;
+    return Some( moves );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn header( count: u32, names_count: u16, year: u16, board_size: u8 ) -> Vec<u8> {
+        let mut data = vec![ 20, 24, 1, 2 ];
+        data.extend_from_slice( &count.to_le_bytes() );
+        data.extend_from_slice( &names_count.to_le_bytes() );
+        data.extend_from_slice( &year.to_le_bytes() );
+        data.extend_from_slice( &[ board_size, 0, 0, 0 ] );
+        return data;
+    }
+
+    fn names_file( names: &[&str], size: usize ) -> Vec<u8> {
+        let mut data = header( 0, names.len() as u16, 0, 0 );
+        for name in names.iter() {
+            let mut chunk = name.as_bytes().to_vec();
+            chunk.resize( size, 0 );
+            data.extend_from_slice( &chunk );
+        }
+        return data;
+    }
+
+    //  1局 68 バイト ( 手は 10 * 行 + 列 )
+    fn game_record( players: ( u16, u16 ), black_score: u8, moves: &[Vec2<i32>] ) -> Vec<u8> {
+        let mut data = vec![ 0, 0 ];
+        data.extend_from_slice( &players.0.to_le_bytes() );
+        data.extend_from_slice( &players.1.to_le_bytes() );
+        data.extend_from_slice( &[ black_score, black_score ] );
+        data.extend( moves.iter().map( | p | ( ( p.y + 1 ) * 10 + p.x + 1 ) as u8 ) );
+        data.resize( GAME_SIZE, 0 );
+        return data;
+    }
+
+    //  WTHOR.JOU・WTHOR.TRN と 1つの .wtb を書いたディレクトリ
+    fn database_directory( test: &str, players: Vec<u8>, wtb: Vec<u8> ) -> PathBuf {
+        let directory = std::env::temp_dir().join( format!( "othello_wthor_{}_{}", test, std::process::id() ) );
+        let _ = fs::remove_dir_all( &directory );
+        fs::create_dir_all( &directory ).unwrap();
+        fs::write( directory.join( PLAYERS_FILE ), players ).unwrap();
+        fs::write( directory.join( TOURNAMENTS_FILE ), names_file( &[ "Open" ], TOURNAMENT_SIZE ) ).unwrap();
+        fs::write( directory.join( "WTH_2001.wtb" ), wtb ).unwrap();
+        return directory;
+    }
+
+    fn tiger() -> Vec<Vec2<i32>> {
+        return rules::parse_transcript( "f5d6c3d3c4f4f6f3e6e7" ).unwrap();
+    }
+
+    #[test]
+    fn reads_players_tournament_and_moves() {
+        let mut wtb = header( 2, 0, 2001, 8 );
+        wtb.extend( game_record( ( 0, 1 ), 40, &tiger() ) );
+        wtb.extend( game_record( ( 1, 5 ), 20, &tiger()[.. 4] ) );
+        let directory = database_directory( "read", names_file( &[ "Alice", "Bob" ], PLAYER_SIZE ), wtb );
+
+        let database = Database::load( &directory ).unwrap();
+        let _ = fs::remove_dir_all( &directory );
+        assert_eq!( ( database.games.len(), database.skipped ), ( 2, 0 ) );
+
+        let game = &database.games[0];
+        assert_eq!( ( game.year, game.tournament.as_str(), game.black.as_str(), game.white.as_str() ), ( 2001, "Open", "Alice", "Bob" ) );
+        assert_eq!( game.moves, tiger() );
+        assert_eq!( game.white_score(), 24 );
+        //  名前の無い番号はそのまま
+        assert_eq!( database.games[1].white, "#5" );
+
+        let filter = Filter { player: Some( "ALI".to_string() ), year: Some( 2001 ), opening: tiger()[.. 2].to_vec() };
+        assert_eq!( database.search( &filter ).len(), 1 );
+
+        //  盤が埋まる前に終わった対局は投了として GGF に書く
+        let record = Record::parse( &game.to_record().to_text() ).unwrap();
+        assert_eq!( record.positions(), tiger() );
+        assert!( record.result.unwrap().ending == Ending::RESIGN );
+        assert_eq!( record.result.unwrap().score, 16.0 );
+    }
+
+    #[test]
+    fn illegal_games_are_skipped() {
+        let mut outside = game_record( ( 0, 1 ), 32, &tiger() );
+        outside[8] = 99;
+        let mut illegal = game_record( ( 0, 1 ), 32, &tiger() );
+        illegal[8] = 11;
+        let mut wtb = header( 3, 0, 2001, 0 );
+        wtb.extend( outside );
+        wtb.extend( illegal );
+        wtb.extend( game_record( ( 0, 1 ), 32, &tiger() ) );
+        let directory = database_directory( "illegal", names_file( &[ "Alice", "Bob" ], PLAYER_SIZE ), wtb );
+
+        let database = Database::load( &directory ).unwrap();
+        let _ = fs::remove_dir_all( &directory );
+        assert_eq!( ( database.games.len(), database.skipped ), ( 1, 2 ) );
+    }
+
+    #[test]
+    fn missing_games_of_a_cut_off_file_are_skipped() {
+        let mut wtb = header( 3, 0, 2001, 8 );
+        wtb.extend( game_record( ( 0, 1 ), 32, &tiger() ) );
+        wtb.extend( &game_record( ( 1, 0 ), 40, &tiger() )[.. GAME_SIZE / 2] );
+        let directory = database_directory( "cut_off", names_file( &[ "Alice", "Bob" ], PLAYER_SIZE ), wtb );
+
+        let database = Database::load( &directory ).unwrap();
+        let _ = fs::remove_dir_all( &directory );
+        assert_eq!( ( database.games.len(), database.skipped ), ( 1, 2 ) );
+    }
+
+    #[test]
+    fn broken_headers_are_errors() {
+        let players = || names_file( &[ "Alice", "Bob" ], PLAYER_SIZE );
+        let cases = vec![
+            ( "short_wtb", players(), header( 1, 0, 2001, 8 )[.. 10].to_vec() ),
+            ( "short_players", players()[.. 6].to_vec(), header( 0, 0, 2001, 8 ) ),
+            ( "board_10", players(), header( 0, 0, 2001, 10 ) ),
+        ];
+        for ( test, players, wtb ) in cases.into_iter() {
+            let directory = database_directory( test, players, wtb );
+            assert!( Database::load( &directory ).is_err(), "{}", test );
+            let _ = fs::remove_dir_all( &directory );
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use tetra::graphics::{ self, Color, Texture, DrawParams };
 use tetra::input::{ self, Key };
 use tetra::math::Vec2;
-use tetra::{ Context, ContextBuilder, State };
+use tetra::{ Context, ContextBuilder, Event, State };
 
+mod cli;
 mod game;
 
 const WINDOW_WIDTH: i32 = 950;
@@ -34,15 +35,22 @@ impl State for GameState {
     }
 
     fn draw( &mut self, ctx: &mut Context ) -> tetra::Result {
-        graphics::clear( ctx, Color::BLACK );
+        graphics::clear( ctx, self.game.get_background_color() );
         self.game.draw( ctx );
         Ok(())
     }
+
+    fn event( &mut self, ctx: &mut Context, event: Event ) -> tetra::Result {
+        if let Event::Resized { width, height } = event {
+            self.game.resize( ctx, width, height );
+        }
+        Ok(())
+    }
 }
 
 impl GameState {
-    fn new( ctx: &mut Context ) -> tetra::Result<GameState> {
-        let mut game = game::Game::new( ctx )?;
+    fn new( ctx: &mut Context, config: &game::Config, network: game::net::Network ) -> tetra::Result<GameState> {
+        let mut game = game::Game::new( ctx, config, network )?;
         game.init( ctx );
         Ok( GameState {
             game,
@@ -51,10 +59,36 @@ impl GameState {
 }
 
 fn main() -> tetra::Result {
+    let mut options = match cli::parse( std::env::args().skip( 1 ) ) {
+        Ok( options ) => options,
+        Err( e ) => {
+            eprintln!( "{}\nsee 'othello --help'", e );
+            std::process::exit( 2 );
+        },
+    };
+
+    game::ai::set_threads( options.config.threads );
+
+    //  通信対戦・観戦は相手と接続してから始める
+    let network = match cli::open_network( &mut options ) {
+        Ok( network ) => network,
+        Err( e ) => {
+            eprintln!( "{}", e );
+            std::process::exit( 1 );
+        },
+    };
+
+    //  play 以外はウィンドウを開かない
+    if options.command != cli::Command::PLAY {
+        std::process::exit( cli::run( &options, network ) );
+    }
+
+    let config = options.config;
     ContextBuilder::new( "Othello", WINDOW_WIDTH as i32, WINDOW_HEIGHT as i32 )
     .quit_on_escape( true) 
+    .resizable( true )
     .build()?
-    .run( GameState::new )
+    .run( | ctx | GameState::new( ctx, &config, network ) )
 }
 
 fn add() -> bool {